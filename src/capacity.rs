@@ -0,0 +1,266 @@
+//! QR code data capacity tables (ISO/IEC 18004 Table 7).
+//!
+//! Capacities are derived from the number of data codewords of each version and
+//! error correction level, so they match the official character tables for
+//! every mode.
+
+use qrcode::{EcLevel, Version};
+
+/// Data encoding modes whose capacity can be queried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataMode {
+    Numeric,
+    Alphanumeric,
+    Byte,
+    Kanji,
+}
+
+impl DataMode {
+    pub const ALL: [DataMode; 4] = [DataMode::Numeric, DataMode::Alphanumeric, DataMode::Byte, DataMode::Kanji];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DataMode::Numeric => "numeric",
+            DataMode::Alphanumeric => "alphanumeric",
+            DataMode::Byte => "byte",
+            DataMode::Kanji => "kanji",
+        }
+    }
+}
+
+/// Number of data codewords for versions 1-40, in the order [L, M, Q, H].
+static DATA_CODEWORDS: [[usize; 4]; 40] = [
+    [19, 16, 13, 9],
+    [34, 28, 22, 16],
+    [55, 44, 34, 26],
+    [80, 64, 48, 36],
+    [108, 86, 62, 46],
+    [136, 108, 76, 60],
+    [156, 124, 88, 66],
+    [194, 154, 110, 86],
+    [232, 182, 132, 100],
+    [274, 216, 154, 122],
+    [324, 254, 180, 140],
+    [370, 290, 206, 158],
+    [428, 334, 244, 180],
+    [461, 365, 261, 197],
+    [523, 415, 295, 223],
+    [589, 453, 325, 253],
+    [647, 507, 367, 283],
+    [721, 563, 397, 313],
+    [795, 627, 445, 341],
+    [861, 669, 485, 385],
+    [932, 714, 512, 406],
+    [1006, 782, 568, 442],
+    [1094, 860, 614, 464],
+    [1174, 914, 664, 514],
+    [1276, 1000, 718, 538],
+    [1370, 1062, 754, 596],
+    [1468, 1128, 808, 628],
+    [1531, 1193, 871, 661],
+    [1631, 1267, 911, 701],
+    [1735, 1373, 985, 745],
+    [1843, 1455, 1033, 793],
+    [1955, 1541, 1115, 845],
+    [2071, 1631, 1171, 901],
+    [2191, 1725, 1231, 961],
+    [2306, 1812, 1286, 986],
+    [2434, 1914, 1354, 1054],
+    [2566, 1992, 1426, 1096],
+    [2702, 2102, 1502, 1142],
+    [2812, 2216, 1582, 1222],
+    [2956, 2334, 1666, 1276],
+];
+
+/// Number of error correction blocks for versions 1-40 (ISO/IEC 18004
+/// Table 9), in the order [L, M, Q, H].
+static EC_BLOCKS: [[usize; 4]; 40] = [
+    [1, 1, 1, 1],
+    [1, 1, 1, 1],
+    [1, 1, 2, 2],
+    [1, 2, 2, 4],
+    [1, 2, 4, 4],
+    [2, 4, 4, 4],
+    [2, 4, 6, 5],
+    [2, 4, 6, 6],
+    [2, 5, 8, 8],
+    [4, 5, 8, 8],
+    [4, 5, 8, 11],
+    [4, 8, 10, 11],
+    [4, 9, 12, 16],
+    [4, 9, 16, 16],
+    [6, 10, 12, 18],
+    [6, 10, 17, 16],
+    [6, 11, 16, 19],
+    [6, 13, 18, 21],
+    [7, 14, 21, 25],
+    [8, 16, 20, 25],
+    [8, 17, 23, 25],
+    [9, 17, 23, 34],
+    [9, 18, 25, 30],
+    [10, 20, 27, 32],
+    [12, 21, 29, 35],
+    [12, 23, 34, 37],
+    [12, 25, 34, 40],
+    [13, 26, 35, 42],
+    [14, 28, 38, 45],
+    [15, 29, 40, 48],
+    [16, 31, 43, 51],
+    [17, 33, 45, 54],
+    [18, 35, 48, 57],
+    [19, 37, 51, 60],
+    [19, 38, 53, 63],
+    [20, 40, 56, 66],
+    [21, 43, 59, 70],
+    [22, 45, 62, 74],
+    [24, 47, 65, 77],
+    [25, 49, 68, 81],
+];
+
+fn version_index(version: Version) -> usize {
+    match version {
+        Version::Normal(v @ 1..=40) => (v - 1) as usize,
+        _ => panic!("unsupported QR version {:?}", version),
+    }
+}
+
+/// Number of data codewords available in `version` at `ec_level`.
+///
+/// # Panics
+///
+/// Panics for Micro QR versions or versions outside 1-40.
+pub fn data_codewords(version: Version, ec_level: EcLevel) -> usize {
+    DATA_CODEWORDS[version_index(version)][ec_level as usize]
+}
+
+/// Number of data bits available in `version` at `ec_level`.
+pub fn data_bits(version: Version, ec_level: EcLevel) -> usize {
+    data_codewords(version, ec_level) * 8
+}
+
+/// Total number of codewords (data and error correction) in `version`.
+pub fn total_codewords(version: Version) -> usize {
+    let v = version_index(version) + 1;
+    // Modules left after finder, timing, alignment, format and version patterns
+    let mut modules = (16 * v + 128) * v + 64;
+    if v >= 2 {
+        let alignment = v / 7 + 2;
+        modules -= (25 * alignment - 10) * alignment - 55;
+        if v >= 7 {
+            modules -= 36;
+        }
+    }
+    modules / 8
+}
+
+/// Number of error correction blocks in `version` at `ec_level`.
+pub fn ec_blocks(version: Version, ec_level: EcLevel) -> usize {
+    EC_BLOCKS[version_index(version)][ec_level as usize]
+}
+
+/// Number of error correction codewords in each block of `version` at `ec_level`.
+pub fn ec_codewords_per_block(version: Version, ec_level: EcLevel) -> usize {
+    (total_codewords(version) - data_codewords(version, ec_level)) / ec_blocks(version, ec_level)
+}
+
+/// Width of the character count indicator for `mode` in `version`.
+pub fn char_count_bits(version: Version, mode: DataMode) -> usize {
+    let group = match version_index(version) {
+        0..=8 => 0,
+        9..=25 => 1,
+        _ => 2,
+    };
+    let bits = match mode {
+        DataMode::Numeric => [10, 12, 14],
+        DataMode::Alphanumeric => [9, 11, 13],
+        DataMode::Byte => [8, 16, 16],
+        DataMode::Kanji => [8, 10, 12],
+    };
+    bits[group]
+}
+
+/// Number of bits needed to encode `chars` characters of `mode` (without
+/// the mode and character count indicators).
+pub fn payload_bits(mode: DataMode, chars: usize) -> usize {
+    match mode {
+        DataMode::Numeric => 10 * (chars / 3) + [0, 4, 7][chars % 3],
+        DataMode::Alphanumeric => 11 * (chars / 2) + 6 * (chars % 2),
+        DataMode::Byte => 8 * chars,
+        DataMode::Kanji => 13 * chars,
+    }
+}
+
+/// Maximum number of characters of `mode` that fit in a single segment of
+/// `version` at `ec_level`.
+pub fn capacity(version: Version, ec_level: EcLevel, mode: DataMode) -> usize {
+    let available = data_bits(version, ec_level);
+    let overhead = 4 + char_count_bits(version, mode);
+    if available < overhead {
+        return 0;
+    }
+    let budget = available - overhead;
+    let max_count = (1usize << char_count_bits(version, mode)) - 1;
+
+    // Characters per bit differ by mode; estimate then step back to the exact fit
+    let mut chars = match mode {
+        DataMode::Numeric => budget * 3 / 10 + 1,
+        DataMode::Alphanumeric => budget * 2 / 11 + 1,
+        DataMode::Byte => budget / 8,
+        DataMode::Kanji => budget / 13,
+    };
+    while chars > 0 && payload_bits(mode, chars) > budget {
+        chars -= 1;
+    }
+    chars.min(max_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qrcode::bits::Bits;
+    use qrcode::ec::construct_codewords;
+
+    const LEVELS: [EcLevel; 4] = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+
+    #[test]
+    fn tables_match_qrcode() {
+        for v in 1..=40 {
+            let version = Version::Normal(v);
+            for ec_level in LEVELS {
+                let data = data_codewords(version, ec_level);
+                assert_eq!(data * 8, Bits::new(version).max_len(ec_level).unwrap(), "{:?}-{:?}", version, ec_level);
+
+                // Fill each block with its index: the interleaved data shows
+                // the block count and sizes, the error correction its length
+                let blocks = ec_blocks(version, ec_level);
+                let (short, long) = (data / blocks, data % blocks);
+                let sizes: Vec<usize> = (0..blocks).map(|b| short + usize::from(b >= blocks - long)).collect();
+                let raw: Vec<u8> = sizes.iter().enumerate().flat_map(|(b, &size)| std::iter::repeat_n(b as u8, size)).collect();
+                let interleaved: Vec<u8> =
+                    (0..short + 1).flat_map(|i| sizes.iter().enumerate().filter(move |&(_, &size)| i < size).map(|(b, _)| b as u8)).collect();
+                let (data_out, ec_out) = construct_codewords(&raw, version, ec_level).unwrap();
+                assert_eq!(data_out, interleaved, "{:?}-{:?} blocks", version, ec_level);
+                assert_eq!(ec_out.len(), blocks * ec_codewords_per_block(version, ec_level), "{:?}-{:?}", version, ec_level);
+                assert_eq!(data_out.len() + ec_out.len(), total_codewords(version), "{:?}-{:?}", version, ec_level);
+            }
+        }
+    }
+
+    #[test]
+    fn capacity_matches_iso_tables() {
+        let cases = [
+            (1, EcLevel::L, DataMode::Numeric, 41),
+            (1, EcLevel::H, DataMode::Byte, 7),
+            (10, EcLevel::M, DataMode::Alphanumeric, 311),
+            (10, EcLevel::Q, DataMode::Kanji, 93),
+            (40, EcLevel::L, DataMode::Numeric, 7089),
+            (40, EcLevel::L, DataMode::Alphanumeric, 4296),
+            (40, EcLevel::L, DataMode::Byte, 2953),
+            (40, EcLevel::H, DataMode::Byte, 1273),
+            (40, EcLevel::L, DataMode::Kanji, 1817),
+        ];
+        for (v, ec_level, mode, expected) in cases {
+            assert_eq!(capacity(Version::Normal(v), ec_level, mode), expected, "{}-{:?} {}", v, ec_level, mode.name());
+        }
+    }
+}
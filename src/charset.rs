@@ -0,0 +1,189 @@
+//! Character sets and ECI designators for non-ASCII text.
+//!
+//! Without an ECI designator readers have to guess how byte-mode data is
+//! encoded (ISO-8859-1 per the standard, UTF-8 or Shift_JIS in practice), so
+//! Japanese or Arabic text is often mis-decoded.
+
+use crate::segment::{self, Payload};
+use encoding_rs::{Encoding, SHIFT_JIS};
+
+/// ECI designator for UTF-8.
+pub const ECI_UTF8: u32 = 26;
+/// ECI designator for Shift_JIS.
+pub const ECI_SHIFT_JIS: u32 = 20;
+
+/// Character sets that can be announced with an ECI designator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Charset {
+    Utf8,
+    /// ISO-8859 part number (1-11, 13-16).
+    Iso8859(u8),
+    ShiftJis,
+}
+
+impl Charset {
+    /// Parses names such as `utf-8`, `iso-8859-6`, `latin1` or `shift_jis`.
+    pub fn parse(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let normalized = name.trim().to_lowercase().replace('_', "-");
+        let charset = match normalized.as_str() {
+            "utf-8" | "utf8" => Charset::Utf8,
+            "shift-jis" | "sjis" | "shiftjis" => Charset::ShiftJis,
+            "latin1" | "latin-1" => Charset::Iso8859(1),
+            other => {
+                let part = other
+                    .strip_prefix("iso-8859-")
+                    .or_else(|| other.strip_prefix("iso8859-"))
+                    .and_then(|p| p.parse::<u8>().ok());
+                match part {
+                    Some(p @ (1..=11 | 13..=16)) => Charset::Iso8859(p),
+                    _ => {
+                        return Err(format!(
+                            "Unsupported charset: {}. Use utf-8, shift_jis or iso-8859-N (1-11, 13-16)",
+                            name
+                        )
+                        .into())
+                    }
+                }
+            }
+        };
+        Ok(charset)
+    }
+
+    /// Display name of the character set.
+    pub fn name(self) -> String {
+        match self {
+            Charset::Utf8 => "UTF-8".to_string(),
+            Charset::Iso8859(p) => format!("ISO-8859-{}", p),
+            Charset::ShiftJis => "Shift_JIS".to_string(),
+        }
+    }
+
+    /// Character set announced by an ECI designator, if supported.
+    pub fn from_eci(eci: u32) -> Option<Self> {
+        match eci {
+            ECI_UTF8 => Some(Charset::Utf8),
+            ECI_SHIFT_JIS => Some(Charset::ShiftJis),
+            // ECI 1 is the obsolete alias of ISO-8859-1
+            1 => Some(Charset::Iso8859(1)),
+            3..=13 | 15..=18 => Some(Charset::Iso8859((eci - 2) as u8)),
+            _ => None,
+        }
+    }
+
+    /// ECI designator assigned to the character set.
+    pub fn eci_designator(self) -> u32 {
+        match self {
+            Charset::Utf8 => ECI_UTF8,
+            // ECI 3-13 are ISO-8859-1 to -11, 15-18 are ISO-8859-13 to -16
+            Charset::Iso8859(p) => u32::from(p) + 2,
+            Charset::ShiftJis => ECI_SHIFT_JIS,
+        }
+    }
+
+    /// Encodes `text` in this character set with the matching ECI designator.
+    ///
+    /// Shift_JIS text uses Kanji mode where possible and only carries its ECI
+    /// designator in versions where non-ASCII characters still end up in byte
+    /// mode, see [`Payload::encoding`].
+    pub fn encode(self, text: &str) -> Result<Payload, Box<dyn std::error::Error>> {
+        let Some(data) = self.to_bytes(text) else {
+            let offending: String = text.chars().filter(|c| self.to_bytes(&c.to_string()).is_none()).take(5).collect();
+            return Err(format!("Text contains characters not representable in {}: {}", self.name(), offending).into());
+        };
+
+        let payload = match self {
+            Charset::Utf8 | Charset::Iso8859(_) => Payload { data, eci: Some(self.eci_designator()), kanji: false },
+            Charset::ShiftJis => Payload { data, eci: Some(ECI_SHIFT_JIS), kanji: true },
+        };
+        Ok(payload)
+    }
+
+    /// Decodes bytes of this character set, or `None` if they are malformed.
+    pub fn decode(self, data: &[u8]) -> Option<String> {
+        let encoding = match self {
+            Charset::Utf8 => return String::from_utf8(data.to_vec()).ok(),
+            // Latin-1 maps every byte to the code point of the same value
+            Charset::Iso8859(1) => return Some(data.iter().map(|&b| char::from(b)).collect()),
+            Charset::Iso8859(p) => Encoding::for_label(format!("iso-8859-{}", p).as_bytes())?,
+            Charset::ShiftJis => SHIFT_JIS,
+        };
+        let (text, had_errors) = encoding.decode_without_bom_handling(data);
+        (!had_errors).then(|| text.into_owned())
+    }
+
+    /// Converts `text` to bytes, or `None` if some character is not representable.
+    fn to_bytes(self, text: &str) -> Option<Vec<u8>> {
+        let encoding = match self {
+            Charset::Utf8 => return Some(text.as_bytes().to_vec()),
+            // WHATWG maps ISO-8859-1/9/11 to their Windows supersets, which
+            // only differ in 0x80-0x9F; those bytes are rejected below.
+            Charset::Iso8859(p) => Encoding::for_label(format!("iso-8859-{}", p).as_bytes())?,
+            Charset::ShiftJis => SHIFT_JIS,
+        };
+        let (data, _, had_errors) = encoding.encode(text);
+        let c1_controls = matches!(self, Charset::Iso8859(_)) && data.iter().any(|b| (0x80..=0x9f).contains(b));
+        (!had_errors && !c1_controls).then(|| data.into_owned())
+    }
+}
+
+/// Whether byte-mode data of `text` needs an ECI designator to be decoded
+/// reliably, i.e. it contains non-ASCII characters.
+pub fn needs_eci(text: &str) -> bool {
+    !text.is_ascii()
+}
+
+/// Whether `text` can be written in Shift_JIS and contains characters that
+/// Kanji mode would encode more compactly than UTF-8 byte mode.
+pub fn suits_kanji_mode(text: &str) -> bool {
+    match Charset::ShiftJis.encode(text) {
+        Ok(payload) => segment::shift_jis_units(&payload.data).iter().any(|unit| segment::is_kanji(unit)),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{self, Decoded};
+    use crate::symbol::{QrSymbol, VersionChoice};
+    use qrcode::{EcLevel, Version};
+
+    /// Encodes `payload` in version `v` and decodes its module grid.
+    fn read_back(payload: &Payload, v: i16) -> Decoded {
+        let symbol = QrSymbol::encode_payload(payload, EcLevel::M, VersionChoice::Exact(v), None).unwrap();
+        let width = symbol.width();
+        let modules: Vec<_> = (0..width * width).map(|i| symbol[(i % width, i / width)]).collect();
+        decode::decode_grid(&modules, width).unwrap()
+    }
+
+    #[test]
+    fn shift_jis_text_reads_back_in_every_version() {
+        for text in ["https://example.com/日本", "日本語のテキスト 123", "ｶﾀｶﾅ と 漢字", "plain ascii"] {
+            let payload = Charset::ShiftJis.encode(text).unwrap();
+            for v in [2, 9, 10, 26, 27, 40] {
+                let decoded = read_back(&payload, v);
+                assert_eq!(decoded.text().as_deref(), Some(text), "{} in version {}", text, v);
+                assert_eq!(decoded.eci, payload.eci(Version::Normal(v)));
+                let non_ascii_bytes = segment::needs_eci(&payload.segments(Version::Normal(v)));
+                assert_eq!(decoded.eci.is_some(), non_ascii_bytes, "{} in version {}", text, v);
+            }
+        }
+    }
+
+    #[test]
+    fn shift_jis_designator_only_when_needed() {
+        let version = Version::Normal(5);
+        assert_eq!(Charset::ShiftJis.encode("日本語").unwrap().eci(version), None);
+        // Half-width katakana has no Kanji mode form
+        assert_eq!(Charset::ShiftJis.encode("ｶﾀｶﾅ").unwrap().eci(version), Some(ECI_SHIFT_JIS));
+    }
+
+    #[test]
+    fn utf8_and_latin_text_read_back() {
+        for (charset, text) in [(Charset::Utf8, "Grüße, 日本"), (Charset::Iso8859(1), "Café crème"), (Charset::Iso8859(7), "Καλημέρα")] {
+            let decoded = read_back(&charset.encode(text).unwrap(), 5);
+            assert_eq!(decoded.text().as_deref(), Some(text));
+            assert_eq!(decoded.eci, Some(charset.eci_designator()));
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Foreground/background contrast and polarity checks.
+//!
+//! Contrast is the WCAG luminance ratio (L1 + 0.05) / (L2 + 0.05), from 1:1
+//! for identical colors to 21:1 for black on white.
+
+use image::Rgb;
+
+/// Below this ratio scanners cannot separate dark from light modules.
+pub const MIN_CONTRAST_RATIO: f64 = 3.0;
+
+/// Below this ratio codes scan poorly in bad light or after printing.
+pub const RECOMMENDED_CONTRAST_RATIO: f64 = 7.0;
+
+/// Relative luminance of an sRGB color, from 0 (black) to 1 (white).
+pub fn relative_luminance(color: Rgb<u8>) -> f64 {
+    let linear = |channel: u8| {
+        let c = f64::from(channel) / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let [r, g, b] = color.0;
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// Contrast between the module color and the background color.
+#[derive(Clone, Copy, Debug)]
+pub struct ContrastCheck {
+    /// Luminance ratio, at least 1.
+    pub ratio: f64,
+    /// Whether the modules are lighter than the background.
+    pub inverted: bool,
+}
+
+impl ContrastCheck {
+    pub fn new(foreground: Rgb<u8>, background: Rgb<u8>) -> Self {
+        let fg = relative_luminance(foreground);
+        let bg = relative_luminance(background);
+        Self { ratio: (fg.max(bg) + 0.05) / (fg.min(bg) + 0.05), inverted: fg > bg }
+    }
+
+    /// The weakest contrast of several foreground colors, such as the stops
+    /// of a gradient, against `background`.
+    pub fn weakest(foregrounds: &[Rgb<u8>], background: Rgb<u8>) -> Self {
+        foregrounds
+            .iter()
+            .map(|&foreground| Self::new(foreground, background))
+            .min_by(|a, b| a.ratio.total_cmp(&b.ratio))
+            .unwrap_or_else(|| Self::new(background, background))
+    }
+
+    /// Returns warnings for a usable color pair, or an error when the code
+    /// would be unreadable or is inverted without `allow_inverted`.
+    pub fn validate(&self, allow_inverted: bool) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        if self.ratio < MIN_CONTRAST_RATIO {
+            return Err(format!(
+                "Contrast {:.1}:1 between QR color and background is too low; at least {:.0}:1 is required",
+                self.ratio, MIN_CONTRAST_RATIO
+            )
+            .into());
+        }
+        let mut warnings = Vec::new();
+        if self.inverted {
+            if !allow_inverted {
+                return Err("QR color is lighter than the background; inverted codes fail on many scanners".into());
+            }
+            warnings.push("Inverted code (light on dark): many scanners cannot read it".to_string());
+        }
+        if self.ratio < RECOMMENDED_CONTRAST_RATIO {
+            warnings.push(format!(
+                "Low contrast {:.1}:1; {:.0}:1 or more is recommended for reliable scanning",
+                self.ratio, RECOMMENDED_CONTRAST_RATIO
+            ));
+        }
+        Ok(warnings)
+    }
+}
@@ -0,0 +1,1474 @@
+//! QR code reader.
+//!
+//! The pipeline runs the encoder in reverse: binarize the image, locate the
+//! three finder patterns (and the bottom-right alignment pattern to correct
+//! perspective), sample the module grid, read the format and version
+//! information, unmask, de-interleave the blocks, correct errors with
+//! Reed-Solomon and finally parse the segments.
+
+use crate::capacity::{self, char_count_bits, DataMode};
+use crate::charset::Charset;
+use crate::reed_solomon;
+use crate::segment::Segment;
+use image::{DynamicImage, GrayImage};
+use qrcode::canvas::{Canvas, Module};
+use qrcode::{Color, EcLevel, Version};
+use std::error::Error;
+
+/// Image coordinates in pixels, or symbol coordinates in modules.
+pub type Point = (f64, f64);
+
+/// Result of decoding a symbol.
+#[derive(Clone, Debug)]
+pub struct Decoded {
+    /// Payload bytes: the data of all segments, concatenated.
+    pub data: Vec<u8>,
+    pub version: Version,
+    pub ec_level: EcLevel,
+    pub mask: u8,
+    /// First ECI designator found in the bit stream, if any.
+    pub eci: Option<u32>,
+    pub segments: Vec<Segment>,
+    /// Codewords corrected in each error correction block.
+    pub corrected: Vec<usize>,
+    /// Codewords each block can correct.
+    pub correctable: usize,
+}
+
+impl Decoded {
+    /// Character set of the payload: the ECI designator if present, Shift_JIS
+    /// when Kanji segments are used, otherwise `None`.
+    pub fn charset(&self) -> Option<Charset> {
+        match self.eci {
+            Some(eci) => Charset::from_eci(eci),
+            None if self.segments.iter().any(|s| s.mode == DataMode::Kanji) => Some(Charset::ShiftJis),
+            None => None,
+        }
+    }
+
+    /// Payload as text. Without ECI designator UTF-8 is tried first, then
+    /// ISO-8859-1 as the standard specifies. `None` if the payload is not
+    /// valid in its announced character set.
+    pub fn text(&self) -> Option<String> {
+        match (self.eci, self.charset()) {
+            (_, Some(charset)) => charset.decode(&self.data),
+            (Some(_), None) => None,
+            (None, None) => Charset::Utf8.decode(&self.data).or_else(|| Charset::Iso8859(1).decode(&self.data)),
+        }
+    }
+}
+
+/// Position and geometry of a symbol found in an image.
+#[derive(Clone, Copy, Debug)]
+pub struct Location {
+    pub top_left: (f64, f64),
+    pub top_right: (f64, f64),
+    pub bottom_left: (f64, f64),
+    /// Bottom-right alignment pattern used for perspective correction.
+    pub alignment: Option<(f64, f64)>,
+    /// Average module size in pixels, measured on the finder patterns.
+    pub module_size: f64,
+    /// Symbol width in modules.
+    pub dimension: usize,
+    /// Maps module coordinates to image coordinates.
+    pub transform: Perspective,
+}
+
+/// A symbol found in an image, with its sampled module grid.
+#[derive(Clone, Debug)]
+pub struct Detection {
+    pub location: Location,
+    /// Sampled modules, row by row.
+    pub modules: Vec<Color>,
+    pub decoded: Decoded,
+}
+
+/// Decodes every QR code found in `image`, in reading order.
+pub fn decode_image(image: &DynamicImage) -> Result<Vec<Decoded>, Box<dyn Error>> {
+    detect(&to_luma(image)).map(|detections| detections.into_iter().map(|d| d.decoded).collect())
+}
+
+/// Decodes `image` and checks that one of its symbols carries exactly
+/// `expected`, byte for byte.
+pub fn verify(image: &DynamicImage, expected: &[u8]) -> Result<Decoded, Box<dyn Error>> {
+    let mut decoded = decode_image(image).map_err(|e| format!("Verification failed: rendered image is not readable ({})", e))?;
+    if let Some(index) = decoded.iter().position(|d| d.data == expected) {
+        return Ok(decoded.swap_remove(index));
+    }
+    let first = &decoded[0];
+    let detail = match first.data.iter().zip(expected).position(|(a, b)| a != b) {
+        Some(i) => format!("first difference at byte {} (expected 0x{:02x}, read 0x{:02x})", i, expected[i], first.data[i]),
+        None => format!("expected {} bytes, read {}", expected.len(), first.data.len()),
+    };
+    Err(format!("Verification failed: decoded payload differs from the input, {}", detail).into())
+}
+
+/// Converts an image to luminance, compositing transparent pixels over white.
+pub fn to_luma(image: &DynamicImage) -> GrayImage {
+    let rgba = image.to_rgba8();
+    GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let luma = (299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b)) / 1000;
+        let alpha = u32::from(a);
+        image::Luma([((luma * alpha + 255 * (255 - alpha)) / 255) as u8])
+    })
+}
+
+/// Gaussian blur radii tried, in pixels, when the raw image does not decode.
+const SMOOTHING_SIGMAS: [f32; 3] = [1.0, 2.0, 4.0];
+
+/// Finder triples that may fail in a row before the search for further
+/// symbols stops.
+const MAX_FAILED_TRIPLES: usize = 3;
+
+/// Locates and decodes every symbol in a luminance image, in reading order.
+/// On success the list holds at least one detection.
+pub fn detect(luma: &GrayImage) -> Result<Vec<Detection>, Box<dyn Error>> {
+    // Version 1 with single-pixel modules is the smallest readable symbol
+    if luma.width() < 21 || luma.height() < 21 {
+        return Err(format!("Image of {}x{} pixels is too small to hold a QR code", luma.width(), luma.height()).into());
+    }
+    // Both binarizations run: each may resolve symbols the other misses
+    let mut first_error: Option<Box<dyn Error>> = None;
+    let mut detections: Vec<Detection> = Vec::new();
+    for binarized in [Binarized::global(luma), Binarized::adaptive(luma)] {
+        match detect_binarized(&binarized) {
+            Ok(found) => merge_detections(&mut detections, found),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    if detections.is_empty() {
+        detections = detect_fallback(luma);
+    }
+    if detections.is_empty() {
+        return Err(first_error.unwrap_or_else(|| "No QR code found".into()));
+    }
+
+    // Rows of symbols top to bottom, each row left to right
+    let band = detections.iter().map(|d| d.location.module_size * d.location.dimension as f64).fold(1.0, f64::max);
+    detections.sort_by_key(|d| {
+        let (x, y) = d.location.centre();
+        ((y / band).floor() as i64, x.round() as i64)
+    });
+    Ok(detections)
+}
+
+/// Retries a degraded image: smoothed against noise, then inverted for
+/// light on dark symbols. Stops at the first pass that finds anything.
+fn detect_fallback(luma: &GrayImage) -> Vec<Detection> {
+    // Noisy images: smooth and retry, as long as modules can survive the blur
+    let min_side = f64::from(luma.width().min(luma.height()));
+    for sigma in SMOOTHING_SIGMAS.into_iter().filter(|&s| f64::from(s) * 150.0 <= min_side) {
+        let smoothed = image::imageops::blur(luma, sigma);
+        for binarized in [Binarized::global(&smoothed), Binarized::adaptive(&smoothed)] {
+            if let Ok(detections) = detect_binarized(&binarized) {
+                return detections;
+            }
+        }
+    }
+    // Inverted (light on dark) symbols
+    let mut inverted = luma.clone();
+    image::imageops::invert(&mut inverted);
+    for binarized in [Binarized::global(&inverted), Binarized::adaptive(&inverted)] {
+        if let Ok(detections) = detect_binarized(&binarized) {
+            return detections;
+        }
+    }
+    Vec::new()
+}
+
+/// Adds the detections not already in `into`, comparing symbol positions.
+fn merge_detections(into: &mut Vec<Detection>, found: Vec<Detection>) {
+    for detection in found {
+        if !into.iter().any(|d| d.location.covers(detection.location.centre())) {
+            into.push(detection);
+        }
+    }
+}
+
+fn detect_binarized(image: &Binarized) -> Result<Vec<Detection>, Box<dyn Error>> {
+    let candidates = find_finder_patterns(image);
+    let triples = finder_triples(&candidates);
+    if triples.is_empty() {
+        return Err("No QR code found: could not locate three finder patterns".into());
+    }
+
+    // The first candidates are the most plausible, so their error is reported
+    let mut first_error: Option<Box<dyn Error>> = None;
+    let mut detections: Vec<Detection> = Vec::new();
+    let mut failed = 0;
+    for triple in triples {
+        if failed == MAX_FAILED_TRIPLES {
+            break;
+        }
+        // Finders of a decoded symbol, or look-alikes in its data area
+        if triple.iter().any(|f| detections.iter().any(|d| d.location.covers((f.x, f.y)))) {
+            continue;
+        }
+        match detect_triple(image, triple) {
+            Ok(detection) => {
+                detections.push(detection);
+                failed = 0;
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+                failed += 1;
+            }
+        }
+    }
+    if detections.is_empty() {
+        return Err(first_error.unwrap_or_else(|| "No QR code found".into()));
+    }
+    Ok(detections)
+}
+
+/// Samples and decodes the symbol spanned by one finder triple, trying each
+/// plausible dimension.
+fn detect_triple(image: &Binarized, [top_left, top_right, bottom_left]: [FinderCandidate; 3]) -> Result<Detection, Box<dyn Error>> {
+    let module_top = module_size_along(image, top_left, top_right);
+    let module_left = module_size_along(image, top_left, bottom_left);
+    let module_size = (module_top + module_left) / 2.0;
+    let mut dimensions = estimate_dimensions(top_left, top_right, bottom_left, module_top, module_left);
+    dimensions.extend(timing_dimensions(image, top_left, top_right, bottom_left, module_top, module_left));
+    let mut first_error: Option<Box<dyn Error>> = None;
+    let mut tried = Vec::new();
+    while let Some(dimension) = dimensions.pop() {
+        if tried.contains(&dimension) {
+            continue;
+        }
+        tried.push(dimension);
+
+        let location = Location::new(image, [top_left, top_right, bottom_left], module_size, dimension);
+        let modules = sample_grid(image, &location);
+        match decode_grid(&modules, dimension) {
+            Ok(decoded) => return Ok(Detection { location, modules, decoded }),
+            Err(e) => {
+                // Large symbols carry their version; retry with it
+                if let Some(version) = read_version_info(&modules, dimension) {
+                    dimensions.push(version as usize * 4 + 17);
+                }
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(first_error.unwrap_or_else(|| "No QR code found".into()))
+}
+
+//------------------------------------------------------------------------------
+// Binarization
+
+/// Luminance image with a dark/light threshold per block of pixels.
+pub struct Binarized<'a> {
+    pub luma: &'a GrayImage,
+    block: u32,
+    blocks_x: u32,
+    thresholds: Vec<u8>,
+}
+
+impl<'a> Binarized<'a> {
+    /// Single threshold for the whole image, chosen with Otsu's method.
+    pub fn global(luma: &'a GrayImage) -> Self {
+        let mut histogram = [0usize; 256];
+        for pixel in luma.pixels() {
+            histogram[pixel.0[0] as usize] += 1;
+        }
+        let threshold = otsu_threshold(&histogram);
+        Self { luma, block: luma.width().max(luma.height()).max(1), blocks_x: 1, thresholds: vec![threshold] }
+    }
+
+    /// Local thresholds averaged over neighbouring blocks, for uneven lighting.
+    pub fn adaptive(luma: &'a GrayImage) -> Self {
+        let (width, height) = luma.dimensions();
+        let block = (width.min(height) / 40).max(8);
+        let blocks_x = width.div_ceil(block).max(1);
+        let blocks_y = height.div_ceil(block).max(1);
+
+        // Per-block black point; flat blocks inherit from their neighbours
+        let mut black_points = vec![0u8; (blocks_x * blocks_y) as usize];
+        for by in 0..blocks_y {
+            for bx in 0..blocks_x {
+                let (mut min, mut max, mut sum, mut count) = (255u32, 0u32, 0u32, 0u32);
+                for y in by * block..((by + 1) * block).min(height) {
+                    for x in bx * block..((bx + 1) * block).min(width) {
+                        let value = u32::from(luma.get_pixel(x, y).0[0]);
+                        min = min.min(value);
+                        max = max.max(value);
+                        sum += value;
+                        count += 1;
+                    }
+                }
+                let mut average = sum / count.max(1);
+                if max - min <= 24 {
+                    average = min / 2;
+                    if bx > 0 && by > 0 {
+                        let index = |x: u32, y: u32| (y * blocks_x + x) as usize;
+                        let neighbours = (u32::from(black_points[index(bx, by - 1)])
+                            + 2 * u32::from(black_points[index(bx - 1, by)])
+                            + u32::from(black_points[index(bx - 1, by - 1)]))
+                            / 4;
+                        if min < neighbours {
+                            average = neighbours;
+                        }
+                    }
+                }
+                black_points[(by * blocks_x + bx) as usize] = average as u8;
+            }
+        }
+
+        let mut thresholds = vec![0u8; black_points.len()];
+        for by in 0..blocks_y {
+            for bx in 0..blocks_x {
+                let (mut sum, mut count) = (0u32, 0u32);
+                for ny in by.saturating_sub(2)..(by + 3).min(blocks_y) {
+                    for nx in bx.saturating_sub(2)..(bx + 3).min(blocks_x) {
+                        sum += u32::from(black_points[(ny * blocks_x + nx) as usize]);
+                        count += 1;
+                    }
+                }
+                thresholds[(by * blocks_x + bx) as usize] = (sum / count) as u8;
+            }
+        }
+        Self { luma, block, blocks_x, thresholds }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.luma.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.luma.height()
+    }
+
+    /// Threshold at a pixel; luminance at or below it counts as dark.
+    pub fn threshold(&self, x: u32, y: u32) -> u8 {
+        self.thresholds[((y / self.block) * self.blocks_x + x / self.block) as usize]
+    }
+
+    /// Whether the pixel is dark; pixels outside the image are light.
+    pub fn is_dark(&self, x: i64, y: i64) -> bool {
+        if x < 0 || y < 0 || x >= i64::from(self.width()) || y >= i64::from(self.height()) {
+            return false;
+        }
+        let (x, y) = (x as u32, y as u32);
+        self.luma.get_pixel(x, y).0[0] <= self.threshold(x, y)
+    }
+
+    /// Classifies the module centred at an image position by the average
+    /// luminance of a small square around it.
+    pub fn sample(&self, x: f64, y: f64, radius: i64) -> Color {
+        let (cx, cy) = (x.floor() as i64, y.floor() as i64);
+        let (mut sum, mut count) = (0i64, 0i64);
+        for py in cy - radius..=cy + radius {
+            for px in cx - radius..=cx + radius {
+                if px >= 0 && py >= 0 && px < i64::from(self.width()) && py < i64::from(self.height()) {
+                    sum += i64::from(self.luma.get_pixel(px as u32, py as u32).0[0]);
+                    count += 1;
+                }
+            }
+        }
+        if count == 0 {
+            return Color::Light;
+        }
+        let tx = cx.clamp(0, i64::from(self.width()) - 1) as u32;
+        let ty = cy.clamp(0, i64::from(self.height()) - 1) as u32;
+        if sum / count <= i64::from(self.threshold(tx, ty)) {
+            Color::Dark
+        } else {
+            Color::Light
+        }
+    }
+}
+
+/// Threshold maximizing the between-class variance of a histogram.
+fn otsu_threshold(histogram: &[usize; 256]) -> u8 {
+    let total: usize = histogram.iter().sum();
+    let sum_all: f64 = histogram.iter().enumerate().map(|(i, &c)| i as f64 * c as f64).sum();
+    let (mut weight_dark, mut sum_dark) = (0usize, 0f64);
+    let (mut best, mut best_variance) = (127u8, -1f64);
+    for (level, &count) in histogram.iter().enumerate() {
+        weight_dark += count;
+        if weight_dark == 0 {
+            continue;
+        }
+        let weight_light = total - weight_dark;
+        if weight_light == 0 {
+            break;
+        }
+        sum_dark += level as f64 * count as f64;
+        let mean_dark = sum_dark / weight_dark as f64;
+        let mean_light = (sum_all - sum_dark) / weight_light as f64;
+        let variance = weight_dark as f64 * weight_light as f64 * (mean_dark - mean_light).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best = level as u8;
+        }
+    }
+    best
+}
+
+//------------------------------------------------------------------------------
+// Finder patterns
+
+#[derive(Clone, Copy, Debug)]
+struct FinderCandidate {
+    x: f64,
+    y: f64,
+    module: f64,
+    count: usize,
+}
+
+/// Whether run lengths follow the 1:1:3:1:1 finder pattern ratio.
+fn is_finder_ratio(counts: &[usize; 5]) -> bool {
+    let total: usize = counts.iter().sum();
+    if total < 7 || counts.contains(&0) {
+        return false;
+    }
+    let module = total as f64 / 7.0;
+    let variance = module / 2.0;
+    let expected = [1.0, 1.0, 3.0, 1.0, 1.0];
+    counts
+        .iter()
+        .zip(expected)
+        .all(|(&count, factor)| (count as f64 - module * factor).abs() < variance * factor)
+}
+
+/// Run lengths along a line through (x, y) in direction (dx, dy), together
+/// with the offset of the centre run's middle from (x, y), in steps.
+fn cross_check(image: &Binarized, x: i64, y: i64, dx: i64, dy: i64, max_count: usize) -> Option<([usize; 5], f64)> {
+    let mut counts = [0usize; 5];
+    let dark = |i: i64| image.is_dark(x + i * dx, y + i * dy);
+
+    let mut i = 0;
+    while dark(i) {
+        counts[2] += 1;
+        i -= 1;
+    }
+    if counts[2] == 0 {
+        return None;
+    }
+    let back_centre = counts[2];
+    while !dark(i) && counts[1] <= max_count {
+        counts[1] += 1;
+        i -= 1;
+    }
+    while dark(i) && counts[0] <= max_count {
+        counts[0] += 1;
+        i -= 1;
+    }
+
+    let mut i = 1;
+    while dark(i) {
+        counts[2] += 1;
+        i += 1;
+    }
+    let forward_centre = counts[2] - back_centre;
+    while !dark(i) && counts[3] <= max_count {
+        counts[3] += 1;
+        i += 1;
+    }
+    while dark(i) && counts[4] <= max_count {
+        counts[4] += 1;
+        i += 1;
+    }
+
+    if !is_finder_ratio(&counts) {
+        return None;
+    }
+    let offset = (forward_centre as f64 - (back_centre as f64 - 1.0)) / 2.0;
+    Some((counts, offset))
+}
+
+/// Confirms a horizontal finder hit and returns its centre and module size.
+fn confirm_finder(image: &Binarized, counts: &[usize; 5], end: usize, y: usize) -> Option<(f64, f64, f64)> {
+    let total: usize = counts.iter().sum();
+    let centre_x = end as f64 - counts[4] as f64 - counts[3] as f64 - counts[2] as f64 / 2.0;
+    let column = centre_x.floor() as i64;
+
+    let (vertical, offset) = cross_check(image, column, y as i64, 0, 1, counts[2])?;
+    let vertical_total: usize = vertical.iter().sum();
+    // Perspective may squeeze the pattern, but not beyond half its width
+    if 2 * vertical_total < total || vertical_total > 2 * total {
+        return None;
+    }
+    let centre_y = y as f64 + 0.5 + offset;
+
+    let (horizontal, offset) = cross_check(image, column, centre_y.floor() as i64, 1, 0, counts[2])?;
+    let horizontal_total: usize = horizontal.iter().sum();
+    let centre_x = column as f64 + 0.5 + offset;
+    Some((centre_x, centre_y, (horizontal_total + vertical_total) as f64 / 14.0))
+}
+
+fn find_finder_patterns(image: &Binarized) -> Vec<FinderCandidate> {
+    let mut candidates: Vec<FinderCandidate> = Vec::new();
+    let mut record = |x: f64, y: f64, module: f64| {
+        for candidate in candidates.iter_mut() {
+            if (candidate.x - x).abs() <= candidate.module
+                && (candidate.y - y).abs() <= candidate.module
+                && (candidate.module - module).abs() <= candidate.module.max(1.0)
+            {
+                let n = candidate.count as f64;
+                candidate.x = (candidate.x * n + x) / (n + 1.0);
+                candidate.y = (candidate.y * n + y) / (n + 1.0);
+                candidate.module = (candidate.module * n + module) / (n + 1.0);
+                candidate.count += 1;
+                return;
+            }
+        }
+        candidates.push(FinderCandidate { x, y, module, count: 1 });
+    };
+
+    let width = image.width() as usize;
+    for y in 0..image.height() as usize {
+        let mut counts = [0usize; 5];
+        let mut state = 0;
+        for x in 0..=width {
+            let dark = x < width && image.is_dark(x as i64, y as i64);
+            if dark {
+                if state == 1 || state == 3 {
+                    state += 1;
+                }
+                counts[state] += 1;
+                continue;
+            }
+            match state {
+                0 if counts[0] > 0 => {
+                    state = 1;
+                    counts[1] += 1;
+                }
+                0 => {}
+                2 => {
+                    state = 3;
+                    counts[3] += 1;
+                }
+                4 => {
+                    if is_finder_ratio(&counts) {
+                        if let Some((cx, cy, module)) = confirm_finder(image, &counts, x, y) {
+                            record(cx, cy, module);
+                        }
+                    }
+                    counts = [counts[2], counts[3], counts[4], 1, 0];
+                    state = 3;
+                }
+                _ => counts[state] += 1,
+            }
+        }
+    }
+    candidates
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+/// Plausible [top-left, top-right, bottom-left] finder triples, best first.
+fn finder_triples(candidates: &[FinderCandidate]) -> Vec<[FinderCandidate; 3]> {
+    let mut sorted: Vec<FinderCandidate> = candidates.to_vec();
+    sorted.sort_by_key(|c| std::cmp::Reverse(c.count));
+    // A real finder is confirmed on most rows crossing its 3-module centre,
+    // look-alikes in the data area only on a few
+    let solid = |c: &FinderCandidate| c.count >= 2 && c.count as f64 >= 1.5 * c.module;
+    if sorted.iter().filter(|c| solid(c)).count() >= 3 {
+        sorted.retain(solid);
+    }
+    // Room for the finders of several symbols
+    sorted.truncate(24);
+
+    let mut triples = Vec::new();
+    for i in 0..sorted.len() {
+        for j in i + 1..sorted.len() {
+            for k in j + 1..sorted.len() {
+                let points = [sorted[i], sorted[j], sorted[k]];
+                let modules = points.map(|p| p.module);
+                let (min, max) = (modules.iter().cloned().fold(f64::MAX, f64::min), modules.iter().cloned().fold(0.0, f64::max));
+                if max > 2.0 * min {
+                    continue;
+                }
+
+                // The corner opposite the longest side is the top-left finder
+                let sides = [
+                    distance((points[1].x, points[1].y), (points[2].x, points[2].y)),
+                    distance((points[0].x, points[0].y), (points[2].x, points[2].y)),
+                    distance((points[0].x, points[0].y), (points[1].x, points[1].y)),
+                ];
+                let corner = (0..3).max_by(|&a, &b| sides[a].total_cmp(&sides[b])).unwrap_or(0);
+                let top_left = points[corner];
+                let mut a = points[(corner + 1) % 3];
+                let mut b = points[(corner + 2) % 3];
+                let (ax, ay) = (a.x - top_left.x, a.y - top_left.y);
+                let (bx, by) = (b.x - top_left.x, b.y - top_left.y);
+                let (leg_a, leg_b) = (ax.hypot(ay), bx.hypot(by));
+                if leg_a.min(leg_b) < 10.0 * min || leg_a.max(leg_b) > 2.0 * leg_a.min(leg_b) {
+                    continue;
+                }
+                let cosine = (ax * bx + ay * by) / (leg_a * leg_b);
+                if cosine.abs() > 0.5 {
+                    continue;
+                }
+                // With y pointing down, top-right follows top-left clockwise
+                if ax * by - ay * bx < 0.0 {
+                    std::mem::swap(&mut a, &mut b);
+                }
+                let score = (leg_a - leg_b).abs() / leg_a.max(leg_b) + cosine.abs() + (max - min) / max;
+                triples.push((score, [top_left, a, b]));
+            }
+        }
+    }
+    triples.sort_by(|x, y| x.0.total_cmp(&y.0));
+    triples.into_iter().map(|(_, triple)| triple).collect()
+}
+
+/// Width of the finder pattern at `from` along the line to `to`, in modules
+/// of 1/7 of the pattern, or `None` if its edges cannot be found.
+fn finder_module_along(image: &Binarized, from: FinderCandidate, to: FinderCandidate) -> Option<f64> {
+    let length = distance((from.x, from.y), (to.x, to.y));
+    let (dx, dy) = ((to.x - from.x) / length, (to.y - from.y) / length);
+    let limit = 8.0 * from.module;
+    let mut total = 0.0;
+    for sign in [1.0, -1.0] {
+        // Dark centre, light ring, dark ring; the next light pixel ends the pattern
+        let (mut t, mut dark, mut transitions) = (0.0, true, 0);
+        while transitions < 3 {
+            t += 0.5;
+            if t > limit {
+                return None;
+            }
+            let x = (from.x + sign * dx * t).floor() as i64;
+            let y = (from.y + sign * dy * t).floor() as i64;
+            if image.is_dark(x, y) != dark {
+                dark = !dark;
+                transitions += 1;
+            }
+        }
+        total += t;
+    }
+    Some(total / 7.0)
+}
+
+/// Module size along the line between two finder patterns, which unlike the
+/// horizontal scan is not stretched by rotation.
+fn module_size_along(image: &Binarized, a: FinderCandidate, b: FinderCandidate) -> f64 {
+    match (finder_module_along(image, a, b), finder_module_along(image, b, a)) {
+        (Some(x), Some(y)) => (x + y) / 2.0,
+        (Some(x), None) | (None, Some(x)) => x,
+        (None, None) => (a.module + b.module) / 2.0,
+    }
+}
+
+/// Candidate symbol widths from the finder spacing, most likely last.
+fn estimate_dimensions(
+    top_left: FinderCandidate,
+    top_right: FinderCandidate,
+    bottom_left: FinderCandidate,
+    module_top: f64,
+    module_left: f64,
+) -> Vec<usize> {
+    let top = distance((top_left.x, top_left.y), (top_right.x, top_right.y)) / module_top;
+    let left = distance((top_left.x, top_left.y), (bottom_left.x, bottom_left.y)) / module_left;
+    let raw = ((top + left) / 2.0).round() as i64 + 7;
+    // Widths are 4·version + 17, i.e. 1 modulo 4
+    let snapped = match raw.rem_euclid(4) {
+        0 => raw + 1,
+        2 => raw - 1,
+        3 => raw + 2,
+        _ => raw,
+    };
+    [snapped + 4, snapped - 4, snapped]
+        .into_iter()
+        .filter(|d| (21..=177).contains(d))
+        .map(|d| d as usize)
+        .collect()
+}
+
+/// Symbol widths found by counting the modules of the two timing patterns,
+/// which is exact where the finder spacing is only an estimate.
+fn timing_dimensions(
+    image: &Binarized,
+    top_left: FinderCandidate,
+    top_right: FinderCandidate,
+    bottom_left: FinderCandidate,
+    module_top: f64,
+    module_left: f64,
+) -> Vec<usize> {
+    let unit = |a: FinderCandidate, b: FinderCandidate, length: f64| {
+        let d = distance((a.x, a.y), (b.x, b.y));
+        ((b.x - a.x) / d * length, (b.y - a.y) / d * length)
+    };
+    // Row 6 lies three modules below the finder centres, column 6 three to the right
+    let down = unit(top_left, bottom_left, 3.0 * module_left);
+    let right = unit(top_left, top_right, 3.0 * module_top);
+    let lines = [
+        ((top_left.x + down.0, top_left.y + down.1), (top_right.x + down.0, top_right.y + down.1), module_top),
+        ((top_left.x + right.0, top_left.y + right.1), (bottom_left.x + right.0, bottom_left.y + right.1), module_left),
+    ];
+
+    let mut dimensions: Vec<usize> = Vec::new();
+    for (from, to, module) in lines {
+        let length = distance(from, to);
+        let step = (module / 4.0).min(1.0);
+        let samples = (length / step) as usize;
+        let (mut state, mut pending, mut transitions) = (true, 0, 0);
+        for i in 0..=samples {
+            let t = i as f64 / samples.max(1) as f64;
+            let x = from.0 + (to.0 - from.0) * t;
+            let y = from.1 + (to.1 - from.1) * t;
+            // A change only counts once it holds for two samples
+            if image.is_dark(x.floor() as i64, y.floor() as i64) != state {
+                pending += 1;
+                if pending == 2 {
+                    state = !state;
+                    transitions += 1;
+                    pending = 0;
+                }
+            } else {
+                pending = 0;
+            }
+        }
+        let dimension = transitions + 13;
+        if (21..=177).contains(&dimension) && (dimension - 17) % 4 == 0 && !dimensions.contains(&dimension) {
+            dimensions.push(dimension);
+        }
+    }
+    dimensions
+}
+
+//------------------------------------------------------------------------------
+// Geometry
+
+/// Projective transform between two planes.
+#[derive(Clone, Copy, Debug)]
+pub struct Perspective {
+    m: [f64; 8],
+    /// Coordinates are scaled down on input and up on output to keep the
+    /// equations well conditioned.
+    from_scale: f64,
+    to_scale: f64,
+}
+
+impl Perspective {
+    /// Transform mapping each `from` point to the matching `to` point, or
+    /// `None` if the points are degenerate.
+    pub fn from_quads(from: [(f64, f64); 4], to: [(f64, f64); 4]) -> Option<Self> {
+        let pairs: Vec<_> = from.into_iter().zip(to).collect();
+        Self::fit(&pairs)
+    }
+
+    /// Least-squares transform through four or more point pairs.
+    pub fn fit(pairs: &[(Point, Point)]) -> Option<Self> {
+        if pairs.len() < 4 {
+            return None;
+        }
+        let largest = |points: &mut dyn Iterator<Item = (f64, f64)>| points.fold(1.0f64, |m, (a, b)| m.max(a.abs()).max(b.abs()));
+        let from_scale = largest(&mut pairs.iter().map(|p| p.0));
+        let to_scale = largest(&mut pairs.iter().map(|p| p.1));
+
+        // Normal equations of the linearized projective mapping
+        let mut rows = [[0f64; 9]; 8];
+        for &((u, v), (x, y)) in pairs {
+            let (u, v, x, y) = (u / from_scale, v / from_scale, x / to_scale, y / to_scale);
+            for equation in [[u, v, 1.0, 0.0, 0.0, 0.0, -u * x, -v * x, x], [0.0, 0.0, 0.0, u, v, 1.0, -u * y, -v * y, y]] {
+                for (i, row) in rows.iter_mut().enumerate() {
+                    for (j, value) in row.iter_mut().enumerate() {
+                        *value += equation[i] * equation[j];
+                    }
+                }
+            }
+        }
+
+        // Gaussian elimination with partial pivoting
+        for col in 0..8 {
+            let pivot = (col..8).max_by(|&a, &b| rows[a][col].abs().total_cmp(&rows[b][col].abs()))?;
+            if rows[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            rows.swap(col, pivot);
+            let pivot_row = rows[col];
+            for (index, row) in rows.iter_mut().enumerate() {
+                if index != col {
+                    let factor = row[col] / pivot_row[col];
+                    for (value, pivot_value) in row.iter_mut().zip(pivot_row.iter()).skip(col) {
+                        *value -= factor * pivot_value;
+                    }
+                }
+            }
+        }
+        let mut m = [0f64; 8];
+        for (i, value) in m.iter_mut().enumerate() {
+            *value = rows[i][8] / rows[i][i];
+        }
+        Some(Self { m, from_scale, to_scale })
+    }
+
+    /// Maps a point.
+    pub fn map(&self, u: f64, v: f64) -> (f64, f64) {
+        let m = &self.m;
+        let (u, v) = (u / self.from_scale, v / self.from_scale);
+        let w = m[6] * u + m[7] * v + 1.0;
+        (
+            (m[0] * u + m[1] * v + m[2]) / w * self.to_scale,
+            (m[3] * u + m[4] * v + m[5]) / w * self.to_scale,
+        )
+    }
+}
+
+impl Location {
+    fn new(image: &Binarized, finders: [FinderCandidate; 3], module_size: f64, dimension: usize) -> Self {
+        let [tl, tr, bl] = finders.map(|f| (f.x, f.y));
+        let d = dimension as f64;
+        let span = d - 7.0;
+        let affine = |u: f64, v: f64| {
+            (
+                tl.0 + (tr.0 - tl.0) * (u - 3.5) / span + (bl.0 - tl.0) * (v - 3.5) / span,
+                tl.1 + (tr.1 - tl.1) * (u - 3.5) / span + (bl.1 - tl.1) * (v - 3.5) / span,
+            )
+        };
+
+        let finder_points = [(3.5, 3.5), (d - 3.5, 3.5), (3.5, d - 3.5)];
+        let mut transform = Perspective::from_quads(
+            [finder_points[0], finder_points[1], finder_points[2], (d - 3.5, d - 3.5)],
+            [tl, tr, bl, affine(d - 3.5, d - 3.5)],
+        )
+        .unwrap_or(Perspective { m: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0], from_scale: 1.0, to_scale: 1.0 });
+
+        let positions = alignment_positions(dimension);
+        let last = positions.last().copied().unwrap_or(0);
+        let mut alignment = None;
+        if positions.len() == 2 {
+            // Versions 2-6: a single alignment pattern, possibly far from the
+            // affine estimate under perspective
+            let axes = (
+                ((tr.0 - tl.0) / span, (tr.1 - tl.1) / span),
+                ((bl.0 - tl.0) / span, (bl.1 - tl.1) / span),
+            );
+            alignment = find_alignment(image, affine(d - 6.5, d - 6.5), axes, module_size, &[4.0, 8.0, 16.0]);
+            if let Some(point) = alignment {
+                transform = Perspective::from_quads(
+                    [finder_points[0], finder_points[1], finder_points[2], (d - 6.5, d - 6.5)],
+                    [tl, tr, bl, point],
+                )
+                .unwrap_or(transform);
+            }
+        } else if positions.len() > 2 {
+            // Versions 7+: grow outwards from the finders, fitting through every
+            // alignment pattern found so far so errors do not add up
+            let mut pairs = vec![(finder_points[0], tl), (finder_points[1], tr), (finder_points[2], bl)];
+            let mut pending: Vec<(usize, usize)> = positions
+                .iter()
+                .flat_map(|&y| positions.iter().map(move |&x| (x, y)))
+                .filter(|&(x, y)| !(x == 6 && (y == 6 || y == last) || (x == last && y == 6)))
+                .collect();
+            for _ in 0..4 {
+                let found_before = pairs.len();
+                pending.retain(|&(x, y)| {
+                    let (u, v) = (x as f64 + 0.5, y as f64 + 0.5);
+                    let centre = transform.map(u, v);
+                    let right = transform.map(u + 1.0, v);
+                    let down = transform.map(u, v + 1.0);
+                    let axes = ((right.0 - centre.0, right.1 - centre.1), (down.0 - centre.0, down.1 - centre.1));
+                    match find_alignment(image, centre, axes, module_size, &[1.5]) {
+                        Some(point) => {
+                            if x == last && y == last {
+                                alignment = Some(point);
+                            }
+                            pairs.push(((u, v), point));
+                            false
+                        }
+                        None => true,
+                    }
+                });
+                if pairs.len() == found_before {
+                    break;
+                }
+                transform = Perspective::fit(&pairs).unwrap_or(transform);
+            }
+        }
+
+        Self { top_left: tl, top_right: tr, bottom_left: bl, alignment, module_size, dimension, transform }
+    }
+
+    /// Image coordinates of the symbol centre.
+    pub fn centre(&self) -> Point {
+        let d = self.dimension as f64 / 2.0;
+        self.transform.map(d, d)
+    }
+
+    /// Whether `point`, in image coordinates, lies within the symbol.
+    pub fn covers(&self, point: Point) -> bool {
+        // Module coordinates through the affine frame of the finder centres
+        let span = self.dimension as f64 - 7.0;
+        let (ux, uy) = ((self.top_right.0 - self.top_left.0) / span, (self.top_right.1 - self.top_left.1) / span);
+        let (vx, vy) = ((self.bottom_left.0 - self.top_left.0) / span, (self.bottom_left.1 - self.top_left.1) / span);
+        let determinant = ux * vy - uy * vx;
+        if determinant.abs() < 1e-9 {
+            return false;
+        }
+        let (px, py) = (point.0 - self.top_left.0, point.1 - self.top_left.1);
+        let u = (px * vy - py * vx) / determinant + 3.5;
+        let v = (ux * py - uy * px) / determinant + 3.5;
+        let range = 0.0..=self.dimension as f64;
+        range.contains(&u) && range.contains(&v)
+    }
+}
+
+/// Centre coordinates of the alignment patterns in a symbol of `dimension` modules.
+pub fn alignment_positions(dimension: usize) -> Vec<usize> {
+    let version = (dimension - 17) / 4;
+    if version < 2 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let step = if version == 32 { 26 } else { (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2 };
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| dimension - 7 - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// Searches around `estimate` for the 5×5 alignment pattern (dark centre,
+/// light ring, dark ring), using `axes` as the per-module step vectors.
+fn find_alignment(
+    image: &Binarized,
+    estimate: (f64, f64),
+    axes: ((f64, f64), (f64, f64)),
+    module_size: f64,
+    allowances: &[f64],
+) -> Option<(f64, f64)> {
+    let step = ((module_size / 4.0).floor() as i64).max(1);
+    let (ex, ey) = (estimate.0.round() as i64, estimate.1.round() as i64);
+
+    let score_at = |cx: f64, cy: f64| {
+        let mut score = 0;
+        for j in -2i32..=2 {
+            for i in -2i32..=2 {
+                let expected_dark = i.abs().max(j.abs()) != 1;
+                let x = cx + f64::from(i) * axes.0 .0 + f64::from(j) * axes.1 .0;
+                let y = cy + f64::from(i) * axes.0 .1 + f64::from(j) * axes.1 .1;
+                if image.is_dark(x.floor() as i64, y.floor() as i64) == expected_dark {
+                    score += 1;
+                }
+            }
+        }
+        score
+    };
+
+    // Perspective moves the pattern away from the affine estimate, so widen
+    // the search until a convincing match turns up
+    for &allowance in allowances {
+        let radius = (allowance * module_size).ceil() as i64;
+        let mut best = 0;
+        let mut hits: Vec<(f64, f64)> = Vec::new();
+        for y in (ey - radius..=ey + radius).step_by(step as usize) {
+            for x in (ex - radius..=ex + radius).step_by(step as usize) {
+                let (cx, cy) = (x as f64 + 0.5, y as f64 + 0.5);
+                let score = score_at(cx, cy);
+                if score > best {
+                    best = score;
+                    hits.clear();
+                }
+                if score == best {
+                    hits.push((cx, cy));
+                }
+            }
+        }
+        if best < 23 {
+            continue;
+        }
+        // Best hits form a blob around the true centre; keep the one nearest the estimate
+        let nearest = hits.iter().copied().min_by(|a, b| distance(*a, estimate).total_cmp(&distance(*b, estimate)))?;
+        let blob: Vec<(f64, f64)> = hits.into_iter().filter(|h| distance(*h, nearest) <= module_size).collect();
+        let n = blob.len() as f64;
+        return Some((blob.iter().map(|h| h.0).sum::<f64>() / n, blob.iter().map(|h| h.1).sum::<f64>() / n));
+    }
+    None
+}
+
+/// Samples every module of the located symbol, row by row.
+pub fn sample_grid(image: &Binarized, location: &Location) -> Vec<Color> {
+    let dimension = location.dimension;
+    let radius = (location.module_size * 0.2).floor() as i64;
+    let mut modules = Vec::with_capacity(dimension * dimension);
+    for y in 0..dimension {
+        for x in 0..dimension {
+            let (px, py) = location.transform.map(x as f64 + 0.5, y as f64 + 0.5);
+            modules.push(image.sample(px, py, radius));
+        }
+    }
+    modules
+}
+
+//------------------------------------------------------------------------------
+// Format and version information
+
+static FORMAT_INFO_COORDS_MAIN: [(i16, i16); 15] = [
+    (0, 8),
+    (1, 8),
+    (2, 8),
+    (3, 8),
+    (4, 8),
+    (5, 8),
+    (7, 8),
+    (8, 8),
+    (8, 7),
+    (8, 5),
+    (8, 4),
+    (8, 3),
+    (8, 2),
+    (8, 1),
+    (8, 0),
+];
+
+static FORMAT_INFO_COORDS_SIDE: [(i16, i16); 15] = [
+    (8, -1),
+    (8, -2),
+    (8, -3),
+    (8, -4),
+    (8, -5),
+    (8, -6),
+    (8, -7),
+    (-8, 8),
+    (-7, 8),
+    (-6, 8),
+    (-5, 8),
+    (-4, 8),
+    (-3, 8),
+    (-2, 8),
+    (-1, 8),
+];
+
+/// Format information for 5 data bits: BCH(15,5) code XOR 0x5412.
+fn format_code(data: u32) -> u32 {
+    let mut remainder = data << 10;
+    for bit in (10..15).rev() {
+        if remainder & (1 << bit) != 0 {
+            remainder ^= 0x537 << (bit - 10);
+        }
+    }
+    ((data << 10) | remainder) ^ 0x5412
+}
+
+/// Version information for versions 7-40: BCH(18,6) code.
+fn version_code(version: u32) -> u32 {
+    let mut remainder = version << 12;
+    for bit in (12..18).rev() {
+        if remainder & (1 << bit) != 0 {
+            remainder ^= 0x1f25 << (bit - 12);
+        }
+    }
+    (version << 12) | remainder
+}
+
+/// Reads bits (most significant first) at `coords`; negative coordinates
+/// count from the right or bottom edge.
+fn read_number(modules: &[Color], width: usize, coords: impl Iterator<Item = (i16, i16)>) -> u32 {
+    let w = width as i16;
+    coords.fold(0, |acc, (x, y)| {
+        let x = if x < 0 { x + w } else { x } as usize;
+        let y = if y < 0 { y + w } else { y } as usize;
+        acc << 1 | u32::from(modules[y * width + x] == Color::Dark)
+    })
+}
+
+/// Closest valid code within 3 bit errors.
+fn nearest_code(read: u32, codes: impl Iterator<Item = (u32, u32)>) -> Option<(u32, u32)> {
+    codes
+        .map(|(value, code)| (value, (code ^ read).count_ones()))
+        .min_by_key(|&(_, distance)| distance)
+        .filter(|&(_, distance)| distance <= 3)
+}
+
+/// Error correction level and mask pattern from the format information.
+fn read_format_info(modules: &[Color], width: usize) -> Option<(EcLevel, u8)> {
+    let copies = [
+        read_number(modules, width, FORMAT_INFO_COORDS_MAIN.iter().copied()),
+        read_number(modules, width, FORMAT_INFO_COORDS_SIDE.iter().copied()),
+    ];
+    let (data, _) = copies
+        .iter()
+        .filter_map(|&read| nearest_code(read, (0..32).map(|data| (data, format_code(data)))))
+        .min_by_key(|&(_, distance)| distance)?;
+    let ec_level = match data >> 3 {
+        0b01 => EcLevel::L,
+        0b00 => EcLevel::M,
+        0b11 => EcLevel::Q,
+        _ => EcLevel::H,
+    };
+    Some((ec_level, (data & 0b111) as u8))
+}
+
+/// Version encoded in the version information blocks (versions 7-40 only).
+fn read_version_info(modules: &[Color], width: usize) -> Option<i16> {
+    if width < 45 {
+        return None;
+    }
+    let bottom_left = (0..6).rev().flat_map(|x| (9..12).map(move |y| (x, -y)));
+    let top_right = (0..6).rev().flat_map(|y| (9..12).map(move |x| (-x, y)));
+    [read_number(modules, width, bottom_left), read_number(modules, width, top_right)]
+        .iter()
+        .filter_map(|&read| nearest_code(read, (7..=40).map(|v| (v, version_code(v)))))
+        .min_by_key(|&(_, distance)| distance)
+        .map(|(version, _)| version as i16)
+}
+
+//------------------------------------------------------------------------------
+// Bit stream
+
+fn mask_applies(mask: u8, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (y / 2 + x / 3).is_multiple_of(2),
+        5 => (x * y) % 2 + (x * y) % 3 == 0,
+        6 => ((x * y) % 2 + (x * y) % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + (x * y) % 3).is_multiple_of(2),
+    }
+}
+
+/// Decodes a sampled module grid (row by row, `width` × `width`).
+pub fn decode_grid(modules: &[Color], width: usize) -> Result<Decoded, Box<dyn Error>> {
+    if !(21..=177).contains(&width) || !(width - 17).is_multiple_of(4) || modules.len() != width * width {
+        return Err(format!("Invalid symbol size: {} modules", width).into());
+    }
+    let version = Version::Normal(((width - 17) / 4) as i16);
+    let (ec_level, mask) = read_format_info(modules, width).ok_or("Unreadable format information")?;
+
+    let codewords: Vec<u8> = codeword_modules(version, ec_level)
+        .iter()
+        .map(|positions| {
+            positions.iter().fold(0u8, |acc, &(x, y)| {
+                acc << 1 | u8::from((modules[y * width + x] == Color::Dark) ^ mask_applies(mask, x, y))
+            })
+        })
+        .collect();
+
+    let layout = block_layout(version, ec_level);
+    let blocks = layout.len();
+    let ec_len = capacity::ec_codewords_per_block(version, ec_level);
+    let mut block_data: Vec<Vec<u8>> =
+        layout.iter().map(|indices| indices.iter().map(|&i| codewords[i]).collect()).collect();
+
+    let mut data = Vec::with_capacity(capacity::data_codewords(version, ec_level));
+    let mut corrected = Vec::with_capacity(blocks);
+    for (b, block) in block_data.iter_mut().enumerate() {
+        let fixed = reed_solomon::correct(block, ec_len)
+            .map_err(|e| format!("Block {} of {}: {}", b + 1, blocks, e))?;
+        corrected.push(fixed);
+        data.extend_from_slice(&block[..block.len() - ec_len]);
+    }
+
+    let (segments, eci) = parse_segments(&data, version)?;
+    let payload = segments.iter().flat_map(|s| s.data.iter().copied()).collect();
+    Ok(Decoded {
+        data: payload,
+        version,
+        ec_level,
+        mask,
+        eci,
+        segments,
+        corrected,
+        correctable: ec_len / 2,
+    })
+}
+
+/// Module coordinates of every codeword in placement order, most significant
+/// bit first. Remainder bits are left out.
+pub fn codeword_modules(version: Version, ec_level: EcLevel) -> Vec<[(usize, usize); 8]> {
+    // Data modules are the ones left empty by the function patterns
+    let width = version.width() as usize;
+    let mut canvas = Canvas::new(version, ec_level);
+    canvas.draw_all_functional_patterns();
+    let mut positions = Vec::with_capacity(width * width);
+    let mut right = width as i64 - 1;
+    let mut upward = true;
+    while right > 0 {
+        if right == 6 {
+            right -= 1;
+        }
+        for i in 0..width {
+            let y = if upward { width - 1 - i } else { i };
+            for x in [right as usize, right as usize - 1] {
+                if canvas.get(x as i16, y as i16) == Module::Empty {
+                    positions.push((x, y));
+                }
+            }
+        }
+        upward = !upward;
+        right -= 2;
+    }
+    positions
+        .chunks_exact(8)
+        .take(capacity::total_codewords(version))
+        .map(|chunk| std::array::from_fn(|i| chunk[i]))
+        .collect()
+}
+
+/// Placement indices of the codewords of each error correction block, data
+/// codewords first. Short blocks come first; long blocks have one more data
+/// codeword.
+pub fn block_layout(version: Version, ec_level: EcLevel) -> Vec<Vec<usize>> {
+    let blocks = capacity::ec_blocks(version, ec_level);
+    let ec_len = capacity::ec_codewords_per_block(version, ec_level);
+    let total = capacity::total_codewords(version);
+    let short_data = total / blocks - ec_len;
+    let long_count = total % blocks;
+    let data_len = |block: usize| short_data + usize::from(block >= blocks - long_count);
+
+    let mut layout: Vec<Vec<usize>> = (0..blocks).map(|b| Vec::with_capacity(data_len(b) + ec_len)).collect();
+    let mut next = 0..total;
+    for i in 0..=short_data {
+        for (b, block) in layout.iter_mut().enumerate() {
+            if i < data_len(b) {
+                block.extend(next.next());
+            }
+        }
+    }
+    for _ in 0..ec_len {
+        for block in layout.iter_mut() {
+            block.extend(next.next());
+        }
+    }
+    layout
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.position
+    }
+
+    fn read(&mut self, bits: usize) -> Result<u32, Box<dyn Error>> {
+        if bits > self.remaining() {
+            return Err("Bit stream ends inside a segment".into());
+        }
+        let mut value = 0u32;
+        for _ in 0..bits {
+            let byte = self.data[self.position / 8];
+            value = value << 1 | u32::from(byte >> (7 - self.position % 8) & 1);
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+const ALPHANUMERIC: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+fn parse_segments(data: &[u8], version: Version) -> Result<(Vec<Segment>, Option<u32>), Box<dyn Error>> {
+    let mut reader = BitReader { data, position: 0 };
+    let mut segments = Vec::new();
+    let mut eci = None;
+
+    while reader.remaining() >= 4 {
+        let mode = match reader.read(4)? {
+            0b0000 => break,
+            0b0001 => DataMode::Numeric,
+            0b0010 => DataMode::Alphanumeric,
+            0b0100 => DataMode::Byte,
+            0b1000 => DataMode::Kanji,
+            0b0111 => {
+                let first = reader.read(8)?;
+                let designator = if first & 0x80 == 0 {
+                    first
+                } else if first & 0xc0 == 0x80 {
+                    (first & 0x3f) << 8 | reader.read(8)?
+                } else {
+                    (first & 0x1f) << 16 | reader.read(16)?
+                };
+                eci.get_or_insert(designator);
+                continue;
+            }
+            // Structured append: sequence indicator and parity
+            0b0011 => {
+                reader.read(16)?;
+                continue;
+            }
+            // FNC1 in first position carries no data, in second position an application indicator
+            0b0101 => continue,
+            0b1001 => {
+                reader.read(8)?;
+                continue;
+            }
+            other => return Err(format!("Unknown mode indicator {:04b}", other).into()),
+        };
+
+        let count = reader.read(char_count_bits(version, mode))? as usize;
+        let mut bytes = Vec::with_capacity(count * 2);
+        match mode {
+            DataMode::Numeric => {
+                let mut left = count;
+                while left > 0 {
+                    let (digits, bits) = match left {
+                        1 => (1, 4),
+                        2 => (2, 7),
+                        _ => (3, 10),
+                    };
+                    let value = reader.read(bits)?;
+                    if value >= 10u32.pow(digits) {
+                        return Err("Invalid numeric segment".into());
+                    }
+                    bytes.extend(format!("{:0width$}", value, width = digits as usize).bytes());
+                    left -= digits as usize;
+                }
+            }
+            DataMode::Alphanumeric => {
+                for _ in 0..count / 2 {
+                    let value = reader.read(11)? as usize;
+                    if value >= 45 * 45 {
+                        return Err("Invalid alphanumeric segment".into());
+                    }
+                    bytes.extend([ALPHANUMERIC[value / 45], ALPHANUMERIC[value % 45]]);
+                }
+                if count % 2 == 1 {
+                    let value = reader.read(6)? as usize;
+                    bytes.push(*ALPHANUMERIC.get(value).ok_or("Invalid alphanumeric segment")?);
+                }
+            }
+            DataMode::Byte => {
+                for _ in 0..count {
+                    bytes.push(reader.read(8)? as u8);
+                }
+            }
+            DataMode::Kanji => {
+                for _ in 0..count {
+                    let value = reader.read(13)?;
+                    let code = ((value / 0xc0) << 8) | (value % 0xc0);
+                    let code = if code < 0x1f00 { code + 0x8140 } else { code + 0xc140 };
+                    bytes.extend([(code >> 8) as u8, code as u8]);
+                }
+            }
+        }
+        segments.push(Segment { mode, data: bytes });
+    }
+    Ok((segments, eci))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raster::{self, Geometry, Sizing};
+    use crate::stress::Distortion;
+    use crate::style::Style;
+    use crate::symbol::{QrSymbol, VersionChoice};
+    use image::{imageops, Luma, Rgba};
+    use qrcode::QrCode;
+
+    const LEVELS: [EcLevel; 4] = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+
+    /// Module grid of `symbol`, row by row.
+    fn grid(symbol: &QrSymbol) -> Vec<Color> {
+        let width = symbol.width();
+        (0..width * width).map(|i| symbol[(i % width, i / width)]).collect()
+    }
+
+    /// `symbol` rendered black on white at `module_px` pixels per module.
+    fn render(symbol: &QrSymbol, module_px: u32) -> GrayImage {
+        let geometry = Geometry::new(symbol.width() as u32, 4, Sizing::ModulePx(module_px)).unwrap();
+        let image = raster::render(symbol, &geometry, &Style::default(), Rgba([0, 0, 0, 255]), Rgba([255, 255, 255, 255]));
+        to_luma(&DynamicImage::ImageRgba8(image))
+    }
+
+    /// `payload` at 4 pixels per module with a 4-module quiet zone.
+    fn symbol(payload: &str) -> GrayImage {
+        let code = QrCode::new(payload).unwrap();
+        let (width, colors) = (code.width() as u32, code.to_colors());
+        GrayImage::from_fn((width + 8) * 4, (width + 8) * 4, |x, y| {
+            let (mx, my) = ((x / 4).wrapping_sub(4), (y / 4).wrapping_sub(4));
+            let dark = mx < width && my < width && colors[(my * width + mx) as usize] == Color::Dark;
+            Luma([if dark { 0 } else { 255 }])
+        })
+    }
+
+    #[test]
+    fn decodes_every_symbol_in_reading_order() {
+        let (first, second, third) = (symbol("first"), symbol("second symbol"), symbol("third"));
+        let mut sheet = GrayImage::from_pixel(first.width() + second.width() + 40, 2 * third.height() + 40, Luma([255]));
+        image::imageops::overlay(&mut sheet, &second, i64::from(first.width()) + 20, 20);
+        image::imageops::overlay(&mut sheet, &third, 20, i64::from(first.height()) + 20);
+        image::imageops::overlay(&mut sheet, &first, 20, 20);
+
+        let payloads: Vec<Vec<u8>> = detect(&sheet).unwrap().into_iter().map(|d| d.decoded.data).collect();
+        assert_eq!(payloads, [b"first".to_vec(), b"second symbol".to_vec(), b"third".to_vec()]);
+    }
+
+    #[test]
+    fn verify_accepts_any_matching_symbol() {
+        let (first, second) = (symbol("first"), symbol("second"));
+        let mut sheet = GrayImage::from_pixel(first.width() + second.width(), first.height().max(second.height()), Luma([255]));
+        image::imageops::overlay(&mut sheet, &first, 0, 0);
+        image::imageops::overlay(&mut sheet, &second, i64::from(first.width()), 0);
+        let image = DynamicImage::ImageLuma8(sheet);
+
+        assert_eq!(verify(&image, b"second").unwrap().data, b"second");
+        assert!(verify(&image, b"third").is_err());
+    }
+
+    #[test]
+    fn decode_grid_round_trips_versions_levels_and_masks() {
+        for v in [1, 7, 40] {
+            for ec_level in LEVELS {
+                for mask in 0..8 {
+                    let symbol = QrSymbol::encode(b"QR-1234", ec_level, VersionChoice::Exact(v), Some(mask)).unwrap();
+                    let decoded = decode_grid(&grid(&symbol), symbol.width()).unwrap();
+                    let case = format!("version {} {:?} mask {}", v, ec_level, mask);
+                    assert_eq!(decoded.data, b"QR-1234", "{}", case);
+                    assert_eq!((decoded.version, decoded.ec_level, decoded.mask), (Version::Normal(v), ec_level, mask), "{}", case);
+                    assert!(decoded.corrected.iter().all(|&c| c == 0), "{}", case);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn decode_grid_corrects_damaged_modules() {
+        let symbol = QrSymbol::encode(b"https://example.com/damaged", EcLevel::H, VersionChoice::Exact(5), None).unwrap();
+        let width = symbol.width();
+        let mut modules = grid(&symbol);
+        // A 5x5 blot in the data area, away from the function patterns
+        for y in 15..20 {
+            for x in 15..20 {
+                modules[y * width + x] = if modules[y * width + x] == Color::Dark { Color::Light } else { Color::Dark };
+            }
+        }
+        let decoded = decode_grid(&modules, width).unwrap();
+        assert_eq!(decoded.data, b"https://example.com/damaged");
+        assert!(decoded.corrected.iter().sum::<usize>() > 0);
+    }
+
+    #[test]
+    fn reads_version_information() {
+        for v in 7..=40 {
+            let symbol = QrSymbol::encode(b"1", EcLevel::L, VersionChoice::Exact(v), None).unwrap();
+            let width = symbol.width();
+            let mut modules = grid(&symbol);
+            assert_eq!(read_version_info(&modules, width), Some(v));
+
+            // Three wrong bits in one copy are still within the code's distance
+            for (x, y) in [(0, width - 11), (2, width - 10), (5, width - 9)] {
+                modules[y * width + x] = if modules[y * width + x] == Color::Dark { Color::Light } else { Color::Dark };
+            }
+            assert_eq!(read_version_info(&modules, width), Some(v), "version {}", v);
+        }
+        let small = QrSymbol::encode(b"1", EcLevel::L, VersionChoice::Exact(6), None).unwrap();
+        assert_eq!(read_version_info(&grid(&small), small.width()), None);
+    }
+
+    #[test]
+    fn decodes_scaled_and_rotated_renders() {
+        let symbol = QrSymbol::encode(b"https://example.com/scan", EcLevel::M, VersionChoice::Auto, None).unwrap();
+        let sharp = render(&symbol, 6);
+        let scaled = imageops::resize(&sharp, sharp.width() * 5 / 12, sharp.height() * 5 / 12, imageops::FilterType::Triangle);
+        let quarter = imageops::rotate90(&sharp);
+        let geometry = Geometry::new(symbol.width() as u32, 4, Sizing::ModulePx(6)).unwrap();
+        let tilted = Distortion::Rotation.apply(&sharp, &geometry, 22.5).unwrap();
+        for (name, image) in [("scaled", scaled), ("rotated 90°", quarter), ("rotated 22.5°", tilted)] {
+            let decoded = decode_image(&DynamicImage::ImageLuma8(image)).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert_eq!(decoded.len(), 1, "{}", name);
+            assert_eq!(decoded[0].data, b"https://example.com/scan", "{}", name);
+        }
+    }
+}
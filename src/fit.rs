@@ -0,0 +1,164 @@
+//! Auto-fit: pick the strongest error correction level that still fits a
+//! version or physical size budget.
+
+use crate::segment::{self, Payload};
+use qrcode::{EcLevel, Version};
+
+/// Error correction levels from weakest to strongest.
+pub const EC_LEVELS: [EcLevel; 4] = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+
+/// Approximate share of damaged codewords each level can restore.
+pub fn recovery_percent(ec_level: EcLevel) -> u32 {
+    match ec_level {
+        EcLevel::L => 7,
+        EcLevel::M => 15,
+        EcLevel::Q => 25,
+        EcLevel::H => 30,
+    }
+}
+
+/// The budget the symbol has to fit in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FitConstraint {
+    /// Largest acceptable version.
+    MaxVersion(i16),
+    /// Printed size (including quiet zone) with a minimum module size.
+    PhysicalSize { target_mm: f64, min_module_mm: f64, margin: u32 },
+}
+
+impl FitConstraint {
+    /// Largest version allowed by the constraint, or an error if it is out
+    /// of range or not even version 1 fits.
+    pub fn max_version(&self) -> Result<i16, Box<dyn std::error::Error>> {
+        match *self {
+            FitConstraint::MaxVersion(v) if !(1..=crate::symbol::MAX_VERSION).contains(&v) => {
+                Err(format!("Invalid maximum version {}. Use a value between 1 and {}", v, crate::symbol::MAX_VERSION).into())
+            }
+            FitConstraint::MaxVersion(v) => Ok(v),
+            FitConstraint::PhysicalSize { target_mm, min_module_mm, margin } => {
+                let total_modules = (target_mm / min_module_mm).floor() as i64;
+                let width = total_modules - 2 * margin as i64;
+                if width < 21 {
+                    return Err(format!(
+                        "Target size {:.1} mm holds only {} modules of {:.2} mm; version 1 needs {} including the margin",
+                        target_mm,
+                        total_modules,
+                        min_module_mm,
+                        21 + 2 * margin
+                    )
+                    .into());
+                }
+                Ok((((width - 17) / 4) as i16).min(crate::symbol::MAX_VERSION))
+            }
+        }
+    }
+
+    /// Module size in millimetres when a symbol of `version` fills the target size.
+    pub fn module_mm(&self, version: Version) -> Option<f64> {
+        match (*self, version) {
+            (FitConstraint::PhysicalSize { target_mm, margin, .. }, Version::Normal(_)) => {
+                Some(target_mm / (version.width() as f64 + 2.0 * margin as f64))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Outcome for one error correction level.
+#[derive(Clone, Copy, Debug)]
+pub struct FitCandidate {
+    pub ec_level: EcLevel,
+    /// Smallest version holding the data, `None` if it does not fit at all.
+    pub version: Option<Version>,
+    pub fits: bool,
+}
+
+/// Result of the auto-fit search.
+#[derive(Clone, Debug)]
+pub struct FitReport {
+    pub constraint: FitConstraint,
+    pub max_version: i16,
+    pub candidates: Vec<FitCandidate>,
+    /// Strongest level that fits, if any.
+    pub chosen: Option<EcLevel>,
+}
+
+/// Evaluates every error correction level against `constraint`.
+pub fn fit(payload: &Payload, constraint: FitConstraint) -> Result<FitReport, Box<dyn std::error::Error>> {
+    let max_version = constraint.max_version()?;
+    let candidates: Vec<FitCandidate> = EC_LEVELS
+        .iter()
+        .map(|&ec_level| {
+            let version = segment::smallest_fit(ec_level, 1, |v| payload.encoded_bits(v)).map(|(v, _)| v);
+            let fits = matches!(version, Some(Version::Normal(v)) if v <= max_version);
+            FitCandidate { ec_level, version, fits }
+        })
+        .collect();
+    let chosen = candidates.iter().rev().find(|c| c.fits).map(|c| c.ec_level);
+    Ok(FitReport { constraint, max_version, candidates, chosen })
+}
+
+impl FitReport {
+    /// One line per level describing the version it needs.
+    pub fn candidate_lines(&self) -> Vec<String> {
+        self.candidates
+            .iter()
+            .map(|c| {
+                let status = if c.fits { "✓" } else { "✗" };
+                let version = match c.version {
+                    Some(v @ Version::Normal(n)) => match self.constraint.module_mm(v) {
+                        Some(mm) => format!("version {} ({:.2} mm modules)", n, mm),
+                        None => format!("version {}", n),
+                    },
+                    _ => "does not fit any version".to_string(),
+                };
+                format!("{} {:?} (~{}% recovery): {}", status, c.ec_level, recovery_percent(c.ec_level), version)
+            })
+            .collect()
+    }
+
+    /// Short explanation of the trade-off behind the chosen level.
+    pub fn explanation(&self) -> String {
+        let Some(chosen) = self.chosen else {
+            return format!("Data does not fit within version {} even at level L", self.max_version);
+        };
+        let mut text = format!(
+            "Selected {:?} (~{}% recovery): strongest level that fits within version {}",
+            chosen,
+            recovery_percent(chosen),
+            self.max_version
+        );
+        if let Some(stronger) = self.candidates.iter().find(|c| !c.fits) {
+            match stronger.version {
+                Some(Version::Normal(v)) => {
+                    text.push_str(&format!("; {:?} would need version {}", stronger.ec_level, v))
+                }
+                _ => text.push_str(&format!("; {:?} does not fit any version", stronger.ec_level)),
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_out_of_range_max_version() {
+        let payload = Payload::bytes(b"https://example.com");
+        for v in [0, -1, 41] {
+            assert!(fit(&payload, FitConstraint::MaxVersion(v)).is_err(), "version {}", v);
+        }
+        assert_eq!(FitConstraint::MaxVersion(40).max_version().unwrap(), 40);
+    }
+
+    #[test]
+    fn picks_strongest_level_within_budget() {
+        // 164 bits in byte mode: version 2 holds them up to Q, version 3 at H
+        let payload = Payload::bytes(b"https://example.com");
+        assert_eq!(fit(&payload, FitConstraint::MaxVersion(3)).unwrap().chosen, Some(EcLevel::H));
+        assert_eq!(fit(&payload, FitConstraint::MaxVersion(2)).unwrap().chosen, Some(EcLevel::Q));
+        assert_eq!(fit(&payload, FitConstraint::MaxVersion(1)).unwrap().chosen, None);
+    }
+}
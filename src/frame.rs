@@ -0,0 +1,465 @@
+//! Frames with a call-to-action caption around the code, laid out relative to
+//! the output canvas.
+
+use crate::contrast::ContrastCheck;
+use crate::font;
+use crate::raster::{self, Sizing, MAX_CANVAS_PX};
+use image::{imageops, Pixel, Rgba, RgbaImage};
+use svg::node::element::{Group, Path, Rectangle, Text};
+use svg::Document;
+
+/// Border thickness as a share of the canvas.
+const BORDER: f64 = 0.025;
+/// Caption band height per caption height.
+const BAND: f64 = 1.8;
+/// Speech bubble tail length per band height.
+const TAIL: f64 = 0.35;
+/// Corner radius of the rounded banner as a share of the canvas.
+const CORNER: f64 = 0.06;
+/// Cap height of common sans-serif fonts per font size, for SVG captions.
+const CAP_HEIGHT: f64 = 0.72;
+/// Samples per pixel side when anti-aliasing frame edges.
+const SUPERSAMPLING: u32 = 4;
+
+/// Shape of the frame around the code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameTemplate {
+    /// Square border with the caption in a filled band.
+    Box,
+    /// Border with rounded corners and the caption in a filled band.
+    Banner,
+    /// Caption in a rounded speech bubble pointing at the code.
+    Bubble,
+}
+
+impl FrameTemplate {
+    pub const ALL: [FrameTemplate; 3] = [FrameTemplate::Box, FrameTemplate::Banner, FrameTemplate::Bubble];
+
+    pub fn parse(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::ALL
+            .into_iter()
+            .find(|template| template.name().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| format!("Invalid frame: {}. Use box, banner or bubble", name).into())
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FrameTemplate::Box => "box",
+            FrameTemplate::Banner => "banner",
+            FrameTemplate::Bubble => "bubble",
+        }
+    }
+}
+
+/// Side of the code the caption goes on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CaptionPosition {
+    Top,
+    #[default]
+    Bottom,
+}
+
+impl CaptionPosition {
+    pub fn parse(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        match name.trim().to_lowercase().as_str() {
+            "top" | "above" => Ok(CaptionPosition::Top),
+            "bottom" | "below" => Ok(CaptionPosition::Bottom),
+            _ => Err(format!("Invalid caption position: {}. Use top or bottom", name).into()),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CaptionPosition::Top => "top",
+            CaptionPosition::Bottom => "bottom",
+        }
+    }
+}
+
+/// Horizontal alignment of the caption.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CaptionAlign {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+impl CaptionAlign {
+    pub fn parse(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        match name.trim().to_lowercase().as_str() {
+            "left" => Ok(CaptionAlign::Left),
+            "center" | "centre" => Ok(CaptionAlign::Center),
+            "right" => Ok(CaptionAlign::Right),
+            _ => Err(format!("Invalid caption alignment: {}. Use left, center or right", name).into()),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CaptionAlign::Left => "left",
+            CaptionAlign::Center => "center",
+            CaptionAlign::Right => "right",
+        }
+    }
+}
+
+/// A frame with a caption around the code.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub template: FrameTemplate,
+    pub caption: String,
+    pub position: CaptionPosition,
+    pub align: CaptionAlign,
+    /// Caption height as a share of the canvas.
+    pub text_size: f64,
+    pub color: Rgba<u8>,
+    pub text_color: Rgba<u8>,
+    /// Output size in pixels, set by [`Frame::fit`].
+    pub canvas: u32,
+}
+
+/// Placement of the frame parts on the canvas, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameLayout {
+    pub border: f64,
+    /// Caption band as top edge and height.
+    pub band: (f64, f64),
+    /// Length of the speech bubble tail.
+    pub tail: f64,
+    /// Square area left for the code, as left edge, top edge and side.
+    pub code: (f64, f64, f64),
+    /// Caption height, reduced for captions too long for the band.
+    pub text_height: f64,
+    /// Caption anchor: its left edge, center or right edge by alignment.
+    pub text_x: f64,
+}
+
+impl Frame {
+    /// Black or white, whichever reads better on `frame_color`.
+    pub fn default_text_color(frame_color: Rgba<u8>) -> Rgba<u8> {
+        let frame = raster::over_white(frame_color);
+        let black = ContrastCheck::new(image::Rgb([0, 0, 0]), frame);
+        let white = ContrastCheck::new(image::Rgb([255, 255, 255]), frame);
+        if black.ratio >= white.ratio {
+            Rgba([0, 0, 0, 255])
+        } else {
+            Rgba([255, 255, 255, 255])
+        }
+    }
+
+    /// Share of the canvas width left for the code.
+    pub fn code_share(&self) -> f64 {
+        let band = BAND * self.text_size;
+        let tail = if self.template == FrameTemplate::Bubble { TAIL * band } else { 0.0 };
+        1.0 - 2.0 * BORDER - band - tail
+    }
+
+    /// Sets the canvas for the requested sizing and returns the sizing of
+    /// the code inside the frame. A canvas size shrinks the code; a module
+    /// size grows the canvas, which may not exceed [`MAX_CANVAS_PX`].
+    pub fn fit(&mut self, sizing: Sizing, total_modules: u32) -> Result<Sizing, Box<dyn std::error::Error>> {
+        if self.code_share() < 0.5 {
+            return Err(format!(
+                "Caption size {:.0}% leaves too little room for the code; use a smaller caption size",
+                self.text_size * 100.0
+            )
+            .into());
+        }
+        match sizing {
+            Sizing::Canvas(size) if size > MAX_CANVAS_PX => {
+                Err(format!("Size {}px is too large; use at most {}px", size, MAX_CANVAS_PX).into())
+            }
+            Sizing::Canvas(size) => {
+                self.canvas = size;
+                Ok(Sizing::Canvas(self.layout().code.2.floor() as u32))
+            }
+            Sizing::ModulePx(module_px) => {
+                // The frame adds to the code, so the limit applies to the framed canvas
+                let canvas = module_px
+                    .checked_mul(total_modules)
+                    .map(|code| (f64::from(code) / self.code_share()).ceil())
+                    .filter(|&canvas| canvas <= f64::from(MAX_CANVAS_PX));
+                match canvas {
+                    Some(canvas) => {
+                        self.canvas = canvas as u32;
+                        Ok(sizing)
+                    }
+                    None => Err(format!(
+                        "Module size too large: the framed {} modules of {}px exceed the {}px canvas limit; use at most {}px",
+                        total_modules,
+                        module_px,
+                        MAX_CANVAS_PX,
+                        (f64::from(MAX_CANVAS_PX) * self.code_share() / f64::from(total_modules.max(1))).floor()
+                    )
+                    .into()),
+                }
+            }
+        }
+    }
+
+    pub fn layout(&self) -> FrameLayout {
+        let size = f64::from(self.canvas);
+        let border = BORDER * size;
+        let nominal = self.text_size * size;
+        let band_height = BAND * nominal;
+        let tail = if self.template == FrameTemplate::Bubble { TAIL * band_height } else { 0.0 };
+        let side = size * self.code_share();
+        let (band_top, code_top) = match self.position {
+            CaptionPosition::Top => (border, border + band_height + tail),
+            CaptionPosition::Bottom => (border + side + tail, border),
+        };
+        // Long captions shrink to fit between the borders
+        let padding = nominal / 2.0;
+        let available = size - 2.0 * (border + padding);
+        let natural = font::text_width(&self.caption, nominal);
+        let text_height = if natural > available { nominal * available / natural } else { nominal };
+        let text_x = match self.align {
+            CaptionAlign::Left => border + padding,
+            CaptionAlign::Center => size / 2.0,
+            CaptionAlign::Right => size - border - padding,
+        };
+        FrameLayout {
+            border,
+            band: (band_top, band_height),
+            tail,
+            code: ((size - side) / 2.0, code_top, side),
+            text_height,
+            text_x,
+        }
+    }
+
+    /// Whether the canvas point (`x`, `y`) is in the frame color.
+    fn contains(&self, layout: &FrameLayout, x: f64, y: f64) -> bool {
+        let size = f64::from(self.canvas);
+        let border = layout.border;
+        let (band_top, band_height) = layout.band;
+        match self.template {
+            FrameTemplate::Box | FrameTemplate::Banner => {
+                let radius = if self.template == FrameTemplate::Banner { CORNER * size } else { 0.0 };
+                let outer = in_rounded_rect(x, y, (0.0, 0.0, size, size), radius);
+                let inner_rect = (border, border, size - 2.0 * border, size - 2.0 * border);
+                let inner = in_rounded_rect(x, y, inner_rect, (radius - border).max(0.0));
+                let band = x >= border && x < size - border && y >= band_top && y < band_top + band_height;
+                outer && (!inner || band)
+            }
+            FrameTemplate::Bubble => {
+                let bubble = (border, band_top, size - 2.0 * border, band_height);
+                // Tail from the band edge facing the code
+                let depth = match self.position {
+                    CaptionPosition::Top => y - (band_top + band_height),
+                    CaptionPosition::Bottom => band_top - y,
+                };
+                let tail = depth >= 0.0 && depth < layout.tail && (x - size / 2.0).abs() <= layout.tail - depth;
+                in_rounded_rect(x, y, bubble, band_height * 0.3) || tail
+            }
+        }
+    }
+
+    /// Draws the frame and caption on a canvas of the light color with the
+    /// rendered `code` in its place.
+    pub fn draw(&self, code: &RgbaImage, light: Rgba<u8>) -> RgbaImage {
+        let layout = self.layout();
+        let samples = SUPERSAMPLING * SUPERSAMPLING;
+        let mut image = RgbaImage::from_fn(self.canvas, self.canvas, |px, py| {
+            let mut covered = 0;
+            for sy in 0..SUPERSAMPLING {
+                for sx in 0..SUPERSAMPLING {
+                    let offset = |s: u32| (f64::from(s) + 0.5) / f64::from(SUPERSAMPLING);
+                    covered += u32::from(self.contains(&layout, f64::from(px) + offset(sx), f64::from(py) + offset(sy)));
+                }
+            }
+            let mut pixel = light;
+            if covered > 0 {
+                let alpha = f64::from(self.color.0[3]) * f64::from(covered) / f64::from(samples);
+                pixel.blend(&Rgba([self.color.0[0], self.color.0[1], self.color.0[2], alpha.round() as u8]));
+            }
+            pixel
+        });
+
+        // The code is centered in its area, which may be slightly larger
+        let (left, top, side) = layout.code;
+        let x = (left + (side - f64::from(code.width())) / 2.0).round() as i64;
+        let y = (top + (side - f64::from(code.height())) / 2.0).round() as i64;
+        imageops::replace(&mut image, code, x, y);
+
+        let (band_top, band_height) = layout.band;
+        let width = font::text_width(&self.caption, layout.text_height);
+        let text_left = match self.align {
+            CaptionAlign::Left => layout.text_x,
+            CaptionAlign::Center => layout.text_x - width / 2.0,
+            CaptionAlign::Right => layout.text_x - width,
+        };
+        let text_top = band_top + (band_height - layout.text_height) / 2.0;
+        font::draw_text(&mut image, &self.caption, text_left, text_top, layout.text_height, self.text_color);
+        image
+    }
+
+    /// SVG path data of the frame's filled parts in canvas pixels, each to
+    /// fill with the even-odd rule: the border ring and the caption band, or
+    /// the speech bubble with its tail.
+    pub fn shape_paths(&self, layout: &FrameLayout) -> Vec<String> {
+        let size = f64::from(self.canvas);
+        let (band_top, band_height) = layout.band;
+        let border = layout.border;
+        match self.template {
+            FrameTemplate::Box | FrameTemplate::Banner => {
+                let radius = if self.template == FrameTemplate::Banner { CORNER * size } else { 0.0 };
+                let inner = size - 2.0 * border;
+                let ring = format!(
+                    "{} {}",
+                    rounded_rect_path((0.0, 0.0, size, size), radius),
+                    rounded_rect_path((border, border, inner, inner), (radius - border).max(0.0))
+                );
+                vec![ring, rounded_rect_path((border, band_top, inner, band_height), 0.0)]
+            }
+            FrameTemplate::Bubble => {
+                let (base, tip) = match self.position {
+                    CaptionPosition::Top => (band_top + band_height, band_top + band_height + layout.tail),
+                    CaptionPosition::Bottom => (band_top, band_top - layout.tail),
+                };
+                let bubble = format!(
+                    "{} M{},{} L{},{} L{},{} Z",
+                    rounded_rect_path((border, band_top, size - 2.0 * border, band_height), band_height * 0.3),
+                    short(size / 2.0 - layout.tail),
+                    short(base),
+                    short(size / 2.0),
+                    short(tip),
+                    short(size / 2.0 + layout.tail),
+                    short(base)
+                );
+                vec![bubble]
+            }
+        }
+    }
+
+    /// Wraps the SVG `code` document in a document of the frame's canvas,
+    /// `dimension` wide and high, with the frame and caption as vector shapes
+    /// and text.
+    pub fn wrap_svg(&self, code: Document, dimension: &str, light: Rgba<u8>) -> Document {
+        let layout = self.layout();
+        let size = f64::from(self.canvas);
+        let mut document = Document::new()
+            .set("viewBox", (0, 0, self.canvas, self.canvas))
+            .set("width", dimension)
+            .set("height", dimension)
+            .set("xmlns", "http://www.w3.org/2000/svg");
+        if light.0[3] > 0 {
+            document = document.add(with_opacity(Rectangle::new(), light).set("width", size).set("height", size));
+        }
+
+        let frame = self
+            .shape_paths(&layout)
+            .into_iter()
+            .fold(Group::new(), |group, d| group.add(Path::new().set("d", d).set("fill-rule", "evenodd")));
+        document = document.add(with_opacity(frame, self.color));
+
+        let (left, top, side) = layout.code;
+        document = document.add(code.set("x", short(left)).set("y", short(top)).set("width", short(side)).set("height", short(side)));
+
+        let anchor = match self.align {
+            CaptionAlign::Left => "start",
+            CaptionAlign::Center => "middle",
+            CaptionAlign::Right => "end",
+        };
+        let (band_top, band_height) = layout.band;
+        let caption = Text::new()
+            .set("x", short(layout.text_x))
+            .set("y", short(band_top + (band_height + layout.text_height) / 2.0))
+            .set("font-family", "Helvetica, Arial, sans-serif")
+            .set("font-weight", "bold")
+            .set("font-size", short(layout.text_height / CAP_HEIGHT))
+            .set("text-anchor", anchor)
+            .add(svg::node::Text::new(escape_xml(&self.caption)));
+        document.add(with_opacity(caption, self.text_color))
+    }
+}
+
+/// Sets `fill` and, for translucent colors, `fill-opacity`.
+pub fn with_opacity<T: svg::Node>(mut element: T, color: Rgba<u8>) -> T {
+    element.assign("fill", format!("#{}", raster::hex(color)));
+    if raster::is_transparent(color) {
+        element.assign("fill-opacity", format!("{:.3}", f64::from(color.0[3]) / 255.0));
+    }
+    element
+}
+
+/// Whether (`x`, `y`) lies in the rectangle (left, top, width, height) with
+/// corners rounded by `radius`.
+fn in_rounded_rect(x: f64, y: f64, (left, top, width, height): (f64, f64, f64, f64), radius: f64) -> bool {
+    if x < left || y < top || x >= left + width || y >= top + height {
+        return false;
+    }
+    let radius = radius.min(width / 2.0).min(height / 2.0);
+    let dx = (left + radius - x).max(x - (left + width - radius)).max(0.0);
+    let dy = (top + radius - y).max(y - (top + height - radius)).max(0.0);
+    dx * dx + dy * dy <= radius * radius
+}
+
+/// Closed SVG subpath of a rectangle with rounded corners, drawn clockwise.
+fn rounded_rect_path((left, top, width, height): (f64, f64, f64, f64), radius: f64) -> String {
+    let r = radius.min(width / 2.0).min(height / 2.0);
+    if r <= 0.0 {
+        return format!("M{},{} h{} v{} h-{} Z", short(left), short(top), short(width), short(height), short(width));
+    }
+    format!(
+        "M{},{} h{} a{r},{r} 0 0 1 {r},{r} v{} a{r},{r} 0 0 1 -{r},{r} h-{} a{r},{r} 0 0 1 -{r},-{r} v-{} a{r},{r} 0 0 1 {r},-{r} Z",
+        short(left + r),
+        short(top),
+        short(width - 2.0 * r),
+        short(height - 2.0 * r),
+        short(width - 2.0 * r),
+        short(height - 2.0 * r),
+        r = short(r)
+    )
+}
+
+/// Rounds SVG coordinates to hundredths of a pixel.
+fn short(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Escapes text content for SVG, which the svg crate writes verbatim.
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> Frame {
+        Frame {
+            template: FrameTemplate::Bubble,
+            caption: "SCAN ME".to_string(),
+            position: CaptionPosition::Bottom,
+            align: CaptionAlign::Center,
+            text_size: 0.1,
+            color: Rgba([0, 0, 0, 255]),
+            text_color: Rgba([255, 255, 255, 255]),
+            canvas: 0,
+        }
+    }
+
+    #[test]
+    fn module_size_grows_the_canvas() {
+        let mut frame = frame();
+        assert_eq!(frame.fit(Sizing::ModulePx(10), 29).unwrap(), Sizing::ModulePx(10));
+        assert!(frame.canvas >= 290);
+        assert!(frame.layout().code.2 >= 290.0);
+    }
+
+    #[test]
+    fn framed_canvas_respects_the_limit() {
+        let mut frame = frame();
+        // Fits without the frame, but not with it
+        let module_px = MAX_CANVAS_PX / 185;
+        assert!(frame.fit(Sizing::ModulePx(module_px), 185).is_err());
+        assert!(frame.fit(Sizing::ModulePx(u32::MAX), 185).is_err());
+        assert!(frame.fit(Sizing::Canvas(MAX_CANVAS_PX + 1), 185).is_err());
+
+        let largest = (f64::from(MAX_CANVAS_PX) * frame.code_share() / 185.0).floor() as u32;
+        frame.fit(Sizing::ModulePx(largest), 185).unwrap();
+        assert!(frame.canvas <= MAX_CANVAS_PX);
+    }
+}
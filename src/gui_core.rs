@@ -1,16 +1,97 @@
 use eframe::egui;
-use qrcode::{QrCode, EcLevel};
+use image::Rgba;
+use qr_forge::charset::{self, Charset};
+use qr_forge::contrast::ContrastCheck;
+use qr_forge::decode;
+use qr_forge::fit::{self, FitConstraint};
+use qr_forge::frame::{CaptionAlign, CaptionPosition, Frame, FrameTemplate};
+use qr_forge::halftone::{self, Halftone};
+use qr_forge::label::Label;
+use qr_forge::svg_output::{self, SvgOptions};
+use qr_forge::logo::{self, Logo};
+use qr_forge::pdf_output::{self, PageSize, PdfOptions};
+use qr_forge::plan::{Plan, PlanTarget};
+use qr_forge::raster::{self, Geometry, Sizing};
+use qr_forge::segment::Payload;
+use qr_forge::style::{EyeShape, Gradient, GradientKind, ModuleShape, Style};
+use qr_forge::symbol::{QrSymbol, VersionChoice};
+use qr_forge::units::{self, DEFAULT_DPI};
+use qrcode::EcLevel;
 use std::path::PathBuf;
 
 pub struct QrForgeGui {
     url: String,
     output_name: String,
     size: u32,
+    size_mode: SizeMode,
+    module_px: u32,
+    print_size_mm: f64,
+    dpi: u16,
+    plan_by_distance: bool,
+    plan_distance_cm: f64,
+    plan_size_mm: f64,
+    plan_summary: Vec<String>,
+    planned_size_mm: Option<f64>,
     margin: u32,
     error_correction: ErrorCorrectionLevel,
+    version_mode: VersionMode,
+    version: i16,
+    mask: Option<u8>,
+    fold_url_case: bool,
+    charset: Option<Charset>,
+    auto_fit: bool,
+    fit_by_size: bool,
+    max_version: i16,
+    fit_size_mm: f64,
+    min_module_mm: f64,
+    fit_summary: Vec<String>,
+    /// Level chosen by the last auto-fit; the user's own choice stays in
+    /// `error_correction` for when auto-fit is turned off.
+    fitted_ec_level: Option<ErrorCorrectionLevel>,
+    verify: bool,
     format: OutputFormat,
     qr_color: [u8; 3],
     background_color: [u8; 3],
+    transparent_background: bool,
+    module_shape: ModuleShape,
+    eye_frame: EyeShape,
+    eye_shape: EyeShape,
+    custom_eye_color: bool,
+    eye_color: [u8; 3],
+    gradient_mode: GradientMode,
+    gradient_angle: f64,
+    gradient_center: (f64, f64),
+    gradient_color: [u8; 3],
+    svg_paths: bool,
+    svg_crisp_edges: bool,
+    svg_precision: Option<u8>,
+    svg_omit_payload: bool,
+    /// PDF sheet as an index into [`pdf_output::SHEETS`], `None` to fit the code.
+    pdf_sheet: Option<usize>,
+    pdf_landscape: bool,
+    pdf_bleed_mm: f64,
+    pdf_omit_payload: bool,
+    logo_path: String,
+    halftone_path: String,
+    halftone_dot: u8,
+    logo_size: f64,
+    logo_padding: f64,
+    logo_clear: bool,
+    logo_plate: bool,
+    logo_plate_color: [u8; 3],
+    frame_template: Option<FrameTemplate>,
+    caption: String,
+    caption_position: CaptionPosition,
+    caption_align: CaptionAlign,
+    caption_size: f64,
+    frame_color: [u8; 3],
+    caption_color: [u8; 3],
+    label_enabled: bool,
+    label_text: String,
+    label_size: f64,
+    label_lines: u8,
+    label_font: String,
+    allow_inverted: bool,
     status_message: String,
     generated_file_path: Option<PathBuf>,
     qr_preview: Option<egui::ColorImage>,
@@ -25,6 +106,15 @@ enum ErrorCorrectionLevel {
 }
 
 impl ErrorCorrectionLevel {
+    fn from_ec_level(ec_level: EcLevel) -> Self {
+        match ec_level {
+            EcLevel::L => ErrorCorrectionLevel::Low,
+            EcLevel::M => ErrorCorrectionLevel::Medium,
+            EcLevel::Q => ErrorCorrectionLevel::Quartile,
+            EcLevel::H => ErrorCorrectionLevel::High,
+        }
+    }
+
     fn to_ec_level(self) -> EcLevel {
         match self {
             ErrorCorrectionLevel::Low => EcLevel::L,
@@ -44,12 +134,52 @@ impl ErrorCorrectionLevel {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum VersionMode {
+    Auto,
+    Exact,
+    Minimum,
+}
+
+impl VersionMode {
+    fn to_version_choice(self, version: i16) -> VersionChoice {
+        match self {
+            VersionMode::Auto => VersionChoice::Auto,
+            VersionMode::Exact => VersionChoice::Exact(version),
+            VersionMode::Minimum => VersionChoice::Minimum(version),
+        }
+    }
+
+    fn to_string(self) -> &'static str {
+        match self {
+            VersionMode::Auto => "Auto (smallest)",
+            VersionMode::Exact => "Exact",
+            VersionMode::Minimum => "Minimum",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SizeMode {
+    Canvas,
+    ModulePx,
+    Print,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum GradientMode {
+    Off,
+    Linear,
+    Radial,
+}
+
 #[derive(Clone, Copy, PartialEq)]
 enum OutputFormat {
     Png,
     Jpg,
     Bmp,
     Svg,
+    Pdf,
 }
 
 impl OutputFormat {
@@ -59,6 +189,7 @@ impl OutputFormat {
             OutputFormat::Jpg => "JPG",
             OutputFormat::Bmp => "BMP",
             OutputFormat::Svg => "SVG",
+            OutputFormat::Pdf => "PDF",
         }
     }
 
@@ -68,6 +199,7 @@ impl OutputFormat {
             OutputFormat::Jpg => "jpg",
             OutputFormat::Bmp => "bmp",
             OutputFormat::Svg => "svg",
+            OutputFormat::Pdf => "pdf",
         }
     }
 }
@@ -78,11 +210,72 @@ impl Default for QrForgeGui {
             url: "https://example.com".to_string(),
             output_name: "qrcode".to_string(),
             size: 800,
+            size_mode: SizeMode::Canvas,
+            module_px: 10,
+            print_size_mm: 25.0,
+            dpi: DEFAULT_DPI,
+            plan_by_distance: true,
+            plan_distance_cm: 50.0,
+            plan_size_mm: 100.0,
+            plan_summary: Vec::new(),
+            planned_size_mm: None,
             margin: 4,
             error_correction: ErrorCorrectionLevel::High,
+            version_mode: VersionMode::Auto,
+            version: 1,
+            mask: None,
+            fold_url_case: false,
+            charset: None,
+            auto_fit: false,
+            fit_by_size: false,
+            max_version: 10,
+            fit_size_mm: 25.0,
+            min_module_mm: 0.33,
+            fit_summary: Vec::new(),
+            fitted_ec_level: None,
+            verify: true,
             format: OutputFormat::Png,
             qr_color: [0, 0, 0],
             background_color: [255, 255, 255],
+            transparent_background: false,
+            module_shape: ModuleShape::Square,
+            eye_frame: EyeShape::Square,
+            eye_shape: EyeShape::Square,
+            custom_eye_color: false,
+            eye_color: [0, 0, 0],
+            gradient_mode: GradientMode::Off,
+            gradient_angle: 45.0,
+            gradient_center: (0.5, 0.5),
+            gradient_color: [0, 0, 160],
+            svg_paths: false,
+            svg_crisp_edges: true,
+            svg_precision: None,
+            svg_omit_payload: false,
+            pdf_sheet: None,
+            pdf_landscape: false,
+            pdf_bleed_mm: 0.0,
+            pdf_omit_payload: false,
+            logo_path: String::new(),
+            halftone_path: String::new(),
+            halftone_dot: halftone::DEFAULT_DOT_PERCENT,
+            logo_size: 20.0,
+            logo_padding: 0.5,
+            logo_clear: true,
+            logo_plate: false,
+            logo_plate_color: [255, 255, 255],
+            frame_template: None,
+            caption: "Scan me".to_string(),
+            caption_position: CaptionPosition::Bottom,
+            caption_align: CaptionAlign::Center,
+            caption_size: 6.0,
+            frame_color: [0, 0, 0],
+            caption_color: [255, 255, 255],
+            label_enabled: false,
+            label_text: String::new(),
+            label_size: 3.0,
+            label_lines: 2,
+            label_font: "monospace".to_string(),
+            allow_inverted: false,
             status_message: "Ready to generate QR codes!".to_string(),
             generated_file_path: None,
             qr_preview: None,
@@ -102,6 +295,34 @@ impl eframe::App for QrForgeGui {
                 ui.text_edit_singleline(&mut self.url);
             });
 
+            ui.checkbox(&mut self.fold_url_case, "🔠 Uppercase scheme and host (smaller code)");
+
+            // Character set / ECI
+            ui.horizontal(|ui| {
+                ui.label("🔤 Charset:");
+                let selected = match self.charset {
+                    Some(charset) => charset.name(),
+                    None => "Auto (no ECI)".to_string(),
+                };
+                egui::ComboBox::from_id_source("charset_combo")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.charset, None, "Auto (no ECI)");
+                        ui.selectable_value(&mut self.charset, Some(Charset::Utf8), "UTF-8");
+                        ui.selectable_value(&mut self.charset, Some(Charset::ShiftJis), "Shift_JIS (Kanji mode)");
+                        for part in (1..=16).filter(|p| *p != 12) {
+                            let charset = Charset::Iso8859(part);
+                            ui.selectable_value(&mut self.charset, Some(charset), charset.name());
+                        }
+                    });
+            });
+            if self.charset.is_none() && charset::needs_eci(&self.url) {
+                ui.colored_label(
+                    egui::Color32::from_rgb(200, 120, 0),
+                    "⚠️ Non-ASCII text without ECI may be mis-decoded. Choose a charset.",
+                );
+            }
+
             ui.add_space(10.0);
 
             // Output settings
@@ -111,8 +332,25 @@ impl eframe::App for QrForgeGui {
             });
 
             ui.horizontal(|ui| {
-                ui.label("📏 Size (pixels):");
-                ui.add(egui::Slider::new(&mut self.size, 100..=4000).text("px"));
+                ui.label("📏 Size:");
+                ui.radio_value(&mut self.size_mode, SizeMode::Canvas, "Output size");
+                ui.radio_value(&mut self.size_mode, SizeMode::ModulePx, "Module size");
+                ui.radio_value(&mut self.size_mode, SizeMode::Print, "Print size");
+            });
+            ui.horizontal(|ui| match self.size_mode {
+                SizeMode::Canvas => {
+                    ui.add(egui::Slider::new(&mut self.size, 100..=4000).text("px"));
+                }
+                SizeMode::ModulePx => {
+                    ui.add(egui::Slider::new(&mut self.module_px, 1..=100).text("px per module"));
+                }
+                SizeMode::Print => {
+                    ui.add(egui::DragValue::new(&mut self.print_size_mm).clamp_range(5.0..=1000.0).suffix(" mm"));
+                    ui.label("at");
+                    ui.add(egui::DragValue::new(&mut self.dpi).clamp_range(72..=2400).suffix(" dpi"));
+                    ui.label("Min module:");
+                    ui.add(egui::DragValue::new(&mut self.min_module_mm).clamp_range(0.1..=5.0).speed(0.01).suffix(" mm"));
+                }
             });
 
             ui.horizontal(|ui| {
@@ -120,6 +358,31 @@ impl eframe::App for QrForgeGui {
                 ui.add(egui::Slider::new(&mut self.margin, 0..=10).text("modules"));
             });
 
+            // Print size from scanning distance (10:1 rule)
+            egui::CollapsingHeader::new("📏 Scanning distance planner").show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.plan_by_distance, true, "Scanning distance");
+                    ui.radio_value(&mut self.plan_by_distance, false, "Available size");
+                    if self.plan_by_distance {
+                        ui.add(egui::DragValue::new(&mut self.plan_distance_cm).clamp_range(5.0..=5000.0).suffix(" cm"));
+                    } else {
+                        ui.add(egui::DragValue::new(&mut self.plan_size_mm).clamp_range(5.0..=5000.0).suffix(" mm"));
+                    }
+                    if ui.button("Plan").clicked() {
+                        self.plan_print();
+                    }
+                });
+                for line in &self.plan_summary {
+                    ui.label(line);
+                }
+                if let Some(size_mm) = self.planned_size_mm {
+                    if ui.button(format!("Use {:.1} mm as print size", size_mm)).clicked() {
+                        self.size_mode = SizeMode::Print;
+                        self.print_size_mm = size_mm;
+                    }
+                }
+            });
+
             ui.add_space(10.0);
 
             // Format selection
@@ -132,37 +395,280 @@ impl eframe::App for QrForgeGui {
                         ui.selectable_value(&mut self.format, OutputFormat::Jpg, "JPG");
                         ui.selectable_value(&mut self.format, OutputFormat::Bmp, "BMP");
                         ui.selectable_value(&mut self.format, OutputFormat::Svg, "SVG");
+                        ui.selectable_value(&mut self.format, OutputFormat::Pdf, "PDF");
                     });
             });
+            if self.format == OutputFormat::Svg {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.svg_paths, "Merge into one path");
+                    ui.checkbox(&mut self.svg_crisp_edges, "Crisp edges");
+                    let mut rounded = self.svg_precision.is_some();
+                    ui.checkbox(&mut rounded, "Round coordinates");
+                    if rounded {
+                        let mut digits = self.svg_precision.unwrap_or(2);
+                        ui.add(egui::DragValue::new(&mut digits).clamp_range(0..=6).suffix(" decimals"));
+                        self.svg_precision = Some(digits);
+                    } else {
+                        self.svg_precision = None;
+                    }
+                    ui.checkbox(&mut self.svg_omit_payload, "Omit payload from metadata");
+                });
+            }
+            if self.format == OutputFormat::Pdf {
+                ui.horizontal(|ui| {
+                    ui.label("Page:");
+                    let selected = self.pdf_sheet.map_or("Fit to code".to_string(), |i| pdf_output::SHEETS[i].0.to_uppercase());
+                    egui::ComboBox::from_id_source("pdf_page_combo")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.pdf_sheet, None, "Fit to code");
+                            for (i, (name, _, _)) in pdf_output::SHEETS.iter().enumerate() {
+                                ui.selectable_value(&mut self.pdf_sheet, Some(i), name.to_uppercase());
+                            }
+                        });
+                    ui.add_enabled(self.pdf_sheet.is_some(), egui::Checkbox::new(&mut self.pdf_landscape, "Landscape"));
+                    ui.label("Bleed:");
+                    ui.add(egui::DragValue::new(&mut self.pdf_bleed_mm).clamp_range(0.0..=25.0).speed(0.1).suffix(" mm"));
+                    ui.checkbox(&mut self.pdf_omit_payload, "Omit payload from title");
+                });
+            }
 
             // Error correction
             ui.horizontal(|ui| {
                 ui.label("🔧 Error Correction:");
-                egui::ComboBox::from_id_source("error_correction_combo")
-                    .selected_text(self.error_correction.to_string())
+                ui.add_enabled_ui(!self.auto_fit, |ui| {
+                    let selected = match (self.auto_fit, self.fitted_ec_level) {
+                        (true, Some(level)) => level.to_string(),
+                        (true, None) => "Auto-fit",
+                        (false, _) => self.error_correction.to_string(),
+                    };
+                    egui::ComboBox::from_id_source("error_correction_combo")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.error_correction, ErrorCorrectionLevel::Low, "L (Low)");
+                            ui.selectable_value(&mut self.error_correction, ErrorCorrectionLevel::Medium, "M (Medium)");
+                            ui.selectable_value(&mut self.error_correction, ErrorCorrectionLevel::Quartile, "Q (Quartile)");
+                            ui.selectable_value(&mut self.error_correction, ErrorCorrectionLevel::High, "H (High)");
+                        });
+                });
+                ui.checkbox(&mut self.auto_fit, "Auto-fit");
+            });
+
+            // Auto-fit budget
+            if self.auto_fit {
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.fit_by_size, false, "Max version");
+                    ui.radio_value(&mut self.fit_by_size, true, "Printed size");
+                });
+                if self.fit_by_size {
+                    ui.horizontal(|ui| {
+                        ui.label("Size:");
+                        ui.add(egui::DragValue::new(&mut self.fit_size_mm).clamp_range(5.0..=1000.0).suffix(" mm"));
+                        ui.label("Min module:");
+                        ui.add(egui::DragValue::new(&mut self.min_module_mm).clamp_range(0.1..=5.0).speed(0.01).suffix(" mm"));
+                    });
+                } else {
+                    ui.add(egui::Slider::new(&mut self.max_version, 1..=40).text("max version"));
+                }
+                for line in &self.fit_summary {
+                    ui.label(line);
+                }
+            }
+
+            // Version selection; auto-fit picks the smallest version itself,
+            // as --fit conflicts with --qr-version and --min-version
+            ui.horizontal(|ui| {
+                ui.label("🔢 Version:");
+                ui.add_enabled_ui(!self.auto_fit, |ui| {
+                    let selected = if self.auto_fit { VersionMode::Auto } else { self.version_mode };
+                    egui::ComboBox::from_id_source("version_mode_combo")
+                        .selected_text(selected.to_string())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.version_mode, VersionMode::Auto, "Auto (smallest)");
+                            ui.selectable_value(&mut self.version_mode, VersionMode::Exact, "Exact");
+                            ui.selectable_value(&mut self.version_mode, VersionMode::Minimum, "Minimum");
+                        });
+                    if selected != VersionMode::Auto {
+                        ui.add(egui::Slider::new(&mut self.version, 1..=40));
+                    }
+                });
+            });
+
+            // Mask pattern
+            ui.horizontal(|ui| {
+                ui.label("🎭 Mask pattern:");
+                let selected = match self.mask {
+                    Some(mask) => mask.to_string(),
+                    None => "Auto (lowest penalty)".to_string(),
+                };
+                egui::ComboBox::from_id_source("mask_combo")
+                    .selected_text(selected)
                     .show_ui(ui, |ui| {
-                        ui.selectable_value(&mut self.error_correction, ErrorCorrectionLevel::Low, "L (Low)");
-                        ui.selectable_value(&mut self.error_correction, ErrorCorrectionLevel::Medium, "M (Medium)");
-                        ui.selectable_value(&mut self.error_correction, ErrorCorrectionLevel::Quartile, "Q (Quartile)");
-                        ui.selectable_value(&mut self.error_correction, ErrorCorrectionLevel::High, "H (High)");
+                        ui.selectable_value(&mut self.mask, None, "Auto (lowest penalty)");
+                        for mask in 0..8u8 {
+                            ui.selectable_value(&mut self.mask, Some(mask), mask.to_string());
+                        }
                     });
             });
 
             ui.add_space(10.0);
 
-            // Color settings (only for SVG)
-            if self.format == OutputFormat::Svg {
-                ui.label("🎨 Colors (SVG only):");
+            // Color settings
+            ui.label("🎨 Colors:");
+            ui.horizontal(|ui| {
+                ui.label("QR Color:");
+                ui.color_edit_button_srgb(&mut self.qr_color);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Background:");
+                ui.add_enabled_ui(!self.transparent_background, |ui| {
+                    ui.color_edit_button_srgb(&mut self.background_color);
+                });
+                // JPG and BMP have no alpha channel
+                let supports_alpha = matches!(self.format, OutputFormat::Png | OutputFormat::Svg);
+                ui.add_enabled(supports_alpha, egui::Checkbox::new(&mut self.transparent_background, "Transparent"));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Module shape:");
+                egui::ComboBox::from_id_source("module_shape_combo")
+                    .selected_text(self.module_shape.name())
+                    .show_ui(ui, |ui| {
+                        for shape in ModuleShape::ALL {
+                            ui.selectable_value(&mut self.module_shape, shape, shape.name());
+                        }
+                    });
+            });
+            ui.horizontal(|ui| {
+                ui.label("Eyes:");
+                egui::ComboBox::from_id_source("eye_frame_combo")
+                    .selected_text(format!("{} ring", self.eye_frame.name()))
+                    .show_ui(ui, |ui| {
+                        for shape in EyeShape::ALL {
+                            ui.selectable_value(&mut self.eye_frame, shape, shape.name());
+                        }
+                    });
+                egui::ComboBox::from_id_source("eye_shape_combo")
+                    .selected_text(format!("{} eye", self.eye_shape.name()))
+                    .show_ui(ui, |ui| {
+                        for shape in EyeShape::ALL {
+                            ui.selectable_value(&mut self.eye_shape, shape, shape.name());
+                        }
+                    });
+                ui.checkbox(&mut self.custom_eye_color, "Own color");
+                if self.custom_eye_color {
+                    ui.color_edit_button_srgb(&mut self.eye_color);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Gradient:");
+                ui.radio_value(&mut self.gradient_mode, GradientMode::Off, "None");
+                ui.radio_value(&mut self.gradient_mode, GradientMode::Linear, "Linear");
+                ui.radio_value(&mut self.gradient_mode, GradientMode::Radial, "Radial");
+            });
+            if self.gradient_mode != GradientMode::Off {
                 ui.horizontal(|ui| {
-                    ui.label("QR Color:");
-                    ui.color_edit_button_srgb(&mut self.qr_color);
+                    match self.gradient_mode {
+                        GradientMode::Linear => {
+                            ui.add(egui::Slider::new(&mut self.gradient_angle, 0.0..=360.0).text("°"));
+                        }
+                        _ => {
+                            ui.label("Center:");
+                            ui.add(egui::DragValue::new(&mut self.gradient_center.0).clamp_range(0.0..=1.0).speed(0.01));
+                            ui.add(egui::DragValue::new(&mut self.gradient_center.1).clamp_range(0.0..=1.0).speed(0.01));
+                        }
+                    }
+                    ui.label("to");
+                    ui.color_edit_button_srgb(&mut self.gradient_color);
                 });
+            }
+            ui.horizontal(|ui| {
+                ui.label("Logo file:");
+                ui.text_edit_singleline(&mut self.logo_path);
+            });
+            if !self.logo_path.trim().is_empty() {
                 ui.horizontal(|ui| {
-                    ui.label("Background:");
-                    ui.color_edit_button_srgb(&mut self.background_color);
+                    ui.add(egui::Slider::new(&mut self.logo_size, 5.0..=40.0).text("% width"));
+                    ui.add(egui::DragValue::new(&mut self.logo_padding).clamp_range(0.0..=4.0).speed(0.1).suffix(" modules padding"));
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.logo_clear, "Clear modules");
+                    ui.checkbox(&mut self.logo_plate, "Plate");
+                    if self.logo_plate {
+                        ui.color_edit_button_srgb(&mut self.logo_plate_color);
+                    }
                 });
-                ui.add_space(10.0);
             }
+            ui.horizontal(|ui| {
+                ui.label("Halftone photo:");
+                ui.text_edit_singleline(&mut self.halftone_path);
+                if !self.halftone_path.trim().is_empty() {
+                    ui.add(egui::Slider::new(&mut self.halftone_dot, 20..=100).text("% dot"));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Frame:");
+                egui::ComboBox::from_id_source("frame_combo")
+                    .selected_text(self.frame_template.map_or("none", FrameTemplate::name))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.frame_template, None, "none");
+                        for template in FrameTemplate::ALL {
+                            ui.selectable_value(&mut self.frame_template, Some(template), template.name());
+                        }
+                    });
+                if self.frame_template.is_some() {
+                    ui.color_edit_button_srgb(&mut self.frame_color);
+                    ui.label("Caption:");
+                    ui.text_edit_singleline(&mut self.caption);
+                    ui.color_edit_button_srgb(&mut self.caption_color);
+                }
+            });
+            if self.frame_template.is_some() {
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.caption_position, CaptionPosition::Top, "Top");
+                    ui.radio_value(&mut self.caption_position, CaptionPosition::Bottom, "Bottom");
+                    ui.separator();
+                    ui.radio_value(&mut self.caption_align, CaptionAlign::Left, "Left");
+                    ui.radio_value(&mut self.caption_align, CaptionAlign::Center, "Center");
+                    ui.radio_value(&mut self.caption_align, CaptionAlign::Right, "Right");
+                    ui.separator();
+                    ui.add(egui::Slider::new(&mut self.caption_size, 3.0..=12.0).text("% text"));
+                });
+            }
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.label_enabled, "Label");
+                if self.label_enabled {
+                    ui.add(egui::TextEdit::singleline(&mut self.label_text).hint_text("encoded value"));
+                    ui.add(egui::Slider::new(&mut self.label_size, 1.0..=10.0).text("% text"));
+                }
+            });
+            if self.label_enabled {
+                ui.horizontal(|ui| {
+                    ui.add(egui::Slider::new(&mut self.label_lines, 1..=6).text("max lines"));
+                    ui.label("SVG font:");
+                    ui.text_edit_singleline(&mut self.label_font);
+                });
+            }
+            ui.checkbox(&mut self.allow_inverted, "Allow inverted (light on dark)");
+
+            // Live contrast feedback
+            let check = self.contrast_check();
+            match check.validate(self.allow_inverted) {
+                Ok(warnings) if warnings.is_empty() => {
+                    ui.label(format!("✅ Contrast {:.1}:1", check.ratio));
+                }
+                Ok(warnings) => {
+                    for warning in warnings {
+                        ui.colored_label(egui::Color32::from_rgb(230, 150, 0), format!("⚠️ {}", warning));
+                    }
+                }
+                Err(e) => {
+                    ui.colored_label(egui::Color32::from_rgb(220, 50, 50), format!("❌ {}", e));
+                }
+            }
+            ui.add_space(10.0);
+
+            ui.checkbox(&mut self.verify, "🔎 Verify that the generated code reads back correctly");
+            ui.add_space(10.0);
 
             // Generate button
             if ui.add_sized([200.0, 40.0], egui::Button::new("🚀 Generate QR Code")).clicked() {
@@ -213,17 +719,72 @@ impl QrForgeGui {
     }
 
     fn generate_qr_code(&mut self) {
-        // Validate URL
-        let url = match self.validate_url() {
-            Ok(url) => url,
+        // Refuse colors scanners cannot read
+        if let Err(e) = self.contrast_check().validate(self.allow_inverted) {
+            self.status_message = format!("❌ {}", e);
+            return;
+        }
+        if let Some(eye_color) = self.style().eye_color {
+            let eye_check = ContrastCheck::new(raster::over_white(eye_color), raster::over_white(self.background_rgba()));
+            if let Err(e) = eye_check.validate(self.allow_inverted) {
+                self.status_message = format!("❌ Eye color: {}", e);
+                return;
+            }
+        }
+        if self.transparent_background && !matches!(self.format, OutputFormat::Png | OutputFormat::Svg) {
+            self.status_message = format!(
+                "❌ {} output does not support transparency. Use PNG or SVG",
+                self.format.extension().to_uppercase()
+            );
+            return;
+        }
+
+        // Validate URL and build the payload
+        let payload = match self.build_payload() {
+            Ok(payload) => payload,
             Err(e) => {
-                self.status_message = format!("❌ Error: {}", e);
+                self.status_message = format!("❌ {}", e);
                 return;
             }
         };
 
-        // Generate QR code
-        let qr_code = match QrCode::with_error_correction_level(&url, self.error_correction.to_ec_level()) {
+        // Auto-fit picks the strongest error correction level within the budget
+        self.fit_summary.clear();
+        self.fitted_ec_level = None;
+        if self.auto_fit {
+            let constraint = if self.fit_by_size {
+                FitConstraint::PhysicalSize {
+                    target_mm: self.fit_size_mm,
+                    min_module_mm: self.min_module_mm,
+                    margin: self.margin,
+                }
+            } else {
+                FitConstraint::MaxVersion(self.max_version)
+            };
+            let report = match fit::fit(&payload, constraint) {
+                Ok(report) => report,
+                Err(e) => {
+                    self.status_message = format!("❌ Auto-fit error: {}", e);
+                    return;
+                }
+            };
+            self.fit_summary = report.candidate_lines();
+            self.fit_summary.push(report.explanation());
+            match report.chosen {
+                Some(ec_level) => self.fitted_ec_level = Some(ErrorCorrectionLevel::from_ec_level(ec_level)),
+                None => {
+                    self.status_message = format!("❌ {}", report.explanation());
+                    return;
+                }
+            }
+        }
+
+        let qr_code = match QrSymbol::encode_payload(
+            &payload,
+            self.ec_level(),
+            self.version_choice(),
+            self.mask,
+        ) {
             Ok(qr) => qr,
             Err(e) => {
                 self.status_message = format!("❌ QR generation error: {}", e);
@@ -231,17 +792,86 @@ impl QrForgeGui {
             }
         };
 
+        // The logo may only destroy part of the error correction
+        let logo = match self.logo() {
+            Ok(logo) => logo,
+            Err(e) => {
+                self.status_message = format!("❌ {}", e);
+                return;
+            }
+        };
+        let logo_damage = match logo.as_ref().map(|logo| logo.damage(&qr_code)).transpose() {
+            Ok(damage) => damage,
+            Err(e) => {
+                self.status_message = format!("❌ {}", e);
+                return;
+            }
+        };
+        if let Some(Err(e)) = logo_damage.as_ref().map(|damage| damage.check(logo::DEFAULT_BUDGET_PERCENT)) {
+            self.status_message = format!("❌ {}", e);
+            return;
+        }
+        let frame = match self.frame(&qr_code) {
+            Ok(frame) => frame,
+            Err(e) => {
+                self.status_message = format!("❌ {}", e);
+                return;
+            }
+        };
+        let halftone = match self.halftone(&qr_code) {
+            Ok(halftone) => halftone,
+            Err(e) => {
+                self.status_message = format!("❌ {}", e);
+                return;
+            }
+        };
+        let label = self.label();
+        let style = Style {
+            logo: logo.as_ref(),
+            frame: frame.as_ref(),
+            label: label.as_ref(),
+            halftone: halftone.as_ref(),
+            ..self.style()
+        };
+
         // Generate preview
-        self.generate_preview(&qr_code);
+        self.generate_preview(&qr_code, &style);
+
+        // Decode the rendered raster before anything is written; halftone
+        // codes are always checked
+        if self.verify || halftone.is_some() {
+            if let Err(e) = self.verify_render(&qr_code, &style, &payload) {
+                self.status_message = format!("❌ {}", e);
+                return;
+            }
+        }
 
         // Save file
         let filename = format!("{}.{}", self.output_name, self.format.extension());
+        let mut symbol_info = format!(
+            "version {:?}, mask {}, penalty {}",
+            qr_code.version(),
+            qr_code.mask(),
+            qr_code.penalty_score()
+        );
+        if self.verify {
+            symbol_info.push_str(", verified");
+        }
+        if let Some(damage) = &logo_damage {
+            symbol_info.push_str(&format!(", logo uses {:.0}% of the error correction", damage.budget_used() * 100.0));
+        }
+        if let Some(module_mm) = self.printed_module_mm(&qr_code) {
+            symbol_info.push_str(&format!(", printed module {:.3} mm", module_mm));
+            if module_mm < self.min_module_mm {
+                symbol_info.push_str(&format!(" ⚠️ below the {:.2} mm minimum", self.min_module_mm));
+            }
+        }
         
         match self.format {
             OutputFormat::Svg => {
-                match self.save_svg(&qr_code, &filename) {
+                match self.save_svg(&qr_code, &style, &filename) {
                     Ok(_) => {
-                        self.status_message = format!("✅ SVG generated successfully: {}", filename);
+                        self.status_message = format!("✅ SVG generated successfully: {} ({})", filename, symbol_info);
                         self.generated_file_path = Some(PathBuf::from(&filename));
                     }
                     Err(e) => {
@@ -249,10 +879,21 @@ impl QrForgeGui {
                     }
                 }
             }
+            OutputFormat::Pdf => {
+                match self.save_pdf(&qr_code, &style, &filename) {
+                    Ok(_) => {
+                        self.status_message = format!("✅ PDF generated successfully: {} ({})", filename, symbol_info);
+                        self.generated_file_path = Some(PathBuf::from(&filename));
+                    }
+                    Err(e) => {
+                        self.status_message = format!("❌ PDF save error: {}", e);
+                    }
+                }
+            }
             _ => {
-                match self.save_bitmap(&qr_code, &filename) {
+                match self.save_bitmap(&qr_code, &style, &filename) {
                     Ok(_) => {
-                        self.status_message = format!("✅ {} generated successfully: {}", self.format.to_string(), filename);
+                        self.status_message = format!("✅ {} generated successfully: {} ({})", self.format.to_string(), filename, symbol_info);
                         self.generated_file_path = Some(PathBuf::from(&filename));
                     }
                     Err(e) => {
@@ -263,6 +904,73 @@ impl QrForgeGui {
         }
     }
 
+    /// Payload for the current URL, case folding and charset settings.
+    fn build_payload(&self) -> Result<Payload, String> {
+        let url = match self.validate_url() {
+            Ok(url) if self.fold_url_case => qr_forge::segment::fold_url_case(&url),
+            Ok(url) => url,
+            Err(e) => return Err(format!("Error: {}", e)),
+        };
+        match self.charset {
+            Some(charset) => charset.encode(&url).map_err(|e| format!("Charset error: {}", e)),
+            None => Ok(Payload::bytes(url.as_bytes())),
+        }
+    }
+
+    /// Error correction level to encode with: the auto-fit result while
+    /// auto-fit is on, otherwise the selected level.
+    fn ec_level(&self) -> EcLevel {
+        match (self.auto_fit, self.fitted_ec_level) {
+            (true, Some(level)) => level.to_ec_level(),
+            _ => self.error_correction.to_ec_level(),
+        }
+    }
+
+    /// Version policy to encode with; auto-fit always takes the smallest.
+    fn version_choice(&self) -> VersionChoice {
+        if self.auto_fit {
+            VersionChoice::Auto
+        } else {
+            self.version_mode.to_version_choice(self.version)
+        }
+    }
+
+    /// Sizes the code for the planner target using the version the current
+    /// settings encode to.
+    fn plan_print(&mut self) {
+        self.plan_summary.clear();
+        self.planned_size_mm = None;
+        let qr_code = self.build_payload().and_then(|payload| {
+            QrSymbol::encode_payload(
+                &payload,
+                self.ec_level(),
+                self.version_choice(),
+                self.mask,
+            )
+            .map_err(|e| format!("QR generation error: {}", e))
+        });
+        let qr_code = match qr_code {
+            Ok(qr_code) => qr_code,
+            Err(e) => {
+                self.plan_summary.push(format!("❌ {}", e));
+                return;
+            }
+        };
+        let target = if self.plan_by_distance {
+            PlanTarget::Distance(self.plan_distance_cm * 10.0)
+        } else {
+            PlanTarget::Size(self.plan_size_mm)
+        };
+        let plan = Plan::new(target, qr_code.width() as u32, self.margin, self.min_module_mm);
+        self.plan_summary.push(format!(
+            "Version {:?}, error correction {:?}",
+            qr_code.version(),
+            qr_code.error_correction_level()
+        ));
+        self.plan_summary.extend(plan.lines(self.min_module_mm));
+        self.planned_size_mm = Some(plan.size_mm);
+    }
+
     fn validate_url(&self) -> Result<String, String> {
         let url = if self.url.starts_with("http://") || self.url.starts_with("https://") {
             self.url.clone()
@@ -277,74 +985,277 @@ impl QrForgeGui {
         Ok(url)
     }
 
-    fn generate_preview(&mut self, qr_code: &QrCode) {
-        // Generate a small preview image
-        let preview_size = 200;
-        let qr_width = qr_code.width();
-        let total_modules = qr_width + (self.margin as usize * 2);
-        let module_size = preview_size / total_modules;
-        let actual_size = module_size * total_modules;
-
-        let mut pixels = vec![255u8; actual_size * actual_size * 3]; // RGB
-
-        // Draw QR code
-        for y in 0..qr_width {
-            for x in 0..qr_width {
-                if qr_code[(x, y)] == qrcode::Color::Dark {
-                    let start_x = (x + self.margin as usize) * module_size;
-                    let start_y = (y + self.margin as usize) * module_size;
-
-                    for dy in 0..module_size {
-                        for dx in 0..module_size {
-                            let px = start_x + dx;
-                            let py = start_y + dy;
-                            
-                            if px < actual_size && py < actual_size {
-                                let idx = (py * actual_size + px) * 3;
-                                if idx + 2 < pixels.len() {
-                                    pixels[idx] = 0;     // R
-                                    pixels[idx + 1] = 0; // G
-                                    pixels[idx + 2] = 0; // B
-                                }
-                            }
-                        }
-                    }
-                }
+    /// Style of the current settings; the logo is loaded separately.
+    fn style(&self) -> Style<'static> {
+        Style {
+            shape: self.module_shape,
+            eye_frame: self.eye_frame,
+            eye: self.eye_shape,
+            eye_color: self.custom_eye_color.then(|| {
+                let [r, g, b] = self.eye_color;
+                Rgba([r, g, b, 255])
+            }),
+            gradient: self.gradient(),
+            logo: None,
+            frame: None,
+            label: None,
+            halftone: None,
+        }
+    }
+
+    /// The logo file, if one is set, loaded with the current placement.
+    fn logo(&self) -> Result<Option<Logo>, Box<dyn std::error::Error>> {
+        let path = self.logo_path.trim();
+        if path.is_empty() {
+            return Ok(None);
+        }
+        let mut logo = Logo::load(path, self.logo_size, self.logo_padding)?;
+        if self.format == OutputFormat::Pdf {
+            return Err("Logos are not supported in PDF output; use SVG or PNG".into());
+        }
+        if logo.is_svg() && self.format != OutputFormat::Svg {
+            return Err("SVG logos can only be embedded in SVG output; use a PNG logo".into());
+        }
+        logo.clear = self.logo_clear;
+        logo.plate = self.logo_plate.then(|| {
+            let [r, g, b] = self.logo_plate_color;
+            Rgba([r, g, b, 255])
+        });
+        Ok(Some(logo))
+    }
+
+    /// The halftone photo, if one is set; bitmap output only.
+    fn halftone(&self, qr_code: &QrSymbol) -> Result<Option<Halftone>, Box<dyn std::error::Error>> {
+        let path = self.halftone_path.trim();
+        if path.is_empty() {
+            return Ok(None);
+        }
+        if matches!(self.format, OutputFormat::Svg | OutputFormat::Pdf) {
+            return Err("Halftone codes are bitmaps; use PNG, JPG or BMP output".into());
+        }
+        let module_px = self.geometry(qr_code)?.module_px;
+        if module_px < halftone::MIN_MODULE_PX {
+            return Err(format!("Halftone needs at least {} pixels per module, got {}", halftone::MIN_MODULE_PX, module_px).into());
+        }
+        Ok(Some(Halftone::load(path, self.halftone_dot)?))
+    }
+
+    /// The URL as encoded, after validation and case folding.
+    fn encoded_url(&self) -> String {
+        match self.validate_url() {
+            Ok(url) if self.fold_url_case => qr_forge::segment::fold_url_case(&url),
+            Ok(url) => url,
+            Err(_) => self.url.clone(),
+        }
+    }
+
+    /// The label, showing the encoded value unless a text is entered.
+    fn label(&self) -> Option<Label> {
+        if !self.label_enabled {
+            return None;
+        }
+        let text = if self.label_text.trim().is_empty() {
+            self.encoded_url()
+        } else {
+            self.label_text.clone()
+        };
+        Some(Label {
+            text,
+            text_size: self.label_size / 100.0,
+            max_lines: usize::from(self.label_lines),
+            font: self.label_font.clone(),
+            color: self.qr_rgba(),
+        })
+    }
+
+    /// The frame, if one is selected, fitted to the output size.
+    fn frame(&self, qr_code: &QrSymbol) -> Result<Option<Frame>, Box<dyn std::error::Error>> {
+        let Some(template) = self.frame_template else {
+            return Ok(None);
+        };
+        let rgba = |[r, g, b]: [u8; 3]| Rgba([r, g, b, 255]);
+        let mut frame = Frame {
+            template,
+            caption: self.caption.clone(),
+            position: self.caption_position,
+            align: self.caption_align,
+            text_size: self.caption_size / 100.0,
+            color: rgba(self.frame_color),
+            text_color: rgba(self.caption_color),
+            canvas: 0,
+        };
+        frame.fit(self.sizing(), qr_code.width() as u32 + 2 * self.margin)?;
+        Ok(Some(frame))
+    }
+
+    fn gradient(&self) -> Option<Gradient> {
+        let kind = match self.gradient_mode {
+            GradientMode::Off => return None,
+            GradientMode::Linear => GradientKind::Linear { angle: self.gradient_angle },
+            GradientMode::Radial => GradientKind::Radial { center: self.gradient_center },
+        };
+        let [r, g, b] = self.gradient_color;
+        Some(Gradient { kind, end: Rgba([r, g, b, 255]) })
+    }
+
+    fn qr_rgba(&self) -> Rgba<u8> {
+        let [r, g, b] = self.qr_color;
+        Rgba([r, g, b, 255])
+    }
+
+    fn background_rgba(&self) -> Rgba<u8> {
+        let [r, g, b] = self.background_color;
+        Rgba([r, g, b, if self.transparent_background { 0 } else { 255 }])
+    }
+
+    /// Contrast as printed on white paper, of the weakest gradient stop.
+    fn contrast_check(&self) -> ContrastCheck {
+        let stops: Vec<_> = std::iter::once(self.qr_rgba())
+            .chain(self.gradient().map(|g| g.end))
+            .map(raster::over_white)
+            .collect();
+        ContrastCheck::weakest(&stops, raster::over_white(self.background_rgba()))
+    }
+
+    fn sizing(&self) -> Sizing {
+        match self.size_mode {
+            SizeMode::Canvas => Sizing::Canvas(self.size),
+            SizeMode::ModulePx => Sizing::ModulePx(self.module_px),
+            SizeMode::Print => Sizing::Canvas(units::mm_to_px(self.print_size_mm, self.dpi)),
+        }
+    }
+
+    /// Layout of the code itself, inside the frame if there is one.
+    fn geometry(&self, qr_code: &QrSymbol) -> Result<Geometry, Box<dyn std::error::Error>> {
+        let sizing = match self.frame(qr_code)? {
+            Some(mut frame) => frame.fit(self.sizing(), qr_code.width() as u32 + 2 * self.margin)?,
+            None => self.sizing(),
+        };
+        Geometry::new(qr_code.width() as u32, self.margin, sizing)
+    }
+
+    fn print_dpi(&self) -> Option<u16> {
+        (self.size_mode == SizeMode::Print).then_some(self.dpi)
+    }
+
+    fn print_size_mm(&self) -> Option<f64> {
+        (self.size_mode == SizeMode::Print).then_some(self.print_size_mm)
+    }
+
+    /// Printed module size in Print size mode; SVG and PDF modules scale
+    /// fractionally.
+    fn printed_module_mm(&self, qr_code: &QrSymbol) -> Option<f64> {
+        let geometry = self.geometry(qr_code).ok()?;
+        let dpi = self.print_dpi()?;
+        let code_share = self.frame(qr_code).ok()?.as_ref().map_or(1.0, Frame::code_share);
+        Some(match self.format {
+            OutputFormat::Svg | OutputFormat::Pdf => self.print_size_mm * code_share / f64::from(geometry.total_modules()),
+            _ => units::px_to_mm(f64::from(geometry.module_px), dpi),
+        })
+    }
+
+    fn generate_preview(&mut self, qr_code: &QrSymbol, style: &Style) {
+        // Generate a small preview image, the code shrunk to make room for a frame
+        let mut preview_size = 200;
+        let total_modules = qr_code.width() as u32 + self.margin * 2;
+        let mut frame = style.frame.cloned();
+        if let Some(frame) = &mut frame {
+            if let Ok(Sizing::Canvas(code_size)) = frame.fit(Sizing::Canvas(preview_size), total_modules) {
+                preview_size = code_size;
             }
         }
+        let module_size = (preview_size / total_modules).max(1);
+        let geometry = Geometry {
+            width: qr_code.width() as u32,
+            margin: self.margin,
+            module_px: module_size,
+            size: module_size * total_modules,
+        };
+        let mut image = raster::render(
+            qr_code,
+            &geometry,
+            style,
+            self.qr_rgba(),
+            self.background_rgba(),
+        );
+        if let Some(frame) = &frame {
+            image = frame.draw(&image, self.background_rgba());
+        }
+        if let Some(label) = style.label {
+            image = label.draw(&image, self.background_rgba());
+        }
 
-        self.qr_preview = Some(egui::ColorImage::from_rgb(
-            [actual_size, actual_size],
-            &pixels,
+        self.qr_preview = Some(egui::ColorImage::from_rgba_unmultiplied(
+            [image.width() as usize, image.height() as usize],
+            image.as_raw(),
         ));
     }
 
-    fn save_svg(&self, qr_code: &QrCode, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
-        crate::generate_svg_qr(
-            qr_code,
-            filename,
-            self.size,
-            self.margin,
-            &format!("{:02x}{:02x}{:02x}", self.qr_color[0], self.qr_color[1], self.qr_color[2]),
-            &format!("{:02x}{:02x}{:02x}", self.background_color[0], self.background_color[1], self.background_color[2]),
-        )
+    fn verify_render(&self, qr_code: &QrSymbol, style: &Style, payload: &Payload) -> Result<(), Box<dyn std::error::Error>> {
+        let image = raster::compose(qr_code, &self.geometry(qr_code)?, style, self.qr_rgba(), self.background_rgba());
+        decode::verify(&image::DynamicImage::ImageRgba8(image), &payload.data)?;
+        Ok(())
     }
 
-    fn save_bitmap(&self, qr_code: &QrCode, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let image = crate::generate_high_quality_image(qr_code, self.size, self.margin)?;
+    fn save_svg(&self, qr_code: &QrSymbol, style: &Style, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let url = self.encoded_url();
+        let settings = [
+            ("margin", self.margin.to_string()),
+            ("color", raster::hex(self.qr_rgba())),
+            ("background-color", raster::hex(self.background_rgba())),
+            ("module-shape", style.shape.name().to_string()),
+            ("eye-frame-shape", style.eye_frame.name().to_string()),
+            ("eye-shape", style.eye.name().to_string()),
+        ];
+        let options = SvgOptions {
+            physical_mm: self.print_size_mm(),
+            merge_paths: self.svg_paths,
+            crisp_edges: self.svg_crisp_edges,
+            precision: self.svg_precision.map(usize::from),
+            payload: (!self.svg_omit_payload).then_some(url.as_str()),
+            settings: &settings,
+        };
+        let document = svg_output::document(qr_code, &self.geometry(qr_code)?, style, self.qr_rgba(), self.background_rgba(), &options);
+        std::fs::write(filename, document.to_string())?;
+        Ok(())
+    }
+
+    /// The PDF page: a sheet, turned for landscape, or fitted to the code.
+    fn pdf_page(&self) -> PageSize {
+        match self.pdf_sheet.map(|i| pdf_output::SHEETS[i]) {
+            Some((_, width_mm, height_mm)) if self.pdf_landscape => PageSize::Sheet { width_mm: height_mm, height_mm: width_mm },
+            Some((_, width_mm, height_mm)) => PageSize::Sheet { width_mm, height_mm },
+            None => PageSize::Fit,
+        }
+    }
+
+    fn save_pdf(&self, qr_code: &QrSymbol, style: &Style, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let geometry = self.geometry(qr_code)?;
+        // Outside Print size mode the pixel size is printed at the set DPI
+        let canvas = style.frame.map_or(geometry.size, |frame| frame.canvas);
+        let url = self.encoded_url();
+        let options = PdfOptions {
+            size_mm: self.print_size_mm().unwrap_or_else(|| units::px_to_mm(f64::from(canvas), self.dpi)),
+            page: self.pdf_page(),
+            bleed_mm: self.pdf_bleed_mm,
+            payload: (!self.pdf_omit_payload).then_some(url.as_str()),
+        };
+        let pdf = pdf_output::document(qr_code, &geometry, style, self.qr_rgba(), self.background_rgba(), &options)?;
+        std::fs::write(filename, pdf)?;
+        Ok(())
+    }
+
+    fn save_bitmap(&self, qr_code: &QrSymbol, style: &Style, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let image = raster::compose(qr_code, &self.geometry(qr_code)?, style, self.qr_rgba(), self.background_rgba());
         
-        match self.format {
-            OutputFormat::Png => {
-                image.save_with_format(filename, image::ImageFormat::Png)?;
-            }
-            OutputFormat::Jpg => {
-                image.save_with_format(filename, image::ImageFormat::Jpeg)?;
-            }
-            OutputFormat::Bmp => {
-                image.save_with_format(filename, image::ImageFormat::Bmp)?;
-            }
+        let format = match self.format {
+            OutputFormat::Png => image::ImageFormat::Png,
+            OutputFormat::Jpg => image::ImageFormat::Jpeg,
+            OutputFormat::Bmp => image::ImageFormat::Bmp,
             _ => unreachable!(),
-        }
+        };
+        let image = raster::for_format(image, format)?;
+        std::fs::write(filename, raster::encode(&image, format, self.print_dpi())?)?;
 
         Ok(())
     }
@@ -0,0 +1,89 @@
+//! Halftone QR codes: a photo rendered behind the symbol, with each data
+//! module shrunk to a center dot and the rest of the module dithered from the
+//! photo, while function patterns stay solid.
+
+use crate::contrast::relative_luminance;
+use crate::raster::Geometry;
+use crate::symbol::QrSymbol;
+use image::imageops::{self, FilterType};
+use image::{Pixel, Rgb, Rgba, RgbaImage};
+use std::error::Error;
+
+/// Default center dot width as a percentage of the module.
+pub const DEFAULT_DOT_PERCENT: u8 = 40;
+/// Smallest module size that leaves room for a dot and a dithered ring.
+pub const MIN_MODULE_PX: u32 = 6;
+/// How far dithered photo pixels are pulled toward the dark or light color,
+/// keeping them clearly on one side of a scanner's threshold.
+const PULL: f64 = 0.7;
+
+/// 4x4 ordered dithering thresholds, in sixteenths.
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// A photo to blend with the code.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Halftone {
+    pub photo: RgbaImage,
+    /// Center dot width as a share of the module.
+    pub dot: f64,
+}
+
+impl Halftone {
+    /// Loads the photo; `dot_percent` is the width of the solid center of
+    /// each data module.
+    pub fn load(path: &str, dot_percent: u8) -> Result<Self, Box<dyn Error>> {
+        let photo = image::open(path).map_err(|e| format!("Cannot read halftone image {}: {}", path, e))?.to_rgba8();
+        if !(20..=100).contains(&dot_percent) {
+            return Err(format!("Halftone dot must be between 20 and 100% of the module, got {}%", dot_percent).into());
+        }
+        Ok(Self { photo, dot: f64::from(dot_percent) / 100.0 })
+    }
+
+    /// Photo cropped to a centered square and scaled to `side` pixels.
+    fn cover(&self, side: u32) -> RgbaImage {
+        let (width, height) = self.photo.dimensions();
+        let crop = width.min(height);
+        let square = imageops::crop_imm(&self.photo, (width - crop) / 2, (height - crop) / 2, crop, crop).to_image();
+        imageops::resize(&square, side, side, FilterType::Lanczos3)
+    }
+
+    /// Renders `symbol` over the photo: function patterns as solid modules,
+    /// data modules as a center dot in their color surrounded by the photo
+    /// dithered toward `dark` and `light`. The quiet zone stays light.
+    pub fn render(&self, symbol: &QrSymbol, geometry: &Geometry, dark: Rgba<u8>, light: Rgba<u8>) -> RgbaImage {
+        let origin = geometry.origin();
+        let module_px = geometry.module_px;
+        let side = geometry.width * module_px;
+        let photo = self.cover(side);
+        // Dot bounds within a module, in pixels
+        let dot_px = (f64::from(module_px) * self.dot).round().max(1.0) as u32;
+        let dot_start = (module_px - dot_px.min(module_px)) / 2;
+        let dot = dot_start..dot_start + dot_px;
+
+        RgbaImage::from_fn(geometry.size, geometry.size, |px, py| {
+            if px < origin || py < origin || px >= origin + side || py >= origin + side {
+                return light;
+            }
+            let (sx, sy) = (px - origin, py - origin);
+            let (x, y) = ((sx / module_px) as usize, (sy / module_px) as usize);
+            let module = if symbol[(x, y)] == qrcode::Color::Dark { dark } else { light };
+            if symbol.is_functional(x, y) || (dot.contains(&(sx % module_px)) && dot.contains(&(sy % module_px))) {
+                return module;
+            }
+            // The photo over the light color, pulled toward the side of the
+            // dithering threshold its luminance falls on
+            let mut pixel = light;
+            pixel.blend(photo.get_pixel(sx, sy));
+            let luminance = relative_luminance(Rgb([pixel.0[0], pixel.0[1], pixel.0[2]]));
+            let threshold = (f64::from(BAYER[(sy % 4) as usize][(sx % 4) as usize]) + 0.5) / 16.0;
+            let target = if luminance < threshold { dark } else { light };
+            mix(pixel, target, PULL)
+        })
+    }
+}
+
+/// `from` moved a share `amount` of the way to `to`.
+fn mix(from: Rgba<u8>, to: Rgba<u8>, amount: f64) -> Rgba<u8> {
+    let channel = |i: usize| (f64::from(from.0[i]) * (1.0 - amount) + f64::from(to.0[i]) * amount).round() as u8;
+    Rgba([channel(0), channel(1), channel(2), channel(3)])
+}
@@ -0,0 +1,418 @@
+//! Print-quality grading in the style of ISO/IEC 15415.
+//!
+//! Reflectance is approximated by image luminance, so the grades are only as
+//! good as the scan or artwork they are computed from. They are meant for
+//! comparing artwork, not as a substitute for a calibrated verifier.
+
+use crate::capacity;
+use crate::decode::{self, Decoded, Detection};
+use image::GrayImage;
+use qrcode::{Color, EcLevel, Version};
+use std::error::Error;
+use std::fmt;
+
+/// Quality grade, A (best) to F (fail).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Grade {
+    F,
+    D,
+    C,
+    B,
+    A,
+}
+
+impl Grade {
+    /// First grade whose lower bound `value` reaches, given the bounds for A, B, C and D.
+    fn at_least(value: f64, bounds: [f64; 4]) -> Self {
+        match bounds.iter().position(|&bound| value >= bound) {
+            Some(0) => Grade::A,
+            Some(1) => Grade::B,
+            Some(2) => Grade::C,
+            Some(3) => Grade::D,
+            _ => Grade::F,
+        }
+    }
+
+    /// First grade whose upper bound `value` stays within, given the bounds for A, B, C and D.
+    fn at_most(value: f64, bounds: [f64; 4]) -> Self {
+        Self::at_least(-value, bounds.map(|bound| -bound))
+    }
+
+    /// Grade for a count of damaged modules: none is A, four or more is F.
+    fn from_damage(damaged: usize) -> Self {
+        Self::at_most(damaged as f64, [0.0, 1.0, 2.0, 3.0])
+    }
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let letter = match self {
+            Grade::A => "A",
+            Grade::B => "B",
+            Grade::C => "C",
+            Grade::D => "D",
+            Grade::F => "F",
+        };
+        f.write_str(letter)
+    }
+}
+
+/// One graded parameter.
+#[derive(Clone, Debug)]
+pub struct Parameter {
+    pub name: &'static str,
+    /// Measured value, formatted for display.
+    pub value: String,
+    pub grade: Grade,
+}
+
+/// Grades of a symbol found in an image.
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub decoded: Decoded,
+    /// Module size in pixels.
+    pub module_size: f64,
+    pub parameters: Vec<Parameter>,
+}
+
+impl Report {
+    /// The overall symbol grade is the lowest parameter grade.
+    pub fn overall(&self) -> Grade {
+        self.parameters.iter().map(|p| p.grade).min().unwrap_or(Grade::F)
+    }
+}
+
+/// Locates, decodes and grades the symbol in `luma`; with several, the first
+/// in reading order.
+pub fn inspect(luma: &GrayImage) -> Result<Report, Box<dyn Error>> {
+    let detection = decode::detect(luma).map_err(|e| format!("Decode grade F: no readable QR code ({})", e))?.swap_remove(0);
+    let dimension = detection.location.dimension;
+
+    // Inverted symbols are graded as their negative: the centre of a finder
+    // pattern is dark, the ring around it light
+    let centre = module_reflectance(luma, &detection, 3, 3).unwrap_or(0.0);
+    let ring = module_reflectance(luma, &detection, 1, 1).unwrap_or(1.0);
+    let inverted = centre > ring;
+    let negative;
+    let luma = if inverted {
+        let mut image = luma.clone();
+        image::imageops::invert(&mut image);
+        negative = image;
+        &negative
+    } else {
+        luma
+    };
+    let reflectance = Reflectance::measure(luma, &detection);
+    let symbol_contrast = reflectance.max - reflectance.min;
+    let global_threshold = (reflectance.max + reflectance.min) / 2.0;
+
+    let mut parameters = vec![Parameter {
+        name: "Decode",
+        value: if inverted { "readable (inverted)" } else { "readable" }.to_string(),
+        grade: Grade::A,
+    }];
+
+    parameters.push(Parameter {
+        name: "Symbol contrast",
+        value: format!("{:.0}%", symbol_contrast * 100.0),
+        grade: Grade::at_least(symbol_contrast, [0.70, 0.55, 0.40, 0.20]),
+    });
+
+    // Modulation: every module graded by its distance from the global
+    // threshold, then codewords at a grade count as erasures below it
+    let module_grades: Vec<Grade> = (0..dimension * dimension)
+        .map(|i| {
+            let r = reflectance.modules[i];
+            let dark = detection.modules[i] == Color::Dark;
+            if symbol_contrast <= 0.0 || (r <= global_threshold) != dark {
+                return Grade::F;
+            }
+            let modulation = 2.0 * (r - global_threshold).abs() / symbol_contrast;
+            Grade::at_least(modulation, [0.50, 0.40, 0.30, 0.20])
+        })
+        .collect();
+    let lowest_modulation = (0..dimension * dimension)
+        .map(|i| 2.0 * (reflectance.modules[i] - global_threshold).abs() / symbol_contrast.max(f64::EPSILON))
+        .fold(f64::INFINITY, f64::min);
+    parameters.push(Parameter {
+        name: "Modulation",
+        value: format!("{:.2} lowest module", lowest_modulation),
+        grade: modulation_grade(&detection.decoded, dimension, &module_grades),
+    });
+
+    let quiet_zone = quiet_zone_width(luma, &detection, global_threshold);
+    parameters.push(Parameter {
+        name: "Quiet zone",
+        value: if quiet_zone >= QUIET_ZONE_LIMIT {
+            format!("{}+ modules", QUIET_ZONE_LIMIT)
+        } else {
+            format!("{} module{}", quiet_zone, if quiet_zone == 1 { "" } else { "s" })
+        },
+        grade: Grade::at_least(quiet_zone as f64, [4.0, 3.0, 2.0, 1.0]),
+    });
+
+    let (deviation, growth) = timing_runs(luma, &detection, global_threshold);
+    parameters.push(Parameter {
+        name: "Module size uniformity",
+        value: format!("±{:.2} modules, print growth {:+.2}", deviation, growth),
+        grade: Grade::at_most(deviation, [0.20, 0.30, 0.40, 0.50]),
+    });
+
+    let location = &detection.location;
+    let span = (dimension - 7) as f64;
+    let x_spacing = distance(location.top_left, location.top_right) / span;
+    let y_spacing = distance(location.top_left, location.bottom_left) / span;
+    let axial = (x_spacing - y_spacing).abs() / ((x_spacing + y_spacing) / 2.0);
+    parameters.push(Parameter {
+        name: "Axial non-uniformity",
+        value: format!("{:.3}", axial),
+        grade: Grade::at_most(axial, [0.06, 0.08, 0.10, 0.12]),
+    });
+
+    let (damaged, worst) = fixed_pattern_damage(&detection.modules, dimension);
+    let worst_count = worst.as_ref().map_or(0, |(_, count)| *count);
+    parameters.push(Parameter {
+        name: "Fixed pattern damage",
+        value: match worst {
+            Some((segment, count)) if count > 0 => {
+                format!("{} damaged modules, {} in the {}", damaged, count, segment)
+            }
+            _ => "no damaged modules".to_string(),
+        },
+        grade: Grade::from_damage(worst_count),
+    });
+
+    let unused = unused_error_correction(&detection.decoded, &[]);
+    parameters.push(Parameter {
+        name: "Unused error correction",
+        value: format!("{:.0}%", unused * 100.0),
+        grade: Grade::at_least(unused, [0.62, 0.50, 0.37, 0.25]),
+    });
+
+    Ok(Report { decoded: detection.decoded, module_size: location.module_size, parameters })
+}
+
+/// Quiet zone widths beyond this are not measured.
+const QUIET_ZONE_LIMIT: usize = 4;
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+/// Reflectance (0 = black, 1 = white) of each module and of the quiet zone.
+struct Reflectance {
+    modules: Vec<f64>,
+    max: f64,
+    min: f64,
+}
+
+impl Reflectance {
+    fn measure(luma: &GrayImage, detection: &Detection) -> Self {
+        let dimension = detection.location.dimension as i64;
+        let modules: Vec<f64> = (0..dimension * dimension)
+            .map(|i| module_reflectance(luma, detection, i % dimension, i / dimension).unwrap_or(1.0))
+            .collect();
+        // Contrast is measured over the symbol and its quiet zone
+        let margin = QUIET_ZONE_LIMIT as i64;
+        let quiet_zone = (-margin..dimension + margin)
+            .flat_map(|y| (-margin..dimension + margin).map(move |x| (x, y)))
+            .filter(|&(x, y)| x < 0 || y < 0 || x >= dimension || y >= dimension)
+            .filter_map(|(x, y)| module_reflectance(luma, detection, x, y));
+        let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+        for r in modules.iter().copied().chain(quiet_zone) {
+            min = min.min(r);
+            max = max.max(r);
+        }
+        Self { modules, max, min }
+    }
+}
+
+/// Mean reflectance of a square aperture about 0.7 modules wide at the centre
+/// of module (x, y); `None` outside the image.
+fn module_reflectance(luma: &GrayImage, detection: &Detection, x: i64, y: i64) -> Option<f64> {
+    let (cx, cy) = detection.location.transform.map(x as f64 + 0.5, y as f64 + 0.5);
+    mean_reflectance(luma, cx, cy, (detection.location.module_size * 0.35).floor() as i64)
+}
+
+/// Mean reflectance of the square of pixels within `radius` of (x, y);
+/// `None` outside the image.
+fn mean_reflectance(luma: &GrayImage, x: f64, y: f64, radius: i64) -> Option<f64> {
+    if x < 0.0 || y < 0.0 || x >= f64::from(luma.width()) || y >= f64::from(luma.height()) {
+        return None;
+    }
+    let (cx, cy) = (x.floor() as i64, y.floor() as i64);
+    let (mut sum, mut count) = (0u64, 0u64);
+    for py in cy - radius..=cy + radius {
+        for px in cx - radius..=cx + radius {
+            if px >= 0 && py >= 0 && px < i64::from(luma.width()) && py < i64::from(luma.height()) {
+                sum += u64::from(luma.get_pixel(px as u32, py as u32).0[0]);
+                count += 1;
+            }
+        }
+    }
+    Some(sum as f64 / count as f64 / 255.0)
+}
+
+/// Misdecode protection codewords reserved by ISO/IEC 18004 for the smallest symbols.
+fn misdecode_protection(version: Version, ec_level: EcLevel) -> usize {
+    match (version, ec_level) {
+        (Version::Normal(1), EcLevel::L) => 3,
+        (Version::Normal(1), EcLevel::M) | (Version::Normal(2), EcLevel::L) => 2,
+        (Version::Normal(1), _) | (Version::Normal(3), EcLevel::L) => 1,
+        _ => 0,
+    }
+}
+
+/// Lowest share of error correction left unused over all blocks, with
+/// `erasures` extra erased codewords per block on top of the corrected errors.
+fn unused_error_correction(decoded: &Decoded, erasures: &[usize]) -> f64 {
+    let ec_len = capacity::ec_codewords_per_block(decoded.version, decoded.ec_level);
+    let budget = ec_len.saturating_sub(misdecode_protection(decoded.version, decoded.ec_level)) as f64;
+    decoded
+        .corrected
+        .iter()
+        .enumerate()
+        .map(|(b, &errors)| {
+            let used = (2 * errors + erasures.get(b).copied().unwrap_or(0)) as f64;
+            if budget > 0.0 {
+                1.0 - used / budget
+            } else {
+                0.0
+            }
+        })
+        .fold(1.0, f64::min)
+}
+
+/// Highest grade at which codewords with a lower module grade, taken as
+/// erasures, can still be recovered by every block.
+fn modulation_grade(decoded: &Decoded, dimension: usize, module_grades: &[Grade]) -> Grade {
+    let codeword_grades: Vec<Grade> = decode::codeword_modules(decoded.version, decoded.ec_level)
+        .iter()
+        .map(|positions| positions.iter().map(|&(x, y)| module_grades[y * dimension + x]).min().unwrap_or(Grade::F))
+        .collect();
+    let layout = decode::block_layout(decoded.version, decoded.ec_level);
+    [Grade::A, Grade::B, Grade::C, Grade::D]
+        .into_iter()
+        .find(|&grade| {
+            let erasures: Vec<usize> = layout
+                .iter()
+                .map(|block| block.iter().filter(|&&i| codeword_grades[i] < grade).count())
+                .collect();
+            unused_error_correction(decoded, &erasures) >= 0.0
+        })
+        .unwrap_or(Grade::F)
+}
+
+/// Number of light module rings around the symbol, on its narrowest side.
+fn quiet_zone_width(luma: &GrayImage, detection: &Detection, threshold: f64) -> usize {
+    let dimension = detection.location.dimension as i64;
+    let light = |x: i64, y: i64| module_reflectance(luma, detection, x, y).is_some_and(|r| r > threshold);
+    let side = |ring: &dyn Fn(i64) -> Vec<(i64, i64)>| {
+        (0..QUIET_ZONE_LIMIT as i64)
+            .take_while(|&depth| ring(depth).into_iter().all(|(x, y)| light(x, y)))
+            .count()
+    };
+    let span = || -1..=dimension;
+    [
+        side(&|d| span().map(|i| (i, -1 - d)).collect()),
+        side(&|d| span().map(|i| (i, dimension + d)).collect()),
+        side(&|d| span().map(|i| (-1 - d, i)).collect()),
+        side(&|d| span().map(|i| (dimension + d, i)).collect()),
+    ]
+    .into_iter()
+    .min()
+    .unwrap_or(0)
+}
+
+/// Largest deviation of a timing pattern module from one module width, and the
+/// average difference between dark and light module widths, both in modules.
+fn timing_runs(luma: &GrayImage, detection: &Detection, threshold: f64) -> (f64, f64) {
+    let dimension = detection.location.dimension as f64;
+    let transform = &detection.location.transform;
+    let steps_per_module = (detection.location.module_size.ceil() as usize).max(4) * 2;
+    let step = 1.0 / steps_per_module as f64;
+    // A small aperture keeps noise from splitting runs
+    let radius = (detection.location.module_size * 0.15).floor() as i64;
+    let mut dark_runs = Vec::new();
+    let mut light_runs = Vec::new();
+    // The timing patterns run from module 8 to dimension - 9 along row and column 6
+    let lines: [&dyn Fn(f64) -> (f64, f64); 2] = [&|t| (t, 6.5), &|t| (6.5, t)];
+    for line in lines {
+        let mut runs: Vec<(bool, usize)> = Vec::new();
+        let samples = ((dimension - 16.0) * steps_per_module as f64) as usize;
+        for i in 0..samples {
+            let (u, v) = line(8.0 + (i as f64 + 0.5) * step);
+            let (x, y) = transform.map(u, v);
+            let dark = mean_reflectance(luma, x, y, radius).is_some_and(|r| r <= threshold);
+            match runs.last_mut() {
+                Some((colour, length)) if *colour == dark => *length += 1,
+                _ => runs.push((dark, 1)),
+            }
+        }
+        // The outermost runs may be clipped by the sampling window
+        for &(dark, length) in runs.iter().skip(1).take(runs.len().saturating_sub(2)) {
+            let modules = length as f64 * step;
+            if dark {
+                dark_runs.push(modules);
+            } else {
+                light_runs.push(modules);
+            }
+        }
+    }
+    let deviation = dark_runs.iter().chain(&light_runs).map(|m| (m - 1.0).abs()).fold(0.0, f64::max);
+    let mean = |runs: &[f64]| if runs.is_empty() { 1.0 } else { runs.iter().sum::<f64>() / runs.len() as f64 };
+    (deviation, mean(&dark_runs) - mean(&light_runs))
+}
+
+/// Damaged modules in the finder, timing and alignment patterns: the total,
+/// and the worst segment with its count.
+fn fixed_pattern_damage(modules: &[Color], dimension: usize) -> (usize, Option<(String, usize)>) {
+    let dark = |x: usize, y: usize| modules[y * dimension + x] == Color::Dark;
+    let mut segments: Vec<(String, usize)> = Vec::new();
+
+    // Finder patterns with their separators
+    let last = dimension - 7;
+    for (name, ox, oy) in [("top-left finder", 0, 0), ("top-right finder", last, 0), ("bottom-left finder", 0, last)] {
+        let mut damaged = 0;
+        for y in oy.saturating_sub(1)..(oy + 8).min(dimension) {
+            for x in ox.saturating_sub(1)..(ox + 8).min(dimension) {
+                let ring = (x as i64 - ox as i64 - 3).abs().max((y as i64 - oy as i64 - 3).abs());
+                if dark(x, y) != (ring != 2 && ring <= 3) {
+                    damaged += 1;
+                }
+            }
+        }
+        segments.push((name.to_string(), damaged));
+    }
+
+    // Timing patterns alternate, starting dark next to the separators
+    let horizontal = (8..dimension - 8).filter(|&x| dark(x, 6) != x.is_multiple_of(2)).count();
+    let vertical = (8..dimension - 8).filter(|&y| dark(6, y) != y.is_multiple_of(2)).count();
+    segments.push(("horizontal timing pattern".to_string(), horizontal));
+    segments.push(("vertical timing pattern".to_string(), vertical));
+
+    // Alignment patterns, except where they would overlap a finder
+    let positions = decode::alignment_positions(dimension);
+    let first = positions.first().copied().unwrap_or(0);
+    let edge = positions.last().copied().unwrap_or(0);
+    for &cy in &positions {
+        for &cx in &positions {
+            if (cx == first && (cy == first || cy == edge)) || (cx == edge && cy == first) {
+                continue;
+            }
+            let mut damaged = 0;
+            for y in cy - 2..=cy + 2 {
+                for x in cx - 2..=cx + 2 {
+                    let ring = (x as i64 - cx as i64).abs().max((y as i64 - cy as i64).abs());
+                    if dark(x, y) != (ring != 1) {
+                        damaged += 1;
+                    }
+                }
+            }
+            segments.push((format!("alignment pattern at ({}, {})", cx, cy), damaged));
+        }
+    }
+
+    let total = segments.iter().map(|(_, count)| count).sum();
+    (total, segments.into_iter().max_by_key(|(_, count)| *count))
+}
@@ -0,0 +1,157 @@
+//! Human-readable text under the code, such as the encoded value on asset
+//! tags, wrapped to the output width in a band that extends the canvas.
+
+use crate::font;
+use crate::frame::{escape_xml, with_opacity};
+use image::{imageops, Rgba, RgbaImage};
+use svg::node::element::{Rectangle, Text};
+use svg::Document;
+
+/// Space above and below the text, as a share of the text height.
+const PADDING: f64 = 0.75;
+/// Space between lines, as a share of the text height.
+const LINE_GAP: f64 = 0.5;
+/// Character advance of common monospace fonts, in em.
+const MONOSPACE_ADVANCE: f64 = 0.6;
+/// Marks text cut off after the last line.
+const ELLIPSIS: &str = "...";
+
+/// Text label drawn under the code.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    pub text: String,
+    /// Text height as a share of the output width.
+    pub text_size: f64,
+    /// Lines the text may wrap to before it is truncated.
+    pub max_lines: usize,
+    /// Monospace font family for SVG output; bitmaps use the built-in font.
+    pub font: String,
+    pub color: Rgba<u8>,
+}
+
+impl Label {
+    /// Text height in pixels on an output `width` pixels wide.
+    pub fn text_height(&self, width: u32) -> f64 {
+        self.text_size * f64::from(width)
+    }
+
+    /// Characters per line on an output `width` pixels wide.
+    pub fn columns(&self, width: u32) -> usize {
+        let height = self.text_height(width);
+        font::chars_fitting(f64::from(width) - 2.0 * height, height).max(1)
+    }
+
+    /// Lines of the label on an output `width` pixels wide, and whether the
+    /// text had to be truncated.
+    pub fn lines(&self, width: u32) -> (Vec<String>, bool) {
+        wrap(&self.text, self.columns(width), self.max_lines)
+    }
+
+    /// Height in pixels of the band holding `lines` lines.
+    pub fn band_height(&self, lines: usize, width: u32) -> u32 {
+        let lines = lines as f64;
+        let height = self.text_height(width) * (2.0 * PADDING + lines + (lines - 1.0).max(0.0) * LINE_GAP);
+        height.ceil() as u32
+    }
+
+    /// Height of a square output `width` pixels wide with the label below.
+    pub fn canvas_height(&self, width: u32) -> u32 {
+        width + self.band_height(self.lines(width).0.len(), width)
+    }
+
+    /// Top edge of line `index` below content `top` pixels high.
+    pub fn line_top(&self, index: usize, top: u32, width: u32) -> f64 {
+        let height = self.text_height(width);
+        f64::from(top) + height * (PADDING + index as f64 * (1.0 + LINE_GAP))
+    }
+
+    /// Copies `image` onto a taller canvas with the label centered below it.
+    pub fn draw(&self, image: &RgbaImage, light: Rgba<u8>) -> RgbaImage {
+        let width = image.width();
+        let (lines, _) = self.lines(width);
+        let band = self.band_height(lines.len(), width);
+        let mut canvas = RgbaImage::from_pixel(width, image.height() + band, light);
+        imageops::replace(&mut canvas, image, 0, 0);
+        let height = self.text_height(width);
+        for (i, line) in lines.iter().enumerate() {
+            let x = (f64::from(width) - font::text_width(line, height)) / 2.0;
+            let y = self.line_top(i, image.height(), width);
+            font::draw_text(&mut canvas, line, x, y, height, self.color);
+        }
+        canvas
+    }
+
+    /// Places the square SVG document `content`, `width` pixels wide, on a
+    /// taller document with the label as text below it. Width and height are
+    /// set in pixels; physical output replaces them.
+    pub fn wrap_svg(&self, content: Document, width: u32, light: Rgba<u8>) -> Document {
+        let (lines, _) = self.lines(width);
+        let total = self.canvas_height(width);
+        let mut document = Document::new()
+            .set("viewBox", (0, 0, width, total))
+            .set("width", width)
+            .set("height", total)
+            .set("xmlns", "http://www.w3.org/2000/svg");
+        if light.0[3] > 0 {
+            document = document.add(with_opacity(Rectangle::new(), light).set("width", width).set("height", total));
+        }
+        document = document.add(content.set("x", 0).set("y", 0).set("width", width).set("height", width));
+
+        // Font size chosen so monospace characters advance like the built-in
+        // font, keeping the wrapping identical to bitmap output
+        let height = self.text_height(width);
+        let font_size = font::advance(height) / MONOSPACE_ADVANCE;
+        for (i, line) in lines.iter().enumerate() {
+            let baseline = self.line_top(i, width, width) + height;
+            let text = Text::new()
+                .set("x", f64::from(width) / 2.0)
+                .set("y", (baseline * 100.0).round() / 100.0)
+                .set("font-family", self.font.as_str())
+                .set("font-size", (font_size * 100.0).round() / 100.0)
+                .set("text-anchor", "middle")
+                .add(svg::node::Text::new(escape_xml(line)));
+            document = document.add(with_opacity(text, self.color));
+        }
+        document
+    }
+}
+
+/// Wraps `text` into lines of at most `columns` characters, breaking at
+/// spaces where possible and inside words too long for a line. Text beyond
+/// `max_lines` is cut and the last line ends in an ellipsis; the flag tells
+/// whether that happened.
+pub fn wrap(text: &str, columns: usize, max_lines: usize) -> (Vec<String>, bool) {
+    let mut lines: Vec<Vec<char>> = Vec::new();
+    for paragraph in text.lines() {
+        let mut line: Vec<char> = Vec::new();
+        for word in paragraph.split_whitespace() {
+            let word: Vec<char> = word.chars().collect();
+            if !line.is_empty() && line.len() + 1 + word.len() <= columns {
+                line.push(' ');
+                line.extend(&word);
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            let mut rest = &word[..];
+            while rest.len() > columns {
+                lines.push(rest[..columns].to_vec());
+                rest = &rest[columns..];
+            }
+            line.extend(rest);
+        }
+        lines.push(line);
+    }
+
+    let truncated = lines.len() > max_lines;
+    if truncated {
+        lines.truncate(max_lines);
+        if let Some(last) = lines.last_mut() {
+            let keep = columns.saturating_sub(ELLIPSIS.len()).min(last.len());
+            last.truncate(keep);
+            last.extend(ELLIPSIS.chars().take(columns));
+        }
+    }
+    (lines.into_iter().map(|line| line.into_iter().collect()).collect(), truncated)
+}
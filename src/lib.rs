@@ -0,0 +1,23 @@
+//! Core QR encoding helpers shared by the `qr-forge` CLI and GUI.
+
+pub mod capacity;
+pub mod charset;
+pub mod contrast;
+pub mod decode;
+pub mod fit;
+pub mod font;
+pub mod frame;
+pub mod halftone;
+pub mod inspect;
+pub mod label;
+pub mod logo;
+pub mod pdf_output;
+pub mod plan;
+pub mod raster;
+pub mod reed_solomon;
+pub mod segment;
+pub mod stress;
+pub mod style;
+pub mod svg_output;
+pub mod symbol;
+pub mod units;
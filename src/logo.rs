@@ -0,0 +1,315 @@
+//! Center logos: placement over the symbol, the codewords they destroy, and
+//! drawing into bitmaps and SVG documents.
+
+use crate::decode;
+use crate::raster::{self, Geometry};
+use crate::svg_output;
+use crate::symbol::QrSymbol;
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+use std::error::Error;
+use std::path::Path;
+use svg::node::element::{Group, Image, Rectangle};
+
+/// Default share of each block's error correction a logo may use up, leaving
+/// the rest for dirt, glare and print defects.
+pub const DEFAULT_BUDGET_PERCENT: u8 = 60;
+
+/// Logo artwork as loaded from disk.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LogoImage {
+    /// PNG, JPEG or another bitmap the image crate reads, with the original
+    /// file contents for embedding.
+    Bitmap { image: RgbaImage, bytes: Vec<u8>, mime: &'static str },
+    /// SVG source, embedded as is; it cannot be drawn into bitmaps.
+    Svg(String),
+}
+
+/// A logo centered on the symbol.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Logo {
+    pub image: LogoImage,
+    /// Logo width as a share of the symbol width (without quiet zone).
+    pub size: f64,
+    /// Space around the logo, in modules, covered by the plate and cleared.
+    pub padding: f64,
+    /// Color of the plate drawn behind the logo and its padding.
+    pub plate: Option<Rgba<u8>>,
+    /// Whether modules under the logo and its padding are left out.
+    pub clear: bool,
+}
+
+/// Codewords a logo destroys compared to the error correction capacity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogoDamage {
+    /// Modules under the logo or its padding.
+    pub modules: usize,
+    /// Function pattern modules among them, such as alignment patterns.
+    pub function_modules: usize,
+    /// Codewords with at least one covered module.
+    pub codewords: usize,
+    pub total_codewords: usize,
+    /// Block with the largest share of its correction capacity used up, as
+    /// destroyed and correctable codewords.
+    pub worst_block: (usize, usize),
+}
+
+impl LogoDamage {
+    /// Share of the worst block's correction capacity the logo uses up.
+    pub fn budget_used(&self) -> f64 {
+        let (destroyed, correctable) = self.worst_block;
+        destroyed as f64 / correctable as f64
+    }
+
+    /// Refuses logos that use up more than `budget_percent` of any block's
+    /// error correction; otherwise returns warnings.
+    pub fn check(&self, budget_percent: u8) -> Result<Vec<String>, Box<dyn Error>> {
+        let (destroyed, correctable) = self.worst_block;
+        if self.budget_used() * 100.0 > f64::from(budget_percent) {
+            return Err(format!(
+                "Logo destroys {} of {} correctable codewords in one block ({:.0}%), more than the {}% safety margin. Use a smaller --logo-size or padding, or higher error correction",
+                destroyed,
+                correctable,
+                self.budget_used() * 100.0,
+                budget_percent
+            )
+            .into());
+        }
+        let mut warnings = Vec::new();
+        if self.function_modules > 0 {
+            warnings.push(format!(
+                "Logo covers {} function pattern modules (timing or alignment patterns); check the result with --verify",
+                self.function_modules
+            ));
+        }
+        Ok(warnings)
+    }
+
+    pub fn describe(&self) -> String {
+        let (destroyed, correctable) = self.worst_block;
+        format!(
+            "covers {} modules in {} of {} codewords; worst block loses {} of {} correctable ({:.0}%)",
+            self.modules,
+            self.codewords,
+            self.total_codewords,
+            destroyed,
+            correctable,
+            self.budget_used() * 100.0
+        )
+    }
+}
+
+impl Logo {
+    /// Loads a bitmap or SVG logo; `size_percent` is the logo width relative
+    /// to the symbol.
+    pub fn load(path: &str, size_percent: f64, padding: f64) -> Result<Self, Box<dyn Error>> {
+        let bytes = std::fs::read(path).map_err(|e| format!("Cannot read logo {}: {}", path, e))?;
+        let is_svg = Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg"));
+        let image = if is_svg {
+            let source = String::from_utf8(bytes).map_err(|_| format!("Logo {} is not UTF-8 SVG", path))?;
+            if !source.contains("<svg") {
+                return Err(format!("Logo {} has no <svg> element", path).into());
+            }
+            LogoImage::Svg(source)
+        } else {
+            let format = image::guess_format(&bytes).map_err(|e| format!("Cannot read logo {}: {}", path, e))?;
+            let image = image::load_from_memory_with_format(&bytes, format)
+                .map_err(|e| format!("Cannot read logo {}: {}", path, e))?
+                .to_rgba8();
+            LogoImage::Bitmap { image, bytes, mime: format.to_mime_type() }
+        };
+        if !(size_percent > 0.0 && size_percent <= 50.0) {
+            return Err(format!("Logo size must be between 0 and 50% of the symbol, got {}%", size_percent).into());
+        }
+        if padding < 0.0 {
+            return Err(format!("Logo padding cannot be negative, got {}", padding).into());
+        }
+        Ok(Self { image, size: size_percent / 100.0, padding, plate: None, clear: false })
+    }
+
+    pub fn is_svg(&self) -> bool {
+        matches!(self.image, LogoImage::Svg(_))
+    }
+
+    /// Logo box as (start, side) in modules from the symbol's top-left
+    /// corner; the box is square and centered.
+    pub fn bounds(&self, width: usize) -> (f64, f64) {
+        let side = width as f64 * self.size;
+        ((width as f64 - side) / 2.0, side)
+    }
+
+    /// Logo box grown by the padding, as (start, end) in modules.
+    pub fn zone(&self, width: usize) -> (f64, f64) {
+        let (start, side) = self.bounds(width);
+        (start - self.padding, start + side + self.padding)
+    }
+
+    /// Range of module rows and columns the zone touches.
+    fn cells(&self, width: usize) -> std::ops::Range<usize> {
+        let (start, end) = self.zone(width);
+        (start.floor().max(0.0) as usize)..(end.ceil() as usize).min(width)
+    }
+
+    /// Whether the module at (x, y) lies under the logo or its padding.
+    pub fn covers(&self, width: usize, x: usize, y: usize) -> bool {
+        let cells = self.cells(width);
+        cells.contains(&x) && cells.contains(&y)
+    }
+
+    /// Counts the modules and codewords the logo destroys on `symbol`,
+    /// refusing logos that reach the finder patterns or format information.
+    pub fn damage(&self, symbol: &QrSymbol) -> Result<LogoDamage, Box<dyn Error>> {
+        let width = symbol.width();
+        let cells = self.cells(width);
+        // Finder patterns with their separators and format information
+        let reserved = |v: usize| v < 9 || v >= width - 8;
+        if cells.clone().any(reserved) {
+            return Err("Logo reaches the finder patterns; use a smaller --logo-size or padding".into());
+        }
+        let modules = cells.len() * cells.len();
+        let function_modules = cells
+            .clone()
+            .flat_map(|y| cells.clone().map(move |x| (x, y)))
+            .filter(|&(x, y)| symbol.is_functional(x, y))
+            .count();
+
+        let (version, ec_level) = (symbol.version(), symbol.error_correction_level());
+        let destroyed: Vec<bool> = decode::codeword_modules(version, ec_level)
+            .iter()
+            .map(|positions| positions.iter().any(|&(x, y)| self.covers(width, x, y)))
+            .collect();
+        let layout = decode::block_layout(version, ec_level);
+        let correctable = crate::capacity::ec_codewords_per_block(version, ec_level) / 2;
+        let worst = layout.iter().map(|block| block.iter().filter(|&&i| destroyed[i]).count()).max().unwrap_or(0);
+        Ok(LogoDamage {
+            modules,
+            function_modules,
+            codewords: destroyed.iter().filter(|&&d| d).count(),
+            total_codewords: destroyed.len(),
+            worst_block: (worst, correctable),
+        })
+    }
+
+    /// Draws the cleared zone, plate and logo onto a rendering. SVG logos
+    /// are left out, so their area shows only the plate or cleared zone.
+    pub fn draw(&self, image: &mut RgbaImage, geometry: &Geometry, light: Rgba<u8>) {
+        let width = geometry.width as usize;
+        let origin = f64::from(geometry.origin());
+        let module_px = f64::from(geometry.module_px);
+        let to_px = |modules: f64| (origin + modules * module_px).round().max(0.0) as u32;
+        let mut fill = |start: u32, end: u32, color: Rgba<u8>| {
+            for y in start..end.min(image.height()) {
+                for x in start..end.min(image.width()) {
+                    image.put_pixel(x, y, color);
+                }
+            }
+        };
+        if self.clear {
+            let cells = self.cells(width);
+            fill(to_px(cells.start as f64), to_px(cells.end as f64), light);
+        }
+        if let Some(plate) = self.plate {
+            let (start, end) = self.zone(width);
+            fill(to_px(start), to_px(end), plate);
+        }
+        if let LogoImage::Bitmap { image: logo, .. } = &self.image {
+            let (start, side) = self.bounds(width);
+            let box_px = (side * module_px).round() as u32;
+            // Fit inside the box, keeping the aspect ratio
+            let scale = f64::from(box_px) / f64::from(logo.width().max(logo.height()));
+            let logo_w = ((f64::from(logo.width()) * scale).round() as u32).max(1);
+            let logo_h = ((f64::from(logo.height()) * scale).round() as u32).max(1);
+            let scaled = imageops::resize(logo, logo_w, logo_h, FilterType::Lanczos3);
+            let x = i64::from(to_px(start) + (box_px - logo_w) / 2);
+            let y = i64::from(to_px(start) + (box_px - logo_h) / 2);
+            imageops::overlay(image, &scaled, x, y);
+        }
+    }
+
+    /// SVG group with the plate and the embedded logo, in module units of a
+    /// document whose symbol starts at `margin`. Coordinates are rounded to
+    /// `precision` decimals like the module paths.
+    pub fn svg_group(&self, width: usize, margin: u32, precision: Option<usize>) -> Group {
+        let number = |value: f64| match precision {
+            Some(precision) => svg_output::format_number(value, precision),
+            None => value.to_string(),
+        };
+        let offset = f64::from(margin);
+        let mut group = Group::new();
+        if let Some(plate) = self.plate {
+            let (start, end) = self.zone(width);
+            let mut rect = Rectangle::new()
+                .set("x", number(offset + start))
+                .set("y", number(offset + start))
+                .set("width", number(end - start))
+                .set("height", number(end - start))
+                .set("fill", format!("#{}", raster::hex(plate)));
+            if raster::is_transparent(plate) {
+                rect = rect.set("fill-opacity", format!("{:.3}", f64::from(plate.0[3]) / 255.0));
+            }
+            group = group.add(rect);
+        }
+        let href = match &self.image {
+            LogoImage::Bitmap { bytes, mime, .. } => format!("data:{};base64,{}", mime, base64(bytes)),
+            LogoImage::Svg(source) => format!("data:image/svg+xml;base64,{}", base64(source.as_bytes())),
+        };
+        let (start, side) = self.bounds(width);
+        group.add(
+            Image::new()
+                .set("x", number(offset + start))
+                .set("y", number(offset + start))
+                .set("width", number(side))
+                .set("height", number(side))
+                .set("preserveAspectRatio", "xMidYMid meet")
+                .set("href", href),
+        )
+    }
+}
+
+/// Standard base64 with padding, for data URIs.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let bits = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Numeric attribute values of an SVG fragment.
+    fn numbers(svg: &str) -> Vec<String> {
+        svg.split('"').skip(1).step_by(2).filter(|v| v.parse::<f64>().is_ok()).map(str::to_string).collect()
+    }
+
+    #[test]
+    fn svg_group_rounds_coordinates() {
+        let logo = Logo {
+            image: LogoImage::Svg("<svg/>".to_string()),
+            size: 0.23,
+            padding: 1.0 / 3.0,
+            plate: Some(Rgba([255, 255, 255, 255])),
+            clear: false,
+        };
+        let exact = logo.svg_group(29, 4, None).to_string();
+        assert!(numbers(&exact).iter().any(|v| v.len() > 8), "{}", exact);
+
+        let rounded = logo.svg_group(29, 4, Some(2)).to_string();
+        let values = numbers(&rounded);
+        assert_eq!(values.len(), 8);
+        for value in values {
+            assert!(value.split('.').nth(1).is_none_or(|decimals| decimals.len() <= 2), "{} in {}", value, rounded);
+        }
+    }
+}
@@ -1,9 +1,28 @@
-use clap::Parser;
-use image::{ImageBuffer, Rgb};
-use qrcode::{QrCode, EcLevel};
+use clap::{ArgGroup, Parser, Subcommand};
+use image::{Rgba, RgbaImage};
+use qr_forge::capacity::{capacity, DataMode};
+use qr_forge::charset::{self, Charset};
+use qr_forge::contrast::{ContrastCheck, MIN_CONTRAST_RATIO};
+use qr_forge::decode::{self, Decoded};
+use qr_forge::fit::{self, FitConstraint, FitReport};
+use qr_forge::font;
+use qr_forge::frame::{CaptionAlign, CaptionPosition, Frame, FrameTemplate};
+use qr_forge::halftone::{self, Halftone};
+use qr_forge::inspect;
+use qr_forge::label::Label;
+use qr_forge::logo::{self, Logo};
+use qr_forge::pdf_output::{self, PageSize, PdfOptions};
+use qr_forge::plan::{Plan, PlanTarget};
+use qr_forge::raster::{self, parse_hex_color, Geometry, Sizing};
+use qr_forge::segment::{self, Payload};
+use qr_forge::stress;
+use qr_forge::style::{EyeShape, Gradient, ModuleShape, Style};
+use qr_forge::svg_output::{self, SvgOptions};
+use qr_forge::symbol::{QrSymbol, VersionChoice};
+use qr_forge::units::{self, parse_length_mm, DEFAULT_DPI, DEFAULT_MIN_MODULE_MM};
+use qrcode::EcLevel;
 use std::fs;
-use svg::Document;
-use svg::node::element::{Rectangle, Group};
+use svg::node::element::Group;
 
 mod gui_core;
 
@@ -11,44 +30,276 @@ mod gui_core;
 #[command(name = "qr-forge")]
 #[command(about = "🔥 QR Forge - High-quality QR code generator with SVG support")]
 #[command(version = "1.0.0")]
+#[command(disable_version_flag = true)]
 #[command(author = "Francesco")]
 #[command(long_about = "QR Forge is a powerful QR code generator that supports scalable SVG formats and high-resolution bitmaps. Perfect for professional use, printing, and web.")]
 struct Args {
     /// Website URL to generate QR code for
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     url: Option<String>,
 
     /// Output file name (without extension)
-    #[arg(short, long, default_value = "qrcode")]
+    #[arg(short, long, default_value = "qrcode", global = true)]
     output: String,
 
-    /// QR code size in pixels (width x height)
-    #[arg(short, long, default_value = "800")]
+    /// Output size in pixels (exact width x height; the code is centered)
+    #[arg(short, long, default_value = "800", global = true)]
     size: u32,
 
+    /// Exact module size in pixels instead of a fixed output size
+    #[arg(long, conflicts_with = "size", value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    module_px: Option<u32>,
+
+    /// Printed size including margin (e.g. 25mm, 2.5cm, 1in); pixels follow from --dpi
+    #[arg(long, conflicts_with_all = ["size", "module_px"], global = true)]
+    physical_size: Option<String>,
+
+    /// Print resolution stored in PNG, JPEG and SVG output, and of --size in PDF output [default: 300 with --physical-size or PDF]
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..), global = true)]
+    dpi: Option<u16>,
+
     /// Error correction level: L (low), M (medium), Q (quartile), H (high)
-    #[arg(short, long, default_value = "H")]
+    #[arg(short, long, default_value = "H", global = true)]
     error_correction: String,
 
     /// Margin around QR code (in modules)
-    #[arg(short, long, default_value = "4")]
+    #[arg(short, long, default_value = "4", global = true)]
     margin: u32,
 
-    /// Output format: png, jpg, bmp, svg
-    #[arg(short, long, default_value = "png")]
+    /// Output format: png, jpg, bmp, svg, pdf
+    #[arg(short, long, default_value = "png", global = true)]
     format: String,
 
-    /// QR code color (for SVG, hex format without #)
-    #[arg(long, default_value = "000000")]
+    /// Merge the SVG modules into one traced path instead of one element per module
+    #[arg(long, action, global = true)]
+    svg_paths: bool,
+
+    /// Let renderers anti-alias square SVG modules instead of crisp edges
+    #[arg(long, action, global = true)]
+    no_crisp_edges: bool,
+
+    /// Decimals kept in SVG path coordinates (0-6) [default: as computed]
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=6), global = true)]
+    svg_precision: Option<u8>,
+
+    /// Leave the encoded content out of the SVG title, description and metadata
+    #[arg(long, action, global = true)]
+    svg_omit_payload: bool,
+
+    /// PDF page size: fit (the code's size), a3, a4, a5, a6, letter, legal (add -landscape) or WIDTHxHEIGHT (e.g. 100x150mm)
+    #[arg(long, default_value = "fit", global = true)]
+    page_size: String,
+
+    /// Background extended past each PDF page edge for trimming (e.g. 3mm)
+    #[arg(long, global = true)]
+    bleed: Option<String>,
+
+    /// Leave the encoded content out of the PDF document title
+    #[arg(long, action, global = true)]
+    pdf_omit_payload: bool,
+
+    /// QR code color (hex format without #, 8 digits for alpha)
+    #[arg(long, default_value = "000000", global = true)]
     color: String,
 
-    /// Background color (for SVG, hex format without #)
-    #[arg(long, default_value = "ffffff")]
+    /// Background color (hex format without #, 8 digits for alpha, or "none")
+    #[arg(long, default_value = "ffffff", global = true)]
     background_color: String,
 
+    /// Module shape: square, circle, rounded, diamond, liquid
+    #[arg(long, default_value = "square", global = true)]
+    module_shape: String,
+
+    /// Finder pattern outer ring shape: square, rounded, circle, leaf
+    #[arg(long, default_value = "square", global = true)]
+    eye_frame_shape: String,
+
+    /// Finder pattern inner eye shape: square, rounded, circle, leaf
+    #[arg(long, default_value = "square", global = true)]
+    eye_shape: String,
+
+    /// Finder pattern color (hex format without #) [default: --color]
+    #[arg(long, global = true)]
+    eye_color: Option<String>,
+
+    /// Gradient fill of the dark modules from --color to --gradient-color:
+    /// linear, linear:ANGLE, radial or radial:X,Y (center from 0 to 1)
+    #[arg(long, requires = "gradient_color", global = true)]
+    gradient: Option<String>,
+
+    /// End color of the gradient (hex format without #, 8 digits for alpha)
+    #[arg(long, requires = "gradient", global = true)]
+    gradient_color: Option<String>,
+
+    /// Logo image (PNG, JPEG or SVG) drawn in the center of the code
+    #[arg(long, global = true)]
+    logo: Option<String>,
+
+    /// Logo width as a percentage of the code width (without margin)
+    #[arg(long, default_value = "20", requires = "logo", global = true)]
+    logo_size: f64,
+
+    /// Space around the logo in modules, covered by the plate and cleared
+    #[arg(long, default_value = "0.5", requires = "logo", global = true)]
+    logo_padding: f64,
+
+    /// Plate color drawn behind the logo and its padding (hex format without #)
+    #[arg(long, requires = "logo", global = true)]
+    logo_plate: Option<String>,
+
+    /// Leave out modules under the logo and its padding
+    #[arg(long, requires = "logo", global = true)]
+    logo_clear: bool,
+
+    /// Largest share (%) of any error correction block the logo may destroy
+    #[arg(long, default_value_t = logo::DEFAULT_BUDGET_PERCENT, value_parser = clap::value_parser!(u8).range(1..=100), requires = "logo", global = true)]
+    logo_budget: u8,
+
+    /// Frame around the code: box, banner, bubble
+    #[arg(long, global = true)]
+    frame: Option<String>,
+
+    /// Caption text in the frame
+    #[arg(long, default_value = "Scan me", requires = "frame", global = true)]
+    caption: String,
+
+    /// Caption position: top, bottom
+    #[arg(long, default_value = "bottom", requires = "frame", global = true)]
+    caption_position: String,
+
+    /// Caption alignment: left, center, right
+    #[arg(long, default_value = "center", requires = "frame", global = true)]
+    caption_align: String,
+
+    /// Caption height as a percentage of the output size
+    #[arg(long, default_value = "6", requires = "frame", global = true)]
+    caption_size: f64,
+
+    /// Frame color (hex format without #) [default: --color]
+    #[arg(long, requires = "frame", global = true)]
+    frame_color: Option<String>,
+
+    /// Caption color (hex format without #) [default: black or white, whichever reads better on the frame]
+    #[arg(long, requires = "frame", global = true)]
+    caption_color: Option<String>,
+
+    /// Artistic halftone mode: blend the data modules with this photo (bitmap output, always verified)
+    #[arg(long, conflicts_with_all = ["module_shape", "eye_frame_shape", "eye_shape", "eye_color", "gradient", "logo"], global = true)]
+    halftone: Option<String>,
+
+    /// Solid center dot of each halftone data module, as a percentage of the module
+    #[arg(long, default_value_t = halftone::DEFAULT_DOT_PERCENT, requires = "halftone", global = true)]
+    halftone_dot: u8,
+
+    /// Human-readable label under the code; without text, the encoded value
+    #[arg(long, num_args = 0..=1, default_missing_value = "", global = true)]
+    label: Option<String>,
+
+    /// Label text height as a percentage of the output width
+    #[arg(long, default_value = "3", requires = "label", global = true)]
+    label_size: f64,
+
+    /// Lines the label may wrap to before it is truncated
+    #[arg(long, default_value = "2", value_parser = clap::value_parser!(u8).range(1..), requires = "label", global = true)]
+    label_lines: u8,
+
+    /// Monospace font family for SVG labels (bitmaps use the built-in font)
+    #[arg(long, default_value = "monospace", requires = "label", global = true)]
+    label_font: String,
+
+    /// Label color (hex format without #) [default: --color]
+    #[arg(long, requires = "label", global = true)]
+    label_color: Option<String>,
+
+    /// Encode at exactly this QR version (1-40), failing if the data does not fit
+    #[arg(long = "version", value_parser = clap::value_parser!(i16).range(1..=40), global = true)]
+    qr_version: Option<i16>,
+
+    /// Smallest QR version (1-40) to use; larger versions are picked only if needed
+    #[arg(long, value_parser = clap::value_parser!(i16).range(1..=40), conflicts_with = "qr_version", global = true)]
+    min_version: Option<i16>,
+
+    /// Mask pattern (0-7); chosen automatically by lowest penalty score if omitted
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=7), global = true)]
+    mask: Option<u8>,
+
+    /// Uppercase the URL scheme and host so they encode in compact alphanumeric mode
+    #[arg(long, action, global = true)]
+    fold_url_case: bool,
+
+    /// Character set announced with an ECI designator: utf-8, shift_jis (uses Kanji mode), iso-8859-N
+    #[arg(long, global = true)]
+    charset: Option<String>,
+
+    /// Pick the strongest error correction level that fits --max-version or --fit-size
+    #[arg(long, action, conflicts_with_all = ["qr_version", "min_version"], global = true)]
+    fit: bool,
+
+    /// Largest QR version (1-40) allowed in --fit mode
+    #[arg(long, requires = "fit", conflicts_with = "fit_size", value_parser = clap::value_parser!(i16).range(1..=40), global = true)]
+    max_version: Option<i16>,
+
+    /// Printed size including margin for --fit mode (e.g. 25mm, 2.5cm, 1in)
+    #[arg(long, requires = "fit", global = true)]
+    fit_size: Option<String>,
+
+    /// Smallest printable module size for --fit-size, --physical-size, --dpi and plan [default: 0.33mm]
+    #[arg(long, global = true)]
+    min_module: Option<String>,
+
+    /// Allow codes whose modules are lighter than the background
+    #[arg(long, action, global = true)]
+    allow_inverted: bool,
+
+    /// Decode the rendered image and fail if it does not read back as the input
+    #[arg(long, action, global = true)]
+    verify: bool,
+
     /// Launch GUI mode instead of CLI
     #[arg(long, action)]
     gui: bool,
+
+    /// Print version
+    #[arg(short = 'V', action = clap::ArgAction::Version)]
+    print_version: Option<bool>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Decode every QR code in an image and print their payloads and symbol details
+    Decode {
+        /// Image file to read (png, jpg, bmp, ...)
+        image: String,
+
+        /// Print the results as a JSON array
+        #[arg(long, action)]
+        json: bool,
+    },
+    /// Render the code with the given options, degrade it step by step and report where decoding fails
+    Stress,
+    /// Grade the print quality of a QR code image (ISO/IEC 15415 style, A-F)
+    Inspect {
+        /// Image file to grade (png, jpg, bmp, ...)
+        image: String,
+    },
+    /// Compute the printed size for a scanning distance (10:1 rule), or the distance a printed size supports
+    #[command(group(ArgGroup::new("target").required(true).args(["distance", "print_size"])))]
+    Plan {
+        /// Expected scanning distance (e.g. 50cm, 3m, 120in)
+        #[arg(long)]
+        distance: Option<String>,
+
+        /// Available printed size including margin (e.g. 20cm for a poster)
+        #[arg(long)]
+        print_size: Option<String>,
+
+        /// Generate the code at the planned size instead of only printing the plan
+        #[arg(long, action)]
+        generate: bool,
+    },
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -60,6 +311,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         return gui_core::run_gui().map_err(|e| e.into());
     }
 
+    if let Some(Command::Decode { image, json }) = &args.command {
+        return decode_file(image, *json);
+    }
+    if let Some(Command::Inspect { image }) = &args.command {
+        return inspect_file(image);
+    }
+
     // CLI mode - URL is required
     let url_str = match &args.url {
         Some(url) => url,
@@ -70,10 +328,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     // URL validation
-    let url = validate_url(url_str)?;
+    let mut url = validate_url(url_str)?;
+    if args.fold_url_case {
+        url = segment::fold_url_case(&url);
+    }
     
     // Determine error correction level
-    let ec_level = match args.error_correction.to_uppercase().as_str() {
+    let mut ec_level = match args.error_correction.to_uppercase().as_str() {
         "L" => EcLevel::L,
         "M" => EcLevel::M,
         "Q" => EcLevel::Q,
@@ -83,27 +344,428 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("🔧 Generating QR code for: {}", url);
     println!("📊 Parameters:");
-    println!("   - Size: {}x{} pixels", args.size, args.size);
-    println!("   - Error correction: {}", args.error_correction);
+    let min_module_mm = match &args.min_module {
+        Some(min) => parse_length_mm(min)?,
+        None => DEFAULT_MIN_MODULE_MM,
+    };
+    let mut dpi = args.dpi.or(args.physical_size.as_ref().map(|_| DEFAULT_DPI));
+    let mut physical_mm = args.physical_size.as_deref().map(parse_length_mm).transpose()?;
+    let mut sizing = match (physical_mm, args.module_px) {
+        (Some(mm), _) => physical_sizing(mm, dpi.unwrap_or(DEFAULT_DPI)),
+        (None, Some(module_px)) => {
+            println!("   - Module size: {} pixels", module_px);
+            Sizing::ModulePx(module_px)
+        }
+        (None, None) => {
+            println!("   - Size: {}x{} pixels", args.size, args.size);
+            Sizing::Canvas(args.size)
+        }
+    };
+    if let (Some(dpi), None) = (args.dpi, physical_mm) {
+        println!("   - Resolution: {} dpi", dpi);
+    }
+    if args.fit {
+        println!("   - Error correction: auto-fit");
+    } else {
+        println!("   - Error correction: {}", args.error_correction);
+    }
     println!("   - Margin: {} modules", args.margin);
     println!("   - Format: {}", args.format);
 
+    // Colors apply to every output format
+    let qr_color = parse_hex_color(&args.color)?;
+    let bg_color = parse_hex_color(&args.background_color)?;
+    let gradient = match (&args.gradient, &args.gradient_color) {
+        (Some(spec), Some(color)) => Some(Gradient::parse(spec, parse_hex_color(color)?)?),
+        _ => None,
+    };
+    // Translucent colors are judged as printed on white paper, and a gradient
+    // by its stop with the least contrast
+    let stops: Vec<_> = std::iter::once(qr_color).chain(gradient.map(|g| g.end)).map(raster::over_white).collect();
+    let check = ContrastCheck::weakest(&stops, raster::over_white(bg_color));
+    match gradient {
+        Some(gradient) => println!(
+            "   - Colors: {} to {} ({}) on {} (contrast at least {:.1}:1)",
+            raster::describe(qr_color),
+            raster::describe(gradient.end),
+            gradient.describe(),
+            raster::describe(bg_color),
+            check.ratio
+        ),
+        None => println!(
+            "   - Colors: {} on {} (contrast {:.1}:1)",
+            raster::describe(qr_color),
+            raster::describe(bg_color),
+            check.ratio
+        ),
+    }
+    let format = args.format.to_lowercase();
+    if format != "svg" && (args.svg_paths || args.no_crisp_edges || args.svg_precision.is_some() || args.svg_omit_payload) {
+        eprintln!("⚠️  Warning: --svg-paths, --no-crisp-edges, --svg-precision and --svg-omit-payload only apply to svg output");
+    }
+    let page = PageSize::parse(&args.page_size)?;
+    let bleed_mm = args.bleed.as_deref().map(parse_length_mm).transpose()?.unwrap_or(0.0);
+    if format == "pdf" {
+        // PDF pages always have a printed size
+        let pdf_dpi = *dpi.get_or_insert(DEFAULT_DPI);
+        if physical_mm.is_none() && args.dpi.is_none() {
+            println!("   - Resolution: {} dpi (pixel sizes become the PDF page size)", pdf_dpi);
+        }
+        match args.bleed {
+            Some(_) => println!("   - Page: {} with {:.1} mm bleed", page.describe(), bleed_mm),
+            None => println!("   - Page: {}", page.describe()),
+        }
+    } else if page != PageSize::Fit || args.bleed.is_some() || args.pdf_omit_payload {
+        eprintln!("⚠️  Warning: --page-size, --bleed and --pdf-omit-payload only apply to pdf output");
+    }
+    if (args.svg_omit_payload || args.pdf_omit_payload) && args.label.as_deref() == Some("") {
+        eprintln!("⚠️  Warning: --label without text still shows the encoded value in the image");
+    }
+    let logo = match &args.logo {
+        Some(path) => {
+            let mut logo = Logo::load(path, args.logo_size, args.logo_padding)?;
+            logo.plate = args.logo_plate.as_deref().map(parse_hex_color).transpose()?;
+            logo.clear = args.logo_clear;
+            if format == "pdf" {
+                return Err("Logos are not supported in PDF output; use svg or png".into());
+            }
+            if logo.is_svg() && format != "svg" {
+                return Err(format!(
+                    "SVG logos can only be embedded in SVG output; use a PNG logo for {} output",
+                    format.to_uppercase()
+                )
+                .into());
+            }
+            if logo.plate.is_some_and(raster::is_transparent) && !matches!(format.as_str(), "png" | "svg") {
+                return Err(format!("{} output does not support a transparent logo plate", format.to_uppercase()).into());
+            }
+            println!("   - Logo: {} at {}% of the code width with {} module padding", path, args.logo_size, args.logo_padding);
+            Some(logo)
+        }
+        None => None,
+    };
+    let mut frame = match &args.frame {
+        Some(template) => {
+            let color = args.frame_color.as_deref().map(parse_hex_color).transpose()?.unwrap_or(qr_color);
+            let frame = Frame {
+                template: FrameTemplate::parse(template)?,
+                caption: args.caption.clone(),
+                position: CaptionPosition::parse(&args.caption_position)?,
+                align: CaptionAlign::parse(&args.caption_align)?,
+                text_size: args.caption_size / 100.0,
+                color,
+                text_color: match &args.caption_color {
+                    Some(hex) => parse_hex_color(hex)?,
+                    None => Frame::default_text_color(color),
+                },
+                canvas: 0,
+            };
+            if (raster::is_transparent(frame.color) || raster::is_transparent(frame.text_color)) && !matches!(format.as_str(), "png" | "svg") {
+                return Err(format!("{} output does not support transparent frame colors", format.to_uppercase()).into());
+            }
+            warn_unsupported_text(&format, &frame.caption);
+            println!(
+                "   - Frame: {} with \"{}\" at the {}, {} aligned",
+                frame.template.name(),
+                frame.caption,
+                frame.position.name(),
+                frame.align.name()
+            );
+            Some(frame)
+        }
+        None => None,
+    };
+    let halftone = match &args.halftone {
+        Some(path) => {
+            if matches!(format.as_str(), "svg" | "pdf") {
+                return Err("Halftone codes are bitmaps; use png, jpg or bmp output".into());
+            }
+            let halftone = Halftone::load(path, args.halftone_dot)?;
+            println!("   - Halftone: {} behind the code with {}% module dots", path, args.halftone_dot);
+            Some(halftone)
+        }
+        None => None,
+    };
+    let label = match &args.label {
+        Some(text) => {
+            // Without text the label shows the encoded value
+            let text = if text.is_empty() { url.clone() } else { text.clone() };
+            if !(args.label_size > 0.0 && args.label_size <= 20.0) {
+                return Err(format!("Label size must be between 0 and 20% of the output width, got {}%", args.label_size).into());
+            }
+            let label = Label {
+                text,
+                text_size: args.label_size / 100.0,
+                max_lines: usize::from(args.label_lines),
+                font: args.label_font.clone(),
+                color: args.label_color.as_deref().map(parse_hex_color).transpose()?.unwrap_or(qr_color),
+            };
+            if raster::is_transparent(label.color) && !matches!(format.as_str(), "png" | "svg") {
+                return Err(format!("{} output does not support a transparent label color", format.to_uppercase()).into());
+            }
+            warn_unsupported_text(&format, &label.text);
+            println!("   - Label: \"{}\" in up to {} lines", label.text, label.max_lines);
+            Some(label)
+        }
+        None => None,
+    };
+    let style = Style {
+        shape: ModuleShape::parse(&args.module_shape)?,
+        eye_frame: EyeShape::parse(&args.eye_frame_shape)?,
+        eye: EyeShape::parse(&args.eye_shape)?,
+        eye_color: args.eye_color.as_deref().map(parse_hex_color).transpose()?,
+        gradient,
+        logo: logo.as_ref(),
+        frame: None,
+        label: label.as_ref(),
+        halftone: halftone.as_ref(),
+    };
+    if style.shape != ModuleShape::Square {
+        println!("   - Module shape: {}", style.shape.name());
+    }
+    if style.custom_eyes() {
+        println!(
+            "   - Finder patterns: {} ring, {} eye in {}",
+            style.eye_frame.name(),
+            style.eye.name(),
+            raster::describe(style.eye_color.unwrap_or(qr_color))
+        );
+    }
+    let eye_transparent = style.eye_color.is_some_and(raster::is_transparent);
+    let gradient_transparent = gradient.is_some_and(|g| raster::is_transparent(g.end));
+    if (raster::is_transparent(qr_color) || raster::is_transparent(bg_color) || eye_transparent || gradient_transparent) && !matches!(format.as_str(), "png" | "svg") {
+        return Err(format!(
+            "{} output does not support transparency. Use png or svg, or opaque colors",
+            format.to_uppercase()
+        )
+        .into());
+    }
+    let warnings = check.validate(args.allow_inverted).map_err(|e| {
+        if check.inverted && check.ratio >= MIN_CONTRAST_RATIO {
+            format!("{}. Use --allow-inverted to generate it anyway", e)
+        } else {
+            e.to_string()
+        }
+    })?;
+    for warning in warnings {
+        eprintln!("⚠️  Warning: {}", warning);
+    }
+    // Finder patterns need the same contrast as the modules
+    if let Some(eye_color) = style.eye_color {
+        let eye_check = ContrastCheck::new(raster::over_white(eye_color), raster::over_white(bg_color));
+        for warning in eye_check.validate(args.allow_inverted).map_err(|e| format!("Eye color: {}", e))? {
+            eprintln!("⚠️  Warning: Eye color: {}", warning);
+        }
+    }
+
+    let version_choice = match (args.qr_version, args.min_version) {
+        (Some(v), _) => {
+            println!("   - Version: {} (fixed)", v);
+            VersionChoice::Exact(v)
+        }
+        (None, Some(v)) => {
+            println!("   - Version: at least {}", v);
+            VersionChoice::Minimum(v)
+        }
+        (None, None) => VersionChoice::Auto,
+    };
+    if let Some(mask) = args.mask {
+        println!("   - Mask pattern: {} (fixed)", mask);
+    }
+
+    // Character set and ECI designator
+    let payload = match &args.charset {
+        Some(name) => {
+            let charset = Charset::parse(name)?;
+            let payload = charset.encode(&url)?;
+            // Shift_JIS only carries its designator in some versions, reported
+            // once the version is known
+            if let (Some(eci), false) = (payload.eci, payload.kanji) {
+                println!("   - Charset: {} (ECI {})", charset.name(), eci);
+            }
+            payload
+        }
+        None => {
+            if charset::needs_eci(&url) {
+                eprintln!("⚠️  Warning: Text contains non-ASCII characters but no ECI designator; some readers will mis-decode it. Use --charset utf-8.");
+                if charset::suits_kanji_mode(&url) {
+                    eprintln!("   💡 --charset shift_jis would encode Japanese text in the more compact Kanji mode.");
+                }
+            }
+            Payload::bytes(url.as_bytes())
+        }
+    };
+
+    // Auto-fit: strongest error correction level within the size budget
+    let fit_report = if args.fit {
+        let constraint = match (args.max_version, &args.fit_size) {
+            (Some(v), _) => FitConstraint::MaxVersion(v),
+            (None, Some(size)) => FitConstraint::PhysicalSize {
+                target_mm: parse_length_mm(size)?,
+                min_module_mm,
+                margin: args.margin,
+            },
+            (None, None) => return Err("--fit requires --max-version or --fit-size".into()),
+        };
+        let report = fit::fit(&payload, constraint)?;
+        ec_level = report.chosen.ok_or_else(|| report.explanation())?;
+        println!("   - Auto-fit: {}", report.explanation());
+        Some(report)
+    } else {
+        None
+    };
+
     // Generate QR code
-    let qr_code = QrCode::with_error_correction_level(&url, ec_level)
-        .map_err(|e| format!("Error generating QR code: {}", e))?;
+    let qr_code = QrSymbol::encode_payload(&payload, ec_level, version_choice, args.mask)?;
+    if payload.kanji {
+        match payload.eci(qr_code.version()) {
+            Some(eci) => println!("   - Charset: {} (ECI {}: non-ASCII text in byte mode)", Charset::ShiftJis.name(), eci),
+            None => println!("   - Charset: {} (no ECI: all non-ASCII text in Kanji mode)", Charset::ShiftJis.name()),
+        }
+    }
+
+    // Codewords the logo destroys, within a share of the error correction
+    if let Some(logo) = &logo {
+        let damage = logo.damage(&qr_code)?;
+        println!("   - Logo {}", damage.describe());
+        for warning in damage.check(args.logo_budget)? {
+            eprintln!("⚠️  Warning: {}", warning);
+        }
+    }
+
+    // Print planning for the actual version, optionally feeding generation
+    if let Some(Command::Plan { distance, print_size, generate }) = &args.command {
+        let target = match (distance, print_size) {
+            (Some(distance), _) => PlanTarget::Distance(parse_length_mm(distance)?),
+            (None, Some(size)) => PlanTarget::Size(parse_length_mm(size)?),
+            (None, None) => return Err("plan requires --distance or --print-size".into()),
+        };
+        let plan = Plan::new(target, qr_code.width() as u32, args.margin, min_module_mm);
+        println!("\n📏 Print plan (version {:?}, error correction {:?}):", qr_code.version(), qr_code.error_correction_level());
+        for line in plan.lines(min_module_mm) {
+            println!("   - {}", line);
+        }
+        if !generate {
+            println!("\n💡 Generate it with --physical-size {:.1}mm, or add --generate to plan", plan.size_mm);
+            return Ok(());
+        }
+        let plan_dpi = *dpi.get_or_insert(DEFAULT_DPI);
+        physical_mm = Some(plan.size_mm);
+        sizing = physical_sizing(plan.size_mm, plan_dpi);
+    }
+
+    // A frame takes its share of the output size, or grows it around the code
+    let total_modules = qr_code.width() as u32 + 2 * args.margin;
+    let code_sizing = match &mut frame {
+        Some(frame) => frame.fit(sizing, total_modules)?,
+        None => sizing,
+    };
+    let geometry = Geometry::new(qr_code.width() as u32, args.margin, code_sizing)?;
+    let style = Style { frame: frame.as_ref(), ..style };
+    if halftone.is_some() && geometry.module_px < halftone::MIN_MODULE_PX {
+        return Err(format!(
+            "Halftone needs at least {} pixels per module, got {}. Use a larger --size or --module-px",
+            halftone::MIN_MODULE_PX,
+            geometry.module_px
+        )
+        .into());
+    }
+
+    // The label wraps to the full output width
+    if let Some(label) = &label {
+        let width = frame.as_ref().map_or(geometry.size, |frame| frame.canvas);
+        let (lines, truncated) = label.lines(width);
+        if truncated {
+            eprintln!(
+                "⚠️  Warning: Label does not fit in {} lines of {} characters and was cut to \"{}\". Use more --label-lines or a smaller --label-size",
+                label.max_lines,
+                label.columns(width),
+                lines.join(" ")
+            );
+        }
+        if !matches!(format.as_str(), "svg" | "pdf") && label.text_height(width) < f64::from(font::GLYPH_HEIGHT) {
+            eprintln!(
+                "⚠️  Warning: Label text is only {:.1} pixels high, less than the {} pixels of the bitmap font. Use a larger --label-size or output size",
+                label.text_height(width),
+                font::GLYPH_HEIGHT
+            );
+        }
+    }
+
+    // Printed size of the whole output and of one module
+    let print = dpi.map(|dpi| {
+        let canvas = frame.as_ref().map_or(geometry.size, |frame| frame.canvas);
+        let size_mm = physical_mm.unwrap_or_else(|| units::px_to_mm(f64::from(canvas), dpi));
+        // Vector output scales modules fractionally; bitmaps use whole pixels
+        let module_mm = if matches!(format.as_str(), "svg" | "pdf") {
+            let code_share = frame.as_ref().map_or(1.0, Frame::code_share);
+            size_mm * code_share / f64::from(geometry.total_modules())
+        } else {
+            units::px_to_mm(f64::from(geometry.module_px), dpi)
+        };
+        (dpi, size_mm, module_mm)
+    });
+    if let Some((_, _, module_mm)) = print {
+        println!("   - Printed module size: {:.3} mm", module_mm);
+        if module_mm < min_module_mm {
+            eprintln!(
+                "⚠️  Warning: Printed module size {:.3} mm is below the {:.2} mm minimum; printers and scanners may not resolve it. Use a larger size, a higher DPI or less data.",
+                module_mm, min_module_mm
+            );
+        }
+    }
+
+    if let Some(Command::Stress) = &args.command {
+        return run_stress(&qr_code, &payload, &geometry, &style, qr_color, bg_color);
+    }
 
     // Determine filename
-    let filename = format!("{}.{}", args.output, args.format.to_lowercase());
+    let filename = format!("{}.{}", args.output, format);
     
     // Generate file based on format
-    match args.format.to_lowercase().as_str() {
+    match format.as_str() {
         "svg" => {
-            generate_svg_qr(&qr_code, &filename, args.size, args.margin, &args.color, &args.background_color)?;
+            if args.verify {
+                // Rasterize the same layout and colors the SVG will use
+                if logo.as_ref().is_some_and(Logo::is_svg) {
+                    println!("   - SVG logo is not rasterized; only its plate or cleared zone is verified");
+                }
+                verify_render(&raster::compose(&qr_code, &geometry, &style, qr_color, bg_color), &payload)?;
+            }
+            let options = SvgOptions {
+                physical_mm: print.map(|(_, size_mm, _)| size_mm),
+                merge_paths: args.svg_paths,
+                crisp_edges: !args.no_crisp_edges,
+                precision: args.svg_precision.map(usize::from),
+                payload: (!args.svg_omit_payload).then_some(url.as_str()),
+                settings: &svg_settings(&args, &style, qr_color, bg_color),
+            };
+            generate_svg_qr(&qr_code, &filename, &geometry, &options, &style, qr_color, bg_color)?;
+        }
+        "pdf" => {
+            if args.verify {
+                verify_render(&raster::compose(&qr_code, &geometry, &style, qr_color, bg_color), &payload)?;
+            }
+            let options = PdfOptions {
+                size_mm: print.map_or(0.0, |(_, size_mm, _)| size_mm),
+                page,
+                bleed_mm,
+                payload: (!args.pdf_omit_payload).then_some(url.as_str()),
+            };
+            generate_pdf_qr(&qr_code, &filename, &geometry, &options, &style, qr_color, bg_color)?;
         }
         _ => {
             // Create high-resolution image for bitmap formats
-            let image = generate_high_quality_image(&qr_code, args.size, args.margin)?;
-            save_image(&image, &filename, &args.format)?;
+            let image = generate_high_quality_image(&qr_code, &geometry, &style, qr_color, bg_color)?;
+            // Halftone codes are always decoded before saving
+            if halftone.is_some() {
+                verify_render(&image, &payload).map_err(|e| {
+                    format!("Halftone code does not decode ({}). Use a larger --halftone-dot or higher error correction", e)
+                })?;
+            } else if args.verify {
+                verify_render(&image, &payload)?;
+            }
+            save_image(image, &filename, &format, dpi)?;
         }
     }
 
@@ -111,11 +773,217 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("📁 File saved as: {}", filename);
     
     // Show QR code statistics
-    print_qr_stats(&qr_code, &url);
+    print_qr_stats(&qr_code, &url, &payload, args.margin, fit_report.as_ref());
+
+    Ok(())
+}
+
+/// Generator settings recorded in the SVG metadata.
+fn svg_settings(args: &Args, style: &Style, qr_color: Rgba<u8>, bg_color: Rgba<u8>) -> Vec<(&'static str, String)> {
+    let mut settings = vec![
+        ("margin", args.margin.to_string()),
+        ("color", raster::hex(qr_color)),
+        ("background-color", raster::hex(bg_color)),
+        ("module-shape", style.shape.name().to_string()),
+        ("eye-frame-shape", style.eye_frame.name().to_string()),
+        ("eye-shape", style.eye.name().to_string()),
+    ];
+    if let Some(eye_color) = style.eye_color {
+        settings.push(("eye-color", raster::hex(eye_color)));
+    }
+    if let Some(gradient) = style.gradient {
+        settings.push(("gradient", format!("{} to {}", gradient.describe(), raster::hex(gradient.end))));
+    }
+    if let Some(charset) = &args.charset {
+        settings.push(("charset", charset.clone()));
+    }
+    if let Some(logo) = style.logo {
+        settings.push(("logo-size", format!("{}%", logo.size * 100.0)));
+    }
+    if let Some(frame) = style.frame {
+        settings.push(("frame", frame.template.name().to_string()));
+    }
+    if args.svg_paths {
+        settings.push(("svg-paths", "merged".to_string()));
+    }
+    settings
+}
+
+/// Warns about characters the output's font cannot draw.
+fn warn_unsupported_text(format: &str, text: &str) {
+    match format {
+        "svg" => {}
+        "pdf" if !text.chars().all(pdf_output::is_supported) => {
+            eprintln!("⚠️  Warning: PDF text uses the standard Courier font, which only has Latin-1 characters; others are drawn as ?. Use svg output for other scripts");
+        }
+        "pdf" => {}
+        _ if !text.chars().all(font::is_supported) => {
+            eprintln!("⚠️  Warning: The built-in bitmap font only has ASCII characters; others are drawn as ?. Use svg output for other scripts");
+        }
+        _ => {}
+    }
+}
+
+/// Canvas sizing for a printed size at `dpi`.
+fn physical_sizing(mm: f64, dpi: u16) -> Sizing {
+    let pixels = units::mm_to_px(mm, dpi);
+    println!("   - Physical size: {:.1} mm at {} dpi ({}x{} pixels)", mm, dpi, pixels, pixels);
+    Sizing::Canvas(pixels)
+}
+
+fn verify_render(image: &RgbaImage, payload: &Payload) -> Result<(), Box<dyn std::error::Error>> {
+    let decoded = decode::verify(&image::DynamicImage::ImageRgba8(image.clone()), &payload.data)?;
+    println!(
+        "🔎 Verified: rendered image reads back all {} payload bytes ({} codewords corrected)",
+        decoded.data.len(),
+        decoded.corrected.iter().sum::<usize>()
+    );
+    Ok(())
+}
+
+fn run_stress(
+    qr_code: &QrSymbol,
+    payload: &Payload,
+    geometry: &Geometry,
+    style: &Style,
+    qr_color: Rgba<u8>,
+    bg_color: Rgba<u8>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Distortions are placed relative to the code, so the frame and label are left out
+    let style = Style { frame: None, label: None, ..*style };
+    let image = generate_high_quality_image(qr_code, geometry, &style, qr_color, bg_color)?;
+    verify_render(&image, payload)?;
+
+    let luma = decode::to_luma(&image::DynamicImage::ImageRgba8(image));
+    println!("\n🧪 Stress test (increasing severity until decoding fails):");
+    for result in stress::run(&luma, geometry, &payload.data) {
+        println!("   - {}: {}", result.distortion.name(), result.summary());
+        if let Some((_, reason)) = &result.failed {
+            println!("       ({})", reason);
+        }
+    }
+    Ok(())
+}
+
+fn inspect_file(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let image = image::open(path).map_err(|e| format!("Cannot read image {}: {}", path, e))?;
+    let report = inspect::inspect(&decode::to_luma(&image))?;
+    let decoded = &report.decoded;
 
+    println!("🔬 Print quality of {}", path);
+    println!(
+        "   Version {:?}, error correction {:?}, {}x{} modules of {:.1} px",
+        decoded.version,
+        decoded.ec_level,
+        decoded.version.width(),
+        decoded.version.width(),
+        report.module_size
+    );
+    println!();
+    println!("   {:<26} {:<44} Grade", "Parameter", "Measured");
+    for parameter in &report.parameters {
+        println!("   {:<26} {:<44} {}", parameter.name, parameter.value, parameter.grade);
+    }
+    println!();
+    println!("🏷️  Overall symbol grade: {}", report.overall());
     Ok(())
 }
 
+fn decode_file(path: &str, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let image = image::open(path).map_err(|e| format!("Cannot read image {}: {}", path, e))?;
+    let decoded = decode::decode_image(&image).map_err(|e| format!("No readable QR code in {}: {}", path, e))?;
+
+    if json {
+        let symbols: Vec<String> = decoded.iter().map(decoded_json).collect();
+        println!("[{}]", symbols.join(", "));
+        return Ok(());
+    }
+
+    if decoded.len() == 1 {
+        println!("🔍 Decoded {}", path);
+    } else {
+        println!("🔍 Decoded {} QR codes in {}", decoded.len(), path);
+    }
+    for (index, symbol) in decoded.iter().enumerate() {
+        if decoded.len() > 1 {
+            println!();
+            println!("🔢 QR code {} of {}", index + 1, decoded.len());
+        }
+        print_decoded(symbol);
+    }
+    Ok(())
+}
+
+fn print_decoded(decoded: &Decoded) {
+    match decoded.text() {
+        Some(text) => println!("📄 Payload: {}", text),
+        None => println!("📄 Payload (hex): {}", hex(&decoded.data)),
+    }
+    println!("📊 Symbol:");
+    println!("   - Version: {:?} ({}x{} modules)", decoded.version, decoded.version.width(), decoded.version.width());
+    println!("   - Error correction level: {:?}", decoded.ec_level);
+    println!("   - Mask pattern: {}", decoded.mask);
+    match (decoded.eci, decoded.charset()) {
+        (Some(eci), Some(charset)) => println!("   - ECI designator: {} ({})", eci, charset.name()),
+        (Some(eci), None) => println!("   - ECI designator: {} (unsupported character set)", eci),
+        (None, _) => println!("   - ECI designator: none"),
+    }
+    let segments: Vec<String> = decoded.segments.iter().map(|s| format!("{} {}", s.mode.name(), s.char_count())).collect();
+    println!("   - Segments: {}", segments.join(", "));
+    println!(
+        "   - Corrected codewords: {} (up to {} per block)",
+        decoded.corrected.iter().sum::<usize>(),
+        decoded.correctable
+    );
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn decoded_json(decoded: &Decoded) -> String {
+    let version = match decoded.version {
+        qrcode::Version::Normal(v) | qrcode::Version::Micro(v) => v,
+    };
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "null".to_string());
+    let segments: Vec<String> = decoded
+        .segments
+        .iter()
+        .map(|s| format!("{{\"mode\": {}, \"length\": {}}}", json_string(s.mode.name()), s.char_count()))
+        .collect();
+    let corrected: Vec<String> = decoded.corrected.iter().map(|c| c.to_string()).collect();
+    format!(
+        "{{\"payload\": {}, \"payload_hex\": {}, \"version\": {}, \"error_correction\": \"{:?}\", \"mask\": {}, \"eci\": {}, \"charset\": {}, \"segments\": [{}], \"corrected_codewords\": [{}]}}",
+        optional(decoded.text().map(|t| json_string(&t))),
+        json_string(&hex(&decoded.data)),
+        version,
+        decoded.ec_level,
+        decoded.mask,
+        optional(decoded.eci.map(|e| e.to_string())),
+        optional(decoded.charset().map(|c| json_string(&c.name()))),
+        segments.join(", "),
+        corrected.join(", ")
+    )
+}
+
 fn validate_url(input: &str) -> Result<String, Box<dyn std::error::Error>> {
     let url = if input.starts_with("http://") || input.starts_with("https://") {
         input.to_string()
@@ -132,77 +1000,60 @@ fn validate_url(input: &str) -> Result<String, Box<dyn std::error::Error>> {
 }
 
 pub fn generate_high_quality_image(
-    qr_code: &QrCode,
-    size: u32,
-    margin: u32,
-) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, Box<dyn std::error::Error>> {
-    let qr_width = qr_code.width() as u32;
-    let total_modules = qr_width + (margin * 2);
-    let module_size = size / total_modules;
-    let actual_size = module_size * total_modules;
+    qr_code: &QrSymbol,
+    geometry: &Geometry,
+    style: &Style,
+    qr_color: Rgba<u8>,
+    bg_color: Rgba<u8>,
+) -> Result<RgbaImage, Box<dyn std::error::Error>> {
+    let qr_width = geometry.width;
+    let total_modules = geometry.total_modules();
+    let module_size = geometry.module_px;
 
     println!("📐 Technical details:");
     println!("   - QR modules: {}x{}", qr_width, qr_width);
     println!("   - Total modules (with margin): {}x{}", total_modules, total_modules);
     println!("   - Module size: {}x{} pixels", module_size, module_size);
-    println!("   - Final size: {}x{} pixels", actual_size, actual_size);
+    let padding = geometry.size - module_size * total_modules;
+    if padding > 0 {
+        println!("   - Padding: {} pixels in total, centered around the quiet zone", padding);
+    }
+    println!("   - Final size: {}x{} pixels", geometry.size, geometry.size);
 
     if module_size < 4 {
         eprintln!("⚠️  Warning: Very small module size ({}px). Consider increasing total size.", module_size);
     }
 
-    let mut image = ImageBuffer::new(actual_size, actual_size);
-
-    // Fill with white
-    for pixel in image.pixels_mut() {
-        *pixel = Rgb([255, 255, 255]);
+    let image = raster::compose(qr_code, geometry, style, qr_color, bg_color);
+    if let Some(frame) = style.frame {
+        println!("   - Frame: {}x{} pixels around the code", frame.canvas, frame.canvas);
     }
-
-    // Draw QR code
-    for y in 0..qr_width {
-        for x in 0..qr_width {
-            if qr_code[(x as usize, y as usize)] == qrcode::Color::Dark {
-                // Calculate position with margin
-                let start_x = (x + margin) * module_size;
-                let start_y = (y + margin) * module_size;
-
-                // Draw a module (black square)
-                for dy in 0..module_size {
-                    for dx in 0..module_size {
-                        let px = start_x + dx;
-                        let py = start_y + dy;
-                        
-                        if px < actual_size && py < actual_size {
-                            image.put_pixel(px, py, Rgb([0, 0, 0]));
-                        }
-                    }
-                }
-            }
-        }
+    if style.label.is_some() {
+        println!("   - Label: {}x{} pixels with the label below", image.width(), image.height());
     }
-
     Ok(image)
 }
 
 fn save_image(
-    image: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    image: RgbaImage,
     filename: &str,
     format: &str,
+    dpi: Option<u16>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    match format.to_lowercase().as_str() {
-        "png" => {
-            image.save_with_format(filename, image::ImageFormat::Png)?;
-        }
-        "jpg" | "jpeg" => {
-            image.save_with_format(filename, image::ImageFormat::Jpeg)?;
-        }
-        "bmp" => {
-            image.save_with_format(filename, image::ImageFormat::Bmp)?;
-        }
+    let image_format = match format.to_lowercase().as_str() {
+        "png" => image::ImageFormat::Png,
+        "jpg" | "jpeg" => image::ImageFormat::Jpeg,
+        "bmp" => image::ImageFormat::Bmp,
         _ => {
-            return Err(format!("Unsupported format: {}. Use png, jpg, bmp, or svg", format).into());
+            return Err(format!("Unsupported format: {}. Use png, jpg, bmp, svg or pdf", format).into());
         }
+    };
+    if dpi.is_some() && image_format == image::ImageFormat::Bmp {
+        eprintln!("⚠️  Warning: BMP output does not store the DPI; use png or jpg for print");
     }
+    // Opaque renderings are saved as RGB; only PNG keeps an alpha channel
+    let image = raster::for_format(image, image_format)?;
+    fs::write(filename, raster::encode(&image, image_format, dpi)?)?;
 
     // Show file information
     if let Ok(metadata) = fs::metadata(filename) {
@@ -213,121 +1064,139 @@ fn save_image(
     Ok(())
 }
 
-fn print_qr_stats(qr_code: &QrCode, url: &str) {
+fn print_qr_stats(qr_code: &QrSymbol, url: &str, payload: &Payload, margin: u32, fit_report: Option<&FitReport>) {
     println!("\n📈 QR Code Statistics:");
     println!("   - Version: {:?}", qr_code.version());
     println!("   - Matrix size: {}x{} modules", qr_code.width(), qr_code.width());
-    println!("   - URL length: {} characters", url.len());
+    println!("   - URL length: {} characters", url.chars().count());
     println!("   - Error correction level: {:?}", qr_code.error_correction_level());
+    println!("   - Mask pattern: {} (penalty score: {})", qr_code.mask(), qr_code.penalty_score());
     
-    // Maximum capacity for this version
-    let max_capacity = get_max_capacity(qr_code.version(), qr_code.error_correction_level());
-    println!("   - Maximum capacity: {} characters", max_capacity);
-    println!("   - Capacity usage: {:.1}%", (url.len() as f64 / max_capacity as f64) * 100.0);
+    // Capacity usage, measured in data bits of the encoded segments
+    let used_bits = qr_code.data_bits();
+    let capacity_bits = qr_code.capacity_bits();
+    let usage = used_bits as f64 / capacity_bits as f64 * 100.0;
+    println!("   - Data bits: {} of {} available", used_bits, capacity_bits);
+    println!("   - Capacity usage: {:.1}%", usage);
+
+    // Maximum capacity for this version, per encoding mode
+    let max_capacity: Vec<String> = DataMode::ALL
+        .iter()
+        .map(|mode| format!("{} {}", capacity(qr_code.version(), qr_code.error_correction_level(), *mode), mode.name()))
+        .collect();
+    println!("   - Maximum capacity: {} characters", max_capacity.join(" / "));
+
+    // Segment optimization compared to plain byte mode
+    if let Some(eci) = payload.eci(qr_code.version()) {
+        println!("   - ECI designator: {}", eci);
+    }
+    let segments: Vec<String> = payload
+        .segments(qr_code.version())
+        .iter()
+        .map(|s| format!("{} {}", s.mode.name(), s.char_count()))
+        .collect();
+    println!("   - Segments: {}", segments.join(", "));
+    if let Some(savings) = segment::savings(payload, qr_code.error_correction_level()) {
+        println!(
+            "   - Optimized encoding: {} bits vs {} bits in byte mode ({} bits saved)",
+            savings.optimized_bits,
+            savings.byte_bits,
+            savings.bits_saved()
+        );
+        match (savings.versions_saved(), savings.byte_version) {
+            (Some(saved), Some(byte_version)) => {
+                println!("   - Versions saved: {} (byte mode needs {:?})", saved, byte_version)
+            }
+            _ => println!("   - Versions saved: plain byte mode would not fit in any version"),
+        }
+    }
+
+    // Error correction trade-off when auto-fit chose the level
+    if let Some(report) = fit_report {
+        println!("\n⚖️  Error correction trade-off (max version {}):", report.max_version);
+        for line in report.candidate_lines() {
+            println!("   {}", line);
+        }
+        println!("   {}", report.explanation());
+    }
     
     println!("\n💡 Usage tips:");
     println!("   - Test the QR code with different readers");
     println!("   - Ensure it's readable even when printed");
-    println!("   - For printing, use at least 2.5cm x 2.5cm");
+    // Smallest printable size for this version
+    let smallest = Plan::new(PlanTarget::Distance(0.0), qr_code.width() as u32, margin, DEFAULT_MIN_MODULE_MM);
+    println!(
+        "   - For printing, use at least {} ({:.2} mm modules), which scans from up to {}",
+        units::format_length(smallest.size_mm),
+        smallest.module_mm,
+        units::format_length(smallest.distance_mm)
+    );
+    println!("   - Use the plan subcommand to size it for a scanning distance");
     
-    if url.len() > max_capacity * 80 / 100 {
+    if used_bits * 100 > capacity_bits * 80 {
         println!("   ⚠️  URL close to capacity limit - consider shortening it");
     }
 }
 
-fn get_max_capacity(version: qrcode::Version, ec_level: EcLevel) -> usize {
-    // Approximate capacities for alphanumeric data based on QR version
-    // We use matrix modules to determine capacity
-    let modules = match version {
-        qrcode::Version::Normal(v) => (4 * v + 17) as u8,
-        qrcode::Version::Micro(_) => 21, // Simplification for micro QR
-    };
-    
-    match (modules, ec_level) {
-        (21, EcLevel::L) => 25,
-        (21, EcLevel::M) => 20,
-        (21, EcLevel::Q) => 16,
-        (21, EcLevel::H) => 10,
-        (25, EcLevel::L) => 47,
-        (25, EcLevel::M) => 38,
-        (25, EcLevel::Q) => 29,
-        (25, EcLevel::H) => 20,
-        (29, EcLevel::L) => 77,
-        (29, EcLevel::M) => 61,
-        (29, EcLevel::Q) => 47,
-        (29, EcLevel::H) => 35,
-        (33, EcLevel::L) => 114,
-        (33, EcLevel::M) => 90,
-        (33, EcLevel::Q) => 67,
-        (33, EcLevel::H) => 50,
-        _ => 1000, // Default value for higher versions
-    }
-}
-
 pub fn generate_svg_qr(
-    qr_code: &QrCode,
+    qr_code: &QrSymbol,
     filename: &str,
-    size: u32,
-    margin: u32,
-    qr_color: &str,
-    bg_color: &str,
+    geometry: &Geometry,
+    options: &SvgOptions,
+    style: &Style,
+    qr_color: Rgba<u8>,
+    bg_color: Rgba<u8>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let qr_width = qr_code.width() as u32;
-    let total_modules = qr_width + (margin * 2);
-    let module_size = size / total_modules;
-    let actual_size = module_size * total_modules;
+    let qr_width = geometry.width;
+    let margin = geometry.margin;
+    let total_modules = geometry.total_modules();
+    let actual_size = geometry.size;
 
     println!("📐 SVG technical details:");
     println!("   - QR modules: {}x{}", qr_width, qr_width);
     println!("   - Total modules (with margin): {}x{}", total_modules, total_modules);
-    println!("   - Module size: {}x{} SVG units", module_size, module_size);
+    println!(
+        "   - Module size: {:.2}x{:.2} SVG units",
+        f64::from(actual_size) / f64::from(total_modules),
+        f64::from(actual_size) / f64::from(total_modules)
+    );
     println!("   - Final size: {}x{} SVG units", actual_size, actual_size);
-    println!("   - QR color: #{}", qr_color);
-    println!("   - Background color: #{}", bg_color);
-
-    // Create SVG document
-    let mut document = Document::new()
-        .set("viewBox", (0, 0, actual_size, actual_size))
-        .set("width", actual_size)
-        .set("height", actual_size)
-        .set("xmlns", "http://www.w3.org/2000/svg");
-
-    // Colored background
-    let background = Rectangle::new()
-        .set("x", 0)
-        .set("y", 0)
-        .set("width", actual_size)
-        .set("height", actual_size)
-        .set("fill", format!("#{}", bg_color));
-    
-    document = document.add(background);
-
-    // Group for all QR code modules with custom color
-    let mut qr_group = Group::new()
-        .set("fill", format!("#{}", qr_color))
-        .set("shape-rendering", "crispEdges"); // For sharp edges
-
-    // Draw QR code modules
-    for y in 0..qr_width {
-        for x in 0..qr_width {
-            if qr_code[(x as usize, y as usize)] == qrcode::Color::Dark {
-                let rect_x = (x + margin) * module_size;
-                let rect_y = (y + margin) * module_size;
-                
-                let module_rect = Rectangle::new()
-                    .set("x", rect_x)
-                    .set("y", rect_y)
-                    .set("width", module_size)
-                    .set("height", module_size);
-                
-                qr_group = qr_group.add(module_rect);
-            }
-        }
+    if let Some(mm) = options.physical_mm {
+        println!("   - Physical size: {:.1}x{:.1} mm", mm, mm);
+    }
+    println!("   - QR color: {}", raster::describe(qr_color));
+    if let Some(gradient) = style.gradient {
+        println!("   - Gradient: {} to {}", gradient.describe(), raster::describe(gradient.end));
     }
+    println!("   - Background color: {}", raster::describe(bg_color));
 
-    document = document.add(qr_group);
+    // Size of the merged path compared to one element per module
+    if options.merge_paths {
+        let separate = svg_output::module_elements(Group::new(), qr_code, style, margin, options.precision).to_string().len();
+        let (merged, subpaths) = svg_output::merged_modules(Group::new(), qr_code, style, margin, options.precision);
+        let compact = merged.to_string().len();
+        println!(
+            "   - Merged paths: 1 path with {} outlines, {:.2} KB instead of {:.2} KB with one element per module ({:.0}% smaller)",
+            subpaths,
+            compact as f64 / 1024.0,
+            separate as f64 / 1024.0,
+            (1.0 - compact as f64 / separate.max(1) as f64) * 100.0
+        );
+    }
+    if let Some(frame) = style.frame {
+        println!("   - Frame: {} with caption \"{}\"", frame.template.name(), frame.caption);
+    }
+    if let Some(label) = style.label {
+        let width = style.frame.map_or(actual_size, |frame| frame.canvas);
+        println!("   - Label: {}x{} SVG units with the label below", width, label.canvas_height(width));
+    }
+    match options.payload {
+        Some(_) => println!("   - Accessibility: role, title, description and metadata with the payload"),
+        None => println!("   - Accessibility: role, title, description and metadata without the payload"),
+    }
 
     // Save SVG file
+    let document = svg_output::document(qr_code, geometry, style, qr_color, bg_color, options);
     std::fs::write(filename, document.to_string())?;
 
     // Show file information
@@ -340,3 +1209,53 @@ pub fn generate_svg_qr(
 
     Ok(())
 }
+
+pub fn generate_pdf_qr(
+    qr_code: &QrSymbol,
+    filename: &str,
+    geometry: &Geometry,
+    options: &PdfOptions,
+    style: &Style,
+    qr_color: Rgba<u8>,
+    bg_color: Rgba<u8>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let layout = pdf_output::page_layout(geometry, style, options)?;
+    let ((width_mm, height_mm), (page_width, page_height)) = (layout.output, layout.page);
+    let total_modules = geometry.total_modules();
+
+    println!("📐 PDF technical details:");
+    println!("   - QR modules: {}x{}", geometry.width, geometry.width);
+    println!("   - Total modules (with margin): {}x{}", total_modules, total_modules);
+    println!("   - Printed size: {:.1}x{:.1} mm", width_mm, height_mm);
+    match options.page {
+        PageSize::Fit => println!("   - Page: {:.1}x{:.1} mm, fitted to the code", page_width, page_height),
+        page => println!("   - Page: {}", page.describe()),
+    }
+    if options.bleed_mm > 0.0 {
+        println!(
+            "   - Bleed: {:.1} mm on each side ({:.1}x{:.1} mm before trimming)",
+            options.bleed_mm,
+            page_width + 2.0 * options.bleed_mm,
+            page_height + 2.0 * options.bleed_mm
+        );
+    }
+    println!("   - QR color: {}", raster::describe(qr_color));
+    if let Some(gradient) = style.gradient {
+        println!("   - Gradient: {} to {}", gradient.describe(), raster::describe(gradient.end));
+    }
+    println!("   - Background color: {}", raster::describe(bg_color));
+    if style.frame.is_some() || style.label.is_some() {
+        println!("   - Text: Courier, not embedded");
+    }
+
+    std::fs::write(filename, pdf_output::document(qr_code, geometry, style, qr_color, bg_color, options)?)?;
+
+    if let Ok(metadata) = fs::metadata(filename) {
+        let size_kb = metadata.len() as f64 / 1024.0;
+        println!("📊 PDF file size: {:.2} KB", size_kb);
+    }
+
+    println!("✨ PDF QR code generated! Modules are vector paths, sharp at any print size.");
+
+    Ok(())
+}
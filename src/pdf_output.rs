@@ -0,0 +1,615 @@
+//! Vector PDF output, written without a PDF library: modules, finder
+//! patterns and frames become filled paths converted from the same SVG path
+//! data the SVG writer uses, captions and labels become text in the standard
+//! Courier fonts, and the page has a physical size with optional bleed.
+
+use crate::font;
+use crate::frame::CaptionAlign;
+use crate::raster::Geometry;
+use crate::style::{finder_origins, Gradient, GradientKind, Style};
+use crate::svg_output::{self, format_number};
+use crate::symbol::QrSymbol;
+use crate::units::{self, MM_PER_INCH};
+use image::Rgba;
+use std::error::Error;
+use std::f64::consts::{FRAC_PI_2, PI};
+
+/// PDF user space units per inch.
+pub const POINTS_PER_INCH: f64 = 72.0;
+
+/// Named sheet sizes in millimetres, portrait.
+pub const SHEETS: [(&str, f64, f64); 6] = [
+    ("a3", 297.0, 420.0),
+    ("a4", 210.0, 297.0),
+    ("a5", 148.0, 210.0),
+    ("a6", 105.0, 148.0),
+    ("letter", 215.9, 279.4),
+    ("legal", 215.9, 355.6),
+];
+
+/// Character advance of Courier, in em.
+const COURIER_ADVANCE: f64 = 0.6;
+/// Decimals kept in path coordinates and colors.
+const PRECISION: usize = 4;
+/// Resource names of the fonts and the gradient.
+const REGULAR_FONT: &str = "F1";
+const BOLD_FONT: &str = "F2";
+const SHADING: &str = "Sh1";
+
+/// Size of the PDF page, without bleed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PageSize {
+    /// Exactly the size of the output.
+    Fit,
+    /// A sheet with the output centered on it.
+    Sheet { width_mm: f64, height_mm: f64 },
+}
+
+impl PageSize {
+    /// Parses `fit`, a sheet name such as `a4` or `letter` (add `-landscape`
+    /// to turn it), or a size such as `100x150mm` or `4x6in`.
+    pub fn parse(input: &str) -> Result<Self, Box<dyn Error>> {
+        let name = input.trim().to_lowercase();
+        if name == "fit" {
+            return Ok(PageSize::Fit);
+        }
+        let (sheet, landscape) = match name.strip_suffix("-landscape") {
+            Some(sheet) => (sheet, true),
+            None => (name.as_str(), false),
+        };
+        if let Some(&(_, width_mm, height_mm)) = SHEETS.iter().find(|(sheet_name, _, _)| *sheet_name == sheet) {
+            return Ok(if landscape {
+                PageSize::Sheet { width_mm: height_mm, height_mm: width_mm }
+            } else {
+                PageSize::Sheet { width_mm, height_mm }
+            });
+        }
+        let (width, height) = name.split_once('x').ok_or_else(|| {
+            format!("Invalid page size: {}. Use fit, a3, a4, a5, a6, letter, legal (with -landscape) or WIDTHxHEIGHT", input)
+        })?;
+        // A unit given only after the height applies to both sides
+        let unit = height.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+        let width = if width.ends_with(|c: char| c.is_ascii_digit()) { format!("{}{}", width, unit) } else { width.to_string() };
+        Ok(PageSize::Sheet { width_mm: units::parse_length_mm(&width)?, height_mm: units::parse_length_mm(height)? })
+    }
+
+    pub fn describe(&self) -> String {
+        match *self {
+            PageSize::Fit => "fitted to the code".to_string(),
+            PageSize::Sheet { width_mm, height_mm } => {
+                let named = SHEETS.iter().find_map(|&(name, w, h)| match (w == width_mm && h == height_mm, h == width_mm && w == height_mm) {
+                    (true, _) => Some(name.to_uppercase()),
+                    (_, true) => Some(format!("{} landscape", name.to_uppercase())),
+                    _ => None,
+                });
+                match named {
+                    Some(name) => format!("{} ({}x{} mm)", name, width_mm, height_mm),
+                    None => format!("{}x{} mm", width_mm, height_mm),
+                }
+            }
+        }
+    }
+}
+
+/// How the PDF writer places the output on the page and describes it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PdfOptions<'a> {
+    /// Printed width of the output, frame included, in millimetres.
+    pub size_mm: f64,
+    pub page: PageSize,
+    /// Background extended past each page edge for trimming, in millimetres.
+    pub bleed_mm: f64,
+    /// Encoded text named in the document title, `None` to leave it out.
+    pub payload: Option<&'a str>,
+}
+
+/// Placement of the output on the page, in millimetres.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageLayout {
+    /// Width and height of the output with its frame and label.
+    pub output: (f64, f64),
+    /// Width and height of the page, without bleed.
+    pub page: (f64, f64),
+}
+
+/// Whether the standard fonts, in WinAnsi encoding, have character `c`.
+pub fn is_supported(c: char) -> bool {
+    (' '..='~').contains(&c) || ('\u{a0}'..='\u{ff}').contains(&c)
+}
+
+/// Sizes of the output and of the page it is placed on. Fails if the output
+/// does not fit on a sheet.
+pub fn page_layout(geometry: &Geometry, style: &Style, options: &PdfOptions) -> Result<PageLayout, Box<dyn Error>> {
+    let (width, height) = canvas(geometry, style);
+    let output = (options.size_mm, options.size_mm * f64::from(height) / f64::from(width));
+    let page = match options.page {
+        PageSize::Fit => output,
+        PageSize::Sheet { width_mm, height_mm } => {
+            if output.0 > width_mm || output.1 > height_mm {
+                return Err(format!(
+                    "The code is {:.1}x{:.1} mm and does not fit on the {} page. Use a smaller --physical-size or a larger --page-size",
+                    output.0,
+                    output.1,
+                    options.page.describe()
+                )
+                .into());
+            }
+            (width_mm, height_mm)
+        }
+    };
+    Ok(PageLayout { output, page })
+}
+
+/// Width and height in pixels of the output with its frame and label.
+fn canvas(geometry: &Geometry, style: &Style) -> (u32, u32) {
+    let width = style.frame.map_or(geometry.size, |frame| frame.canvas);
+    (width, style.label.map_or(width, |label| label.canvas_height(width)))
+}
+
+/// Writes a one-page PDF with the symbol, frame and label as vector paths
+/// and text on a background of the `light` color covering the page and its
+/// bleed. Colors must be opaque.
+pub fn document(
+    symbol: &QrSymbol,
+    geometry: &Geometry,
+    style: &Style,
+    dark: Rgba<u8>,
+    light: Rgba<u8>,
+    options: &PdfOptions,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let PageLayout { output, page } = page_layout(geometry, style, options)?;
+    let points = |mm: f64| mm / MM_PER_INCH * POINTS_PER_INCH;
+    let bleed = options.bleed_mm;
+    let media = (points(page.0 + 2.0 * bleed), points(page.1 + 2.0 * bleed));
+    let mut content = String::new();
+
+    if light.0[3] > 0 {
+        content += &format!("{}0 0 {} {} re f\n", fill_color(light), number(media.0), number(media.1));
+    }
+
+    // Canvas pixels from the top-left corner of the centered output, with y
+    // running downwards as in the SVG and bitmap layouts
+    let (width, _) = canvas(geometry, style);
+    let scale = points(output.0) / f64::from(width);
+    let left = points(bleed + (page.0 - output.0) / 2.0);
+    let top = media.1 - points(bleed + (page.1 - output.1) / 2.0);
+    // Scale kept precise: it multiplies every coordinate
+    let scale = format_number(scale, 8);
+    content += &format!("q {} 0 0 -{} {} {} cm\n", scale, scale, number(left), number(top));
+
+    // The code fills the output, or its area inside the frame
+    let total_modules = f64::from(geometry.total_modules());
+    let (code_left, code_top, side) = match style.frame {
+        Some(frame) => {
+            let layout = frame.layout();
+            content += &fill_color(frame.color);
+            for d in frame.shape_paths(&layout) {
+                content += &path_operators(&d);
+                content += "f*\n";
+            }
+            layout.code
+        }
+        None => (0.0, 0.0, f64::from(geometry.size)),
+    };
+    let module = side / total_modules;
+    content += &format!("q {} 0 0 {} {} {} cm\n", number(module), number(module), number(code_left), number(code_top));
+    content += &code_operators(symbol, style, geometry.margin, dark);
+    content += "Q\n";
+
+    if let Some(frame) = style.frame {
+        let layout = frame.layout();
+        let (band_top, band_height) = layout.band;
+        let width = frame.caption.chars().count() as f64 * font::advance(layout.text_height);
+        let x = match frame.align {
+            CaptionAlign::Left => layout.text_x,
+            CaptionAlign::Center => layout.text_x - width / 2.0,
+            CaptionAlign::Right => layout.text_x - width,
+        };
+        let baseline = band_top + (band_height + layout.text_height) / 2.0;
+        content += &text_operators(BOLD_FONT, &frame.caption, (x, baseline), layout.text_height, frame.text_color);
+    }
+    if let Some(label) = style.label {
+        let height = label.text_height(width);
+        for (i, line) in label.lines(width).0.iter().enumerate() {
+            let x = (f64::from(width) - line.chars().count() as f64 * font::advance(height)) / 2.0;
+            let baseline = label.line_top(i, width, width) + height;
+            content += &text_operators(REGULAR_FONT, line, (x, baseline), height, label.color);
+        }
+    }
+    content += "Q\n";
+
+    // Objects in file order; the page refers to them by these numbers
+    let mut resources = format!("/Font << /{} 5 0 R /{} 6 0 R >>", REGULAR_FONT, BOLD_FONT);
+    if style.gradient.is_some() {
+        resources += &format!(" /Shading << /{} 8 0 R >>", SHADING);
+    }
+    let mut page_dictionary = format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}]", number(media.0), number(media.1));
+    if bleed > 0.0 {
+        page_dictionary += &format!(
+            " /BleedBox [0 0 {} {}] /TrimBox [{} {} {} {}]",
+            number(media.0),
+            number(media.1),
+            number(points(bleed)),
+            number(points(bleed)),
+            number(media.0 - points(bleed)),
+            number(media.1 - points(bleed))
+        );
+    }
+    page_dictionary += &format!(" /Resources << {} >> /Contents 4 0 R >>", resources);
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        page_dictionary,
+        format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content),
+        font_dictionary("Courier"),
+        font_dictionary("Courier-Bold"),
+        format!(
+            "<< /Title ({}) /Creator (qr-forge {}) /Producer (qr-forge {}) >>",
+            pdf_string(&svg_output::accessible_name(options.payload)),
+            env!("CARGO_PKG_VERSION"),
+            env!("CARGO_PKG_VERSION")
+        ),
+    ];
+    if let Some(gradient) = style.gradient {
+        objects.push(shading(&gradient, dark, f64::from(geometry.margin), f64::from(geometry.width)));
+    }
+    Ok(write_objects(&objects, 7))
+}
+
+/// Content stream operators of the dark modules and finder patterns, in
+/// module units with the symbol starting at `margin`.
+fn code_operators(symbol: &QrSymbol, style: &Style, margin: u32, dark: Rgba<u8>) -> String {
+    let mut content = String::new();
+    let (d, _) = svg_output::merged_path(symbol, style, margin);
+    if !d.is_empty() {
+        content += &fill(&d, false, style.gradient.is_some(), dark);
+    }
+    if style.custom_eyes() {
+        let eye_color = style.eye_color.unwrap_or(dark);
+        let gradient = style.eye_color.is_none() && style.gradient.is_some();
+        for (finder, &(fx, fy)) in finder_origins(symbol.width()).iter().enumerate() {
+            let (ring, eye) = style.finder_paths(finder, f64::from(fx as u32 + margin), f64::from(fy as u32 + margin));
+            content += &fill(&ring, true, gradient, eye_color);
+            content += &fill(&eye, false, gradient, eye_color);
+        }
+    }
+    content
+}
+
+/// Fills the SVG path data `d` in `color`, or with the gradient shading
+/// clipped to the path.
+fn fill(d: &str, even_odd: bool, gradient: bool, color: Rgba<u8>) -> String {
+    let rule = if even_odd { "*" } else { "" };
+    if gradient {
+        format!("q\n{}W{} n /{} sh Q\n", path_operators(d), rule, SHADING)
+    } else {
+        format!("{}{}f{}\n", fill_color(color), path_operators(d), rule)
+    }
+}
+
+/// Operators drawing `text` upright in the y-down canvas, starting at `x`
+/// on `baseline`, with characters advancing like the built-in font drawn
+/// `height` pixels high.
+fn text_operators(font_name: &str, text: &str, (x, baseline): (f64, f64), height: f64, color: Rgba<u8>) -> String {
+    let size = font::advance(height) / COURIER_ADVANCE;
+    format!(
+        "{}BT /{} {} Tf 1 0 0 -1 {} {} Tm ({}) Tj ET\n",
+        fill_color(color),
+        font_name,
+        number(size),
+        number(x),
+        number(baseline),
+        pdf_string(text)
+    )
+}
+
+/// Axial or radial shading from `start` to the gradient's end color over a
+/// symbol at `origin` that is `width` modules wide.
+fn shading(gradient: &Gradient, start: Rgba<u8>, origin: f64, width: f64) -> String {
+    let (kind, coords) = match gradient.kind {
+        GradientKind::Linear { angle } => (2, Gradient::linear_endpoints(angle, origin, width).to_vec()),
+        GradientKind::Radial { center } => {
+            let [cx, cy, r] = Gradient::radial_circle(center, origin, width);
+            (3, vec![cx, cy, 0.0, cx, cy, r])
+        }
+    };
+    let coords: Vec<String> = coords.into_iter().map(number).collect();
+    format!(
+        "<< /ShadingType {} /ColorSpace /DeviceRGB /Coords [{}] /Extend [true true] \
+         /Function << /FunctionType 2 /Domain [0 1] /C0 [{}] /C1 [{}] /N 1 >> >>",
+        kind,
+        coords.join(" "),
+        rgb(start),
+        rgb(gradient.end)
+    )
+}
+
+fn font_dictionary(base_font: &str) -> String {
+    format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", base_font)
+}
+
+/// The file: header, numbered `objects`, cross-reference table and a
+/// trailer naming object 1 as the catalog and object `info` as the
+/// document information.
+fn write_objects(objects: &[String], info: usize) -> Vec<u8> {
+    let mut file = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(file.len());
+        file.extend(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).bytes());
+    }
+    let xref = file.len();
+    let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        table += &format!("{:010} 00000 n \n", offset);
+    }
+    table += &format!(
+        "trailer\n<< /Size {} /Root 1 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        info,
+        xref
+    );
+    file.extend(table.bytes());
+    file
+}
+
+/// `color` as nonstroking RGB operator.
+fn fill_color(color: Rgba<u8>) -> String {
+    format!("{} rg\n", rgb(color))
+}
+
+fn rgb(color: Rgba<u8>) -> String {
+    let channel = |i: usize| number(f64::from(color.0[i]) / 255.0);
+    format!("{} {} {}", channel(0), channel(1), channel(2))
+}
+
+fn number(value: f64) -> String {
+    format_number(value, PRECISION)
+}
+
+/// `text` as a literal string in WinAnsi encoding; characters outside it
+/// become `?`.
+fn pdf_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            c if is_supported(c) => out += &format!("\\{:03o}", c as u32),
+            _ => out.push('?'),
+        }
+    }
+    out
+}
+
+/// Path construction operators for SVG path data `d` using the commands
+/// the shape and frame writers emit: M, L, H, V, A and Z, absolute or
+/// relative. Arcs become cubic Bézier curves.
+fn path_operators(d: &str) -> String {
+    let tokens = path_tokens(d);
+    let mut out = String::new();
+    let mut current = (0.0, 0.0);
+    let mut start = (0.0, 0.0);
+    let mut command = 'M';
+    let mut i = 0;
+    while i < tokens.len() {
+        if let PathToken::Command(c) = tokens[i] {
+            command = c;
+            i += 1;
+            if c.eq_ignore_ascii_case(&'z') {
+                out += "h\n";
+                current = start;
+                continue;
+            }
+        }
+        let arity = match command.to_ascii_uppercase() {
+            'M' | 'L' => 2,
+            'H' | 'V' => 1,
+            'A' => 7,
+            _ => break,
+        };
+        let args: Vec<f64> = tokens[i..]
+            .iter()
+            .take(arity)
+            .map_while(|token| match token {
+                PathToken::Number(value) => Some(*value),
+                PathToken::Command(_) => None,
+            })
+            .collect();
+        if args.len() < arity {
+            break;
+        }
+        i += arity;
+        let base = if command.is_ascii_lowercase() { current } else { (0.0, 0.0) };
+        match command.to_ascii_uppercase() {
+            'M' => {
+                current = (base.0 + args[0], base.1 + args[1]);
+                start = current;
+                out += &format!("{} {} m\n", number(current.0), number(current.1));
+                // Further coordinate pairs are lines
+                command = if command == 'm' { 'l' } else { 'L' };
+            }
+            'L' => {
+                current = (base.0 + args[0], base.1 + args[1]);
+                out += &format!("{} {} l\n", number(current.0), number(current.1));
+            }
+            'H' => {
+                current.0 = base.0 + args[0];
+                out += &format!("{} {} l\n", number(current.0), number(current.1));
+            }
+            'V' => {
+                current.1 = base.1 + args[0];
+                out += &format!("{} {} l\n", number(current.0), number(current.1));
+            }
+            _ => {
+                let end = (base.0 + args[5], base.1 + args[6]);
+                out += &arc_operators(current, (args[0], args[1]), args[3] != 0.0, args[4] != 0.0, end);
+                current = end;
+            }
+        }
+    }
+    out
+}
+
+enum PathToken {
+    Command(char),
+    Number(f64),
+}
+
+/// Splits path data into commands and numbers; numbers may run together
+/// as in `.5.5` or `1-2`.
+fn path_tokens(d: &str) -> Vec<PathToken> {
+    let chars: Vec<char> = d.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_ascii_alphabetic() {
+            tokens.push(PathToken::Command(c));
+            i += 1;
+            continue;
+        }
+        if !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+') {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let mut seen_point = c == '.';
+        i += 1;
+        while i < chars.len() && (chars[i].is_ascii_digit() || (chars[i] == '.' && !seen_point)) {
+            seen_point |= chars[i] == '.';
+            i += 1;
+        }
+        let number: String = chars[start..i].iter().collect();
+        if let Ok(value) = number.parse() {
+            tokens.push(PathToken::Number(value));
+        }
+    }
+    tokens
+}
+
+/// Curves for an unrotated elliptical arc from `from` to `to`, following
+/// the endpoint parameterization of the SVG specification (appendix B.2.4),
+/// one curve per quarter turn at most.
+fn arc_operators(from: (f64, f64), (rx, ry): (f64, f64), large_arc: bool, sweep: bool, to: (f64, f64)) -> String {
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+    if rx == 0.0 || ry == 0.0 || from == to {
+        return format!("{} {} l\n", number(to.0), number(to.1));
+    }
+    // Midpoint-relative start, and radii grown if they cannot span the ends
+    let (x1, y1) = ((from.0 - to.0) / 2.0, (from.1 - to.1) / 2.0);
+    let lambda = (x1 / rx).powi(2) + (y1 / ry).powi(2);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let numerator = (rx * ry).powi(2) - (rx * y1).powi(2) - (ry * x1).powi(2);
+    let denominator = (rx * y1).powi(2) + (ry * x1).powi(2);
+    let sign = if large_arc == sweep { -1.0 } else { 1.0 };
+    let coefficient = sign * (numerator / denominator).max(0.0).sqrt();
+    let (cx1, cy1) = (coefficient * rx * y1 / ry, -coefficient * ry * x1 / rx);
+    let center = (cx1 + (from.0 + to.0) / 2.0, cy1 + (from.1 + to.1) / 2.0);
+
+    let start = ((y1 - cy1) / ry).atan2((x1 - cx1) / rx);
+    let end = ((-y1 - cy1) / ry).atan2((-x1 - cx1) / rx);
+    let mut sweep_angle = end - start;
+    if sweep && sweep_angle < 0.0 {
+        sweep_angle += 2.0 * PI;
+    } else if !sweep && sweep_angle > 0.0 {
+        sweep_angle -= 2.0 * PI;
+    }
+
+    let segments = (sweep_angle.abs() / FRAC_PI_2 - 1e-9).ceil().max(1.0) as usize;
+    let step = sweep_angle / segments as f64;
+    let handle = 4.0 / 3.0 * (step / 4.0).tan();
+    let point = |angle: f64, along: f64| {
+        let (sin, cos) = angle.sin_cos();
+        (center.0 + rx * (cos - along * sin), center.1 + ry * (sin + along * cos))
+    };
+    let mut out = String::new();
+    for segment in 0..segments {
+        let a1 = start + step * segment as f64;
+        let a2 = a1 + step;
+        let (c1, c2, end) = (point(a1, handle), point(a2, -handle), point(a2, 0.0));
+        out += &format!(
+            "{} {} {} {} {} {} c\n",
+            number(c1.0),
+            number(c1.1),
+            number(c2.0),
+            number(c2.1),
+            number(end.0),
+            number(end.1)
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raster::Sizing;
+    use crate::symbol::VersionChoice;
+    use qrcode::EcLevel;
+
+    const URL: &str = "https://example.com/print";
+
+    fn pdf(bleed_mm: f64, payload: Option<&str>) -> String {
+        let symbol = QrSymbol::encode(URL.as_bytes(), EcLevel::M, VersionChoice::Auto, None).unwrap();
+        let geometry = Geometry::new(symbol.width() as u32, 4, Sizing::ModulePx(10)).unwrap();
+        let page = PageSize::parse("a4").unwrap();
+        let options = PdfOptions { size_mm: 50.0, page, bleed_mm, payload };
+        let bytes = document(&symbol, &geometry, &Style::default(), Rgba([0, 0, 0, 255]), Rgba([255, 255, 255, 255]), &options).unwrap();
+        // Keeps byte offsets: only the binary marker in the header is not ASCII
+        bytes.iter().map(|&b| if b.is_ascii() { char::from(b) } else { '?' }).collect()
+    }
+
+    /// Numbers of the array following `key` in `file`.
+    fn array(file: &str, key: &str) -> Vec<f64> {
+        let start = file.find(key).unwrap_or_else(|| panic!("no {} in the page", key)) + key.len();
+        let array = &file[start..];
+        array[array.find('[').unwrap() + 1..array.find(']').unwrap()].split(' ').map(|n| n.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn xref_offsets_point_at_their_objects() {
+        let pdf = pdf(3.0, Some(URL));
+        let startxref: usize = pdf.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+        let table = &pdf[startxref..];
+        assert!(table.starts_with("xref\n0 "), "startxref {} points at {:?}", startxref, &table[..20.min(table.len())]);
+        let mut lines = table.lines().skip(1);
+        let count: usize = lines.next().unwrap()[2..].parse().unwrap();
+        assert!(table.contains(&format!("/Size {} ", count)));
+        assert_eq!(lines.next(), Some("0000000000 65535 f "));
+        for object in 1..count {
+            let entry = lines.next().unwrap();
+            assert!(entry.ends_with(" 00000 n "), "{}", entry);
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj\n", object)), "object {} at {}", object, offset);
+        }
+        assert_eq!(lines.next(), Some("trailer"));
+    }
+
+    #[test]
+    fn bleed_records_trim_and_bleed_boxes() {
+        let pdf = pdf(3.0, Some(URL));
+        let points = |mm: f64| mm / MM_PER_INCH * POINTS_PER_INCH;
+        let media = array(&pdf, "/MediaBox");
+        assert!((media[2] - points(216.0)).abs() < 0.01 && (media[3] - points(303.0)).abs() < 0.01, "{:?}", media);
+        assert_eq!(array(&pdf, "/BleedBox"), media);
+        let trim = array(&pdf, "/TrimBox");
+        assert!((trim[0] - points(3.0)).abs() < 0.01 && (trim[1] - points(3.0)).abs() < 0.01, "{:?}", trim);
+        assert!((trim[2] - trim[0] - points(210.0)).abs() < 0.01 && (trim[3] - trim[1] - points(297.0)).abs() < 0.01, "{:?}", trim);
+
+        let unbled = self::pdf(0.0, Some(URL));
+        assert!(!unbled.contains("/TrimBox") && !unbled.contains("/BleedBox"));
+    }
+
+    #[test]
+    fn payload_can_be_left_out_of_the_title() {
+        assert!(pdf(0.0, Some(URL)).contains(URL));
+        assert!(!pdf(0.0, None).contains("example.com"));
+    }
+}
@@ -0,0 +1,97 @@
+//! Print planning: sizes a symbol for an expected scanning distance, or the
+//! distance a given printed size supports, using the 10:1 rule. The rule
+//! measures the symbol itself: the quiet zone adds to the printed size but
+//! not to the scanning distance.
+
+use crate::units::format_length;
+
+/// Scanning distance per unit of symbol width that phone cameras resolve.
+pub const DISTANCE_RATIO: f64 = 10.0;
+
+/// What the print has to satisfy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlanTarget {
+    /// Expected scanning distance in millimetres.
+    Distance(f64),
+    /// Available printed size including the quiet zone, in millimetres.
+    Size(f64),
+}
+
+/// Printed dimensions of a symbol.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plan {
+    pub target: PlanTarget,
+    /// Symbol width in modules.
+    pub width: u32,
+    /// Quiet zone in modules.
+    pub margin: u32,
+    pub module_mm: f64,
+    /// Printed size including the quiet zone.
+    pub size_mm: f64,
+    /// Farthest distance the symbol scans from.
+    pub distance_mm: f64,
+    /// Whether the module size had to be raised to the printable minimum.
+    pub limited_by_module: bool,
+}
+
+impl Plan {
+    /// Plans a symbol of `width` modules with a `margin` module quiet zone.
+    /// The 10:1 rule applies to the symbol without its quiet zone, and modules
+    /// never shrink below `min_module_mm`.
+    pub fn new(target: PlanTarget, width: u32, margin: u32, min_module_mm: f64) -> Self {
+        let total = f64::from(width + 2 * margin);
+        let (module_mm, limited_by_module) = match target {
+            PlanTarget::Distance(distance_mm) => {
+                let module_mm = distance_mm / DISTANCE_RATIO / f64::from(width);
+                (module_mm.max(min_module_mm), module_mm < min_module_mm)
+            }
+            PlanTarget::Size(size_mm) => (size_mm / total, false),
+        };
+        Self {
+            target,
+            width,
+            margin,
+            module_mm,
+            size_mm: module_mm * total,
+            distance_mm: module_mm * f64::from(width) * DISTANCE_RATIO,
+            limited_by_module,
+        }
+    }
+
+    /// Whether the modules are too small to print reliably.
+    pub fn below_minimum(&self, min_module_mm: f64) -> bool {
+        self.module_mm < min_module_mm
+    }
+
+    /// Human-readable summary, one finding per line.
+    pub fn lines(&self, min_module_mm: f64) -> Vec<String> {
+        let mut lines = vec![
+            format!(
+                "Module size: {:.2} mm ({}x{} modules plus {} quiet zone modules per side)",
+                self.module_mm, self.width, self.width, self.margin
+            ),
+            format!("Printed size: {} including the quiet zone", format_length(self.size_mm)),
+            format!(
+                "Scannable from up to {} (10:1 rule on the {} symbol, quiet zone excluded)",
+                format_length(self.distance_mm),
+                format_length(self.module_mm * f64::from(self.width))
+            ),
+        ];
+        if let PlanTarget::Distance(distance_mm) = self.target {
+            if self.limited_by_module {
+                lines.push(format!(
+                    "{} only needs smaller modules than the {:.2} mm printable minimum; the minimum is used",
+                    format_length(distance_mm),
+                    min_module_mm
+                ));
+            }
+        }
+        if self.below_minimum(min_module_mm) {
+            lines.push(format!(
+                "Modules are below the {:.2} mm printable minimum; use a larger size, less data or lower error correction",
+                min_module_mm
+            ));
+        }
+        lines
+    }
+}
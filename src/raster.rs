@@ -0,0 +1,314 @@
+//! In-memory bitmap rendering of QR symbols.
+
+use crate::style::{finder_index, finder_origins, Style};
+use crate::symbol::QrSymbol;
+use crate::units::MM_PER_INCH;
+use image::codecs::jpeg::{JpegEncoder, PixelDensity};
+use image::{DynamicImage, ImageFormat, Rgb, Rgba, RgbaImage};
+use std::io::Cursor;
+
+/// Parses a color such as `ff6600`, `#ff6600`, `ff660080` (with alpha) or
+/// `none` (fully transparent).
+pub fn parse_hex_color(input: &str) -> Result<Rgba<u8>, Box<dyn std::error::Error>> {
+    let trimmed = input.trim();
+    if trimmed.eq_ignore_ascii_case("none") || trimmed.eq_ignore_ascii_case("transparent") {
+        return Ok(Rgba([255, 255, 255, 0]));
+    }
+    let hex = trimmed.trim_start_matches('#');
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid color: {}. Use 6 hex digits like ff6600, 8 with alpha, or none", input).into());
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+    Ok(Rgba([channel(0)?, channel(2)?, channel(4)?, alpha]))
+}
+
+/// `rrggbb` hex digits of a color, without alpha.
+pub fn hex(color: Rgba<u8>) -> String {
+    format!("{:02x}{:02x}{:02x}", color.0[0], color.0[1], color.0[2])
+}
+
+/// Human-readable color, e.g. `#ff6600`, `#ff6600 at 50% opacity` or
+/// `transparent`.
+pub fn describe(color: Rgba<u8>) -> String {
+    match color.0[3] {
+        0 => "transparent".to_string(),
+        255 => format!("#{}", hex(color)),
+        alpha => format!("#{} at {:.0}% opacity", hex(color), f64::from(alpha) / 2.55),
+    }
+}
+
+/// Whether the color is not fully opaque.
+pub fn is_transparent(color: Rgba<u8>) -> bool {
+    color.0[3] < 255
+}
+
+/// The color as it appears over white paper.
+pub fn over_white(color: Rgba<u8>) -> Rgb<u8> {
+    let [r, g, b, a] = color.0.map(u32::from);
+    let blend = |c: u32| ((c * a + 255 * (255 - a)) / 255) as u8;
+    Rgb([blend(r), blend(g), blend(b)])
+}
+
+/// Prepares a rendering for saving: opaque images lose their alpha channel,
+/// and transparency is refused for formats that cannot store it.
+pub fn for_format(image: RgbaImage, format: ImageFormat) -> Result<DynamicImage, Box<dyn std::error::Error>> {
+    if image.pixels().all(|p| p.0[3] == 255) {
+        return Ok(DynamicImage::ImageRgba8(image).to_rgb8().into());
+    }
+    if format != ImageFormat::Png {
+        return Err(format!(
+            "{} output does not support transparency. Use png or svg, or opaque colors",
+            format.extensions_str()[0].to_uppercase()
+        )
+        .into());
+    }
+    Ok(DynamicImage::ImageRgba8(image))
+}
+
+/// Encodes `image` as `format`, recording `dpi` as the print resolution in
+/// PNG (pHYs chunk) and JPEG (JFIF density) files.
+pub fn encode(image: &DynamicImage, format: ImageFormat, dpi: Option<u16>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut bytes = Vec::new();
+    match (format, dpi) {
+        (ImageFormat::Jpeg, Some(dpi)) => {
+            let mut encoder = JpegEncoder::new(&mut bytes);
+            encoder.set_pixel_density(PixelDensity::dpi(dpi));
+            encoder.encode_image(image)?;
+        }
+        _ => image.write_to(&mut Cursor::new(&mut bytes), format)?,
+    }
+    if let (ImageFormat::Png, Some(dpi)) = (format, dpi) {
+        insert_phys(&mut bytes, dpi);
+    }
+    Ok(bytes)
+}
+
+/// Inserts a pHYs chunk right after IHDR, which the PNG specification
+/// requires to come before the image data.
+fn insert_phys(png: &mut Vec<u8>, dpi: u16) {
+    let pixels_per_metre = (f64::from(dpi) / MM_PER_INCH * 1000.0).round() as u32;
+    let mut chunk = Vec::with_capacity(21);
+    chunk.extend_from_slice(&9u32.to_be_bytes());
+    chunk.extend_from_slice(b"pHYs");
+    chunk.extend_from_slice(&pixels_per_metre.to_be_bytes());
+    chunk.extend_from_slice(&pixels_per_metre.to_be_bytes());
+    chunk.push(1); // unit: metre
+    let crc = crc32(&chunk[4..]);
+    chunk.extend_from_slice(&crc.to_be_bytes());
+    // 8-byte signature, then IHDR: length, type, 13 data bytes and CRC
+    let after_ihdr = 8 + 4 + 4 + 13 + 4;
+    png.splice(after_ihdr..after_ihdr, chunk);
+}
+
+/// CRC-32 as used by PNG (ISO 3309, reflected polynomial 0xedb88320).
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// How the output size is chosen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sizing {
+    /// Exact canvas width and height in pixels; the code is centered and
+    /// leftover pixels become padding around the quiet zone.
+    Canvas(u32),
+    /// Exact module size in pixels; the canvas grows with the symbol.
+    ModulePx(u32),
+}
+
+/// Pixel layout of a symbol on its canvas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Geometry {
+    /// Symbol width in modules.
+    pub width: u32,
+    /// Quiet zone in modules.
+    pub margin: u32,
+    /// Pixels per module.
+    pub module_px: u32,
+    /// Canvas width and height in pixels.
+    pub size: u32,
+}
+
+/// Largest canvas side in pixels; a square RGBA canvas of this size already
+/// takes 4 GiB.
+pub const MAX_CANVAS_PX: u32 = 32_768;
+
+impl Geometry {
+    /// Lays out a symbol of `width` modules, failing when not even one pixel
+    /// per module fits or the canvas would exceed [`MAX_CANVAS_PX`].
+    pub fn new(width: u32, margin: u32, sizing: Sizing) -> Result<Self, Box<dyn std::error::Error>> {
+        let total = margin
+            .checked_mul(2)
+            .and_then(|quiet| quiet.checked_add(width))
+            .filter(|&total| total <= MAX_CANVAS_PX)
+            .ok_or_else(|| format!("Margin of {} modules is too large", margin))?;
+        let (module_px, size) = match sizing {
+            Sizing::Canvas(size) if size > MAX_CANVAS_PX => {
+                return Err(format!("Size {}px is too large; use at most {}px", size, MAX_CANVAS_PX).into());
+            }
+            Sizing::Canvas(size) => (size / total, size),
+            Sizing::ModulePx(module_px) => match module_px.checked_mul(total).filter(|&size| size <= MAX_CANVAS_PX) {
+                Some(size) => (module_px, size),
+                None => {
+                    return Err(format!(
+                        "Module size too large: {} modules of {}px exceed the {}px canvas limit; use at most {}px",
+                        total,
+                        module_px,
+                        MAX_CANVAS_PX,
+                        MAX_CANVAS_PX / total
+                    )
+                    .into());
+                }
+            },
+        };
+        if module_px == 0 {
+            return Err(match sizing {
+                Sizing::Canvas(size) => format!(
+                    "Size {}px is too small for {} modules including the margin; use at least {}px or a smaller margin",
+                    size, total, total
+                ),
+                Sizing::ModulePx(_) => "Module size must be at least 1px".to_string(),
+            }
+            .into());
+        }
+        Ok(Self { width, margin, module_px, size })
+    }
+
+    /// Symbol width plus the quiet zone on both sides, in modules.
+    pub fn total_modules(&self) -> u32 {
+        self.width + 2 * self.margin
+    }
+
+    /// Pixels between the canvas edge and the quiet zone on the top and left.
+    pub fn padding(&self) -> u32 {
+        (self.size - self.total_modules() * self.module_px) / 2
+    }
+
+    /// Pixel position of the first symbol module.
+    pub fn origin(&self) -> u32 {
+        self.padding() + self.margin * self.module_px
+    }
+}
+
+/// Samples per pixel side when anti-aliasing curved module shapes.
+const SUPERSAMPLING: u32 = 4;
+
+/// Renders `symbol` as saved: the code from [`render`], then the style's
+/// frame around it and its label below.
+pub fn compose(symbol: &QrSymbol, geometry: &Geometry, style: &Style, dark: Rgba<u8>, light: Rgba<u8>) -> RgbaImage {
+    let mut image = render(symbol, geometry, style, dark, light);
+    if let Some(frame) = style.frame {
+        image = frame.draw(&image, light);
+    }
+    if let Some(label) = style.label {
+        image = label.draw(&image, light);
+    }
+    image
+}
+
+/// Renders `symbol` onto the canvas described by `geometry`, with padding and
+/// quiet zone in the light color. Shapes other than squares are anti-aliased,
+/// and the style's logo is drawn last. Halftone styles render on their own.
+pub fn render(symbol: &QrSymbol, geometry: &Geometry, style: &Style, dark: Rgba<u8>, light: Rgba<u8>) -> RgbaImage {
+    if let Some(halftone) = style.halftone {
+        return halftone.render(symbol, geometry, dark, light);
+    }
+    let origin = f64::from(geometry.origin());
+    let module_px = f64::from(geometry.module_px);
+    let width = f64::from(geometry.width);
+    let finders = finder_origins(symbol.width());
+    // Module color at (mx, my) in modules, following the gradient if any
+    let dark_at = |mx: f64, my: f64| match &style.gradient {
+        Some(gradient) => gradient.color_at(dark, mx / width, my / width),
+        None => dark,
+    };
+    // Color of the canvas point (px, py) in pixels
+    let paint = |px: f64, py: f64| {
+        let (mx, my) = ((px - origin) / module_px, (py - origin) / module_px);
+        if mx < 0.0 || my < 0.0 || mx >= width || my >= width {
+            return light;
+        }
+        let (x, y) = (mx as usize, my as usize);
+        if style.custom_eyes() {
+            if let Some(finder) = finder_index(symbol.width(), x, y) {
+                let (fx, fy) = finders[finder];
+                let inside = style.finder_contains(finder, mx - fx as f64, my - fy as f64);
+                return match (inside, style.eye_color) {
+                    (false, _) => light,
+                    (true, Some(eye_color)) => eye_color,
+                    (true, None) => dark_at(mx, my),
+                };
+            }
+        }
+        if symbol[(x, y)] != qrcode::Color::Dark {
+            return light;
+        }
+        let (shape, corners) = style.module(symbol, x, y);
+        if shape.contains(corners, mx.fract(), my.fract()) {
+            dark_at(mx, my)
+        } else {
+            light
+        }
+    };
+    let samples = if style.is_square() { 1 } else { SUPERSAMPLING };
+    let mut image = RgbaImage::from_fn(geometry.size, geometry.size, |px, py| {
+        if samples == 1 {
+            return paint(f64::from(px) + 0.5, f64::from(py) + 0.5);
+        }
+        // Average of the samples, weighting colors by their alpha
+        let mut sum = [0.0; 4];
+        for sy in 0..samples {
+            for sx in 0..samples {
+                let offset = |s: u32| (f64::from(s) + 0.5) / f64::from(samples);
+                let color = paint(f64::from(px) + offset(sx), f64::from(py) + offset(sy));
+                let alpha = f64::from(color.0[3]);
+                for (total, &channel) in sum.iter_mut().zip(&color.0[..3]) {
+                    *total += f64::from(channel) * alpha;
+                }
+                sum[3] += alpha;
+            }
+        }
+        if sum[3] == 0.0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        let channel = |i: usize| (sum[i] / sum[3]).round() as u8;
+        let alpha = (sum[3] / f64::from(samples * samples)).round() as u8;
+        Rgba([channel(0), channel(1), channel(2), alpha])
+    });
+    if let Some(logo) = style.logo {
+        logo.draw(&mut image, geometry, light);
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geometry_centres_the_code() {
+        let geometry = Geometry::new(21, 4, Sizing::Canvas(300)).unwrap();
+        assert_eq!((geometry.module_px, geometry.padding(), geometry.origin()), (10, 5, 45));
+    }
+
+    #[test]
+    fn geometry_refuses_oversized_modules() {
+        let error = Geometry::new(177, 4, Sizing::ModulePx(u32::MAX / 100)).unwrap_err();
+        assert!(error.to_string().starts_with("Module size too large"), "{}", error);
+        assert!(Geometry::new(177, 4, Sizing::ModulePx(MAX_CANVAS_PX / 185 + 1)).is_err());
+        assert_eq!(Geometry::new(177, 4, Sizing::ModulePx(MAX_CANVAS_PX / 185)).unwrap().size, MAX_CANVAS_PX / 185 * 185);
+    }
+
+    #[test]
+    fn geometry_refuses_oversized_canvas_and_margin() {
+        assert!(Geometry::new(21, 4, Sizing::Canvas(MAX_CANVAS_PX + 1)).is_err());
+        assert!(Geometry::new(21, u32::MAX / 2, Sizing::ModulePx(1)).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+//! Reed-Solomon error correction over GF(256), as used by QR codes.
+//!
+//! Codewords are polynomial coefficients with the highest degree first and the
+//! generator polynomial has the roots α^0 … α^(n-1) with α = 2 modulo
+//! x^8 + x^4 + x^3 + x^2 + 1.
+
+const PRIMITIVE: u16 = 0x11d;
+
+struct Tables {
+    exp: [u8; 512],
+    log: [u8; 256],
+}
+
+const fn build_tables() -> Tables {
+    let mut exp = [0u8; 512];
+    let mut log = [0u8; 256];
+    let mut value: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = value as u8;
+        exp[i + 255] = value as u8;
+        log[value as usize] = i as u8;
+        value <<= 1;
+        if value & 0x100 != 0 {
+            value ^= PRIMITIVE;
+        }
+        i += 1;
+    }
+    Tables { exp, log }
+}
+
+static TABLES: Tables = build_tables();
+
+fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    TABLES.exp[TABLES.log[a as usize] as usize + TABLES.log[b as usize] as usize]
+}
+
+fn div(a: u8, b: u8) -> u8 {
+    debug_assert!(b != 0, "division by zero in GF(256)");
+    if a == 0 {
+        return 0;
+    }
+    TABLES.exp[TABLES.log[a as usize] as usize + 255 - TABLES.log[b as usize] as usize]
+}
+
+/// α^power.
+fn alpha_pow(power: usize) -> u8 {
+    TABLES.exp[power % 255]
+}
+
+/// Evaluates a polynomial given lowest degree first.
+fn eval_low_first(poly: &[u8], x: u8) -> u8 {
+    poly.iter().rev().fold(0, |acc, &c| mul(acc, x) ^ c)
+}
+
+/// Syndromes S_0 … S_(ec_len-1) of `block`; all zero means no errors.
+fn compute_syndromes(block: &[u8], ec_len: usize) -> Vec<u8> {
+    (0..ec_len)
+        .map(|j| {
+            let x = alpha_pow(j);
+            block.iter().fold(0, |acc, &c| mul(acc, x) ^ c)
+        })
+        .collect()
+}
+
+/// Corrects `block` (data followed by `ec_len` error correction codewords) in
+/// place and returns the number of corrected codewords.
+///
+/// Fails when the block has more errors than the code can correct.
+pub fn correct(block: &mut [u8], ec_len: usize) -> Result<usize, Box<dyn std::error::Error>> {
+    let syndromes = compute_syndromes(block, ec_len);
+    if syndromes.iter().all(|&s| s == 0) {
+        return Ok(0);
+    }
+
+    // Berlekamp-Massey: error locator polynomial, lowest degree first
+    let mut locator = vec![1u8];
+    let mut previous = vec![1u8];
+    let mut errors = 0;
+    let mut shift = 1;
+    let mut last_discrepancy = 1u8;
+    for n in 0..ec_len {
+        let discrepancy = (1..=errors.min(locator.len() - 1))
+            .fold(syndromes[n], |acc, i| acc ^ mul(locator[i], syndromes[n - i]));
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+        let factor = div(discrepancy, last_discrepancy);
+        let mut updated = locator.clone();
+        if updated.len() < previous.len() + shift {
+            updated.resize(previous.len() + shift, 0);
+        }
+        for (i, &c) in previous.iter().enumerate() {
+            updated[i + shift] ^= mul(factor, c);
+        }
+        if 2 * errors <= n {
+            previous = locator;
+            errors = n + 1 - errors;
+            last_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            shift += 1;
+        }
+        locator = updated;
+    }
+    while locator.len() > 1 && locator.last() == Some(&0) {
+        locator.pop();
+    }
+    let degree = locator.len() - 1;
+    if degree == 0 || 2 * degree > ec_len {
+        return Err("Too many errors to correct".into());
+    }
+
+    // Chien search: an error at codeword index i has locator X = α^(n-1-i)
+    let n = block.len();
+    let positions: Vec<usize> = (0..n)
+        .filter(|&i| eval_low_first(&locator, alpha_pow(255 - (n - 1 - i) % 255)) == 0)
+        .collect();
+    if positions.len() != degree {
+        return Err("Too many errors to correct".into());
+    }
+
+    // Forney: error evaluator Ω = S·Λ mod x^ec_len
+    let mut evaluator = vec![0u8; ec_len];
+    for (i, &s) in syndromes.iter().enumerate() {
+        for (j, &l) in locator.iter().enumerate() {
+            if i + j < ec_len {
+                evaluator[i + j] ^= mul(s, l);
+            }
+        }
+    }
+    // Formal derivative keeps only the odd powers
+    let derivative: Vec<u8> = locator
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, &c)| if i % 2 == 1 { c } else { 0 })
+        .collect();
+
+    for &i in &positions {
+        let x = alpha_pow(n - 1 - i);
+        let x_inv = div(1, x);
+        let denominator = eval_low_first(&derivative, x_inv);
+        if denominator == 0 {
+            return Err("Too many errors to correct".into());
+        }
+        let magnitude = mul(x, div(eval_low_first(&evaluator, x_inv), denominator));
+        block[i] ^= magnitude;
+    }
+
+    if compute_syndromes(block, ec_len).iter().any(|&s| s != 0) {
+        return Err("Too many errors to correct".into());
+    }
+    Ok(positions.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qrcode::ec::create_error_correction_code;
+
+    /// A data block followed by its `ec_len` error correction codewords.
+    fn codeword_block(ec_len: usize) -> Vec<u8> {
+        let data: Vec<u8> = (0..40u8).map(|i| i.wrapping_mul(37).wrapping_add(11)).collect();
+        let mut block = data.clone();
+        block.extend(create_error_correction_code(&data, ec_len));
+        block
+    }
+
+    /// Flips `count` codewords spread over the block.
+    fn damage(block: &mut [u8], count: usize) {
+        let step = block.len() / count;
+        for i in 0..count {
+            block[i * step] ^= 0x5a ^ i as u8;
+        }
+    }
+
+    #[test]
+    fn clean_block_needs_no_correction() {
+        let mut block = codeword_block(10);
+        assert_eq!(correct(&mut block, 10).unwrap(), 0);
+    }
+
+    #[test]
+    fn corrects_exactly_t_errors() {
+        for ec_len in [10, 22, 30] {
+            let original = codeword_block(ec_len);
+            let mut block = original.clone();
+            damage(&mut block, ec_len / 2);
+            assert_eq!(correct(&mut block, ec_len).unwrap(), ec_len / 2, "{} ec codewords", ec_len);
+            assert_eq!(block, original);
+        }
+    }
+
+    #[test]
+    fn fails_at_t_plus_one_errors() {
+        for ec_len in [10, 22, 30] {
+            let mut block = codeword_block(ec_len);
+            damage(&mut block, ec_len / 2 + 1);
+            assert!(correct(&mut block, ec_len).is_err(), "{} ec codewords", ec_len);
+        }
+    }
+}
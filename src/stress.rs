@@ -0,0 +1,232 @@
+//! Scannability stress test: degrades a rendered symbol step by step and finds
+//! the severity at which the built-in reader stops decoding it.
+
+use crate::decode::{self, Perspective};
+use crate::raster::Geometry;
+use image::imageops::{self, FilterType};
+use image::{GrayImage, Luma};
+use std::error::Error;
+
+/// A simulated real-world degradation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Distortion {
+    Blur,
+    Noise,
+    Jpeg,
+    Rotation,
+    Perspective,
+    Downscale,
+    Occlusion,
+}
+
+impl Distortion {
+    pub const ALL: [Distortion; 7] = [
+        Distortion::Blur,
+        Distortion::Noise,
+        Distortion::Jpeg,
+        Distortion::Rotation,
+        Distortion::Perspective,
+        Distortion::Downscale,
+        Distortion::Occlusion,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Distortion::Blur => "Blur",
+            Distortion::Noise => "Noise",
+            Distortion::Jpeg => "JPEG recompression",
+            Distortion::Rotation => "Rotation",
+            Distortion::Perspective => "Perspective skew",
+            Distortion::Downscale => "Downscaling",
+            Distortion::Occlusion => "Occlusion",
+        }
+    }
+
+    /// Severity levels, from mild to severe.
+    pub fn levels(self) -> &'static [f64] {
+        match self {
+            // Gaussian sigma in modules
+            Distortion::Blur => &[0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0, 1.2, 1.5],
+            // Standard deviation in gray levels
+            Distortion::Noise => &[10.0, 20.0, 40.0, 60.0, 80.0, 100.0, 120.0, 150.0, 200.0, 250.0],
+            // JPEG quality
+            Distortion::Jpeg => &[90.0, 75.0, 50.0, 30.0, 20.0, 10.0, 5.0, 1.0],
+            // Degrees
+            Distortion::Rotation => &[5.0, 10.0, 15.0, 22.5, 30.0, 37.5, 45.0],
+            // Share of the top edge width removed on each side
+            Distortion::Perspective => &[0.05, 0.1, 0.15, 0.2, 0.25, 0.3, 0.35, 0.4],
+            // Pixels per module after scaling
+            Distortion::Downscale => &[4.0, 3.0, 2.5, 2.0, 1.5, 1.25, 1.0, 0.8],
+            // Share of the symbol area covered
+            Distortion::Occlusion => &[0.02, 0.05, 0.1, 0.15, 0.2, 0.25, 0.3, 0.35, 0.4, 0.5],
+        }
+    }
+
+    /// Human-readable severity.
+    pub fn describe(self, level: f64) -> String {
+        match self {
+            Distortion::Blur => format!("σ {:.1} modules", level),
+            Distortion::Noise => format!("σ {} gray levels", level),
+            Distortion::Jpeg => format!("quality {}", level),
+            Distortion::Rotation => format!("{}°", level),
+            Distortion::Perspective => format!("{:.0}% keystone", level * 100.0),
+            Distortion::Downscale => format!("{} px/module", level),
+            Distortion::Occlusion => format!("{:.0}% covered", level * 100.0),
+        }
+    }
+
+    /// Applies the distortion at `level` to `image`.
+    pub fn apply(self, image: &GrayImage, geometry: &Geometry, level: f64) -> Result<GrayImage, Box<dyn Error>> {
+        let module_px = f64::from(geometry.module_px);
+        Ok(match self {
+            Distortion::Blur => imageops::blur(image, (level * module_px) as f32),
+            Distortion::Noise => add_noise(image, level),
+            Distortion::Jpeg => {
+                let mut bytes = Vec::new();
+                image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, level as u8).encode_image(image)?;
+                image::load_from_memory(&bytes)?.to_luma8()
+            }
+            Distortion::Rotation => rotate(image, level.to_radians()),
+            Distortion::Perspective => keystone(image, level)?,
+            Distortion::Downscale => {
+                let scale = level / module_px;
+                let width = ((f64::from(image.width()) * scale).round() as u32).max(1);
+                let height = ((f64::from(image.height()) * scale).round() as u32).max(1);
+                imageops::resize(image, width, height, FilterType::Triangle)
+            }
+            Distortion::Occlusion => {
+                let symbol_px = f64::from(geometry.width) * module_px;
+                let side = (symbol_px * level.sqrt()).round() as u32;
+                let start = ((f64::from(geometry.origin()) + (symbol_px - f64::from(side)) / 2.0).round()) as u32;
+                let mut covered = image.clone();
+                for y in start..(start + side).min(image.height()) {
+                    for x in start..(start + side).min(image.width()) {
+                        covered.put_pixel(x, y, Luma([0]));
+                    }
+                }
+                covered
+            }
+        })
+    }
+
+    /// Whether a level applies to this layout; downscaling only makes sense
+    /// below the rendered module size.
+    fn applies(self, geometry: &Geometry, level: f64) -> bool {
+        self != Distortion::Downscale || level < f64::from(geometry.module_px)
+    }
+}
+
+/// Outcome of the severity sweep for one distortion.
+#[derive(Clone, Debug)]
+pub struct StressResult {
+    pub distortion: Distortion,
+    /// Most severe level that still decoded correctly.
+    pub passed: Option<f64>,
+    /// First level that failed, with the reason.
+    pub failed: Option<(f64, String)>,
+}
+
+impl StressResult {
+    /// One-line summary of the failure threshold.
+    pub fn summary(&self) -> String {
+        let d = self.distortion;
+        match (&self.passed, &self.failed) {
+            (Some(passed), Some((failed, _))) => {
+                format!("readable up to {}, fails at {}", d.describe(*passed), d.describe(*failed))
+            }
+            (None, Some((failed, _))) => format!("fails already at {}", d.describe(*failed)),
+            (Some(passed), None) => format!("readable at every tested level (up to {})", d.describe(*passed)),
+            (None, None) => "not tested at this size".to_string(),
+        }
+    }
+}
+
+/// Sweeps every distortion over increasing severity, stopping at the first
+/// level whose image no longer decodes to `expected`.
+pub fn run(image: &GrayImage, geometry: &Geometry, expected: &[u8]) -> Vec<StressResult> {
+    Distortion::ALL
+        .iter()
+        .map(|&distortion| {
+            let mut result = StressResult { distortion, passed: None, failed: None };
+            for &level in distortion.levels().iter().filter(|&&l| distortion.applies(geometry, l)) {
+                let outcome = distortion
+                    .apply(image, geometry, level)
+                    .and_then(|degraded| decode::detect(&degraded).map(|mut found| found.swap_remove(0).decoded));
+                match outcome {
+                    Ok(decoded) if decoded.data == expected => result.passed = Some(level),
+                    Ok(_) => {
+                        result.failed = Some((level, "decoded payload differs from the input".to_string()));
+                        break;
+                    }
+                    Err(e) => {
+                        result.failed = Some((level, e.to_string()));
+                        break;
+                    }
+                }
+            }
+            result
+        })
+        .collect()
+}
+
+/// Adds Gaussian noise with a fixed seed so runs are reproducible.
+fn add_noise(image: &GrayImage, sigma: f64) -> GrayImage {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut uniform = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        ((state >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    };
+    let mut noisy = image.clone();
+    for pixel in noisy.pixels_mut() {
+        // Box-Muller transform
+        let gaussian = (-2.0 * uniform().ln()).sqrt() * (std::f64::consts::TAU * uniform()).cos();
+        pixel.0[0] = (f64::from(pixel.0[0]) + gaussian * sigma).round().clamp(0.0, 255.0) as u8;
+    }
+    noisy
+}
+
+/// Bilinear sample with white outside the image.
+fn sample(image: &GrayImage, x: f64, y: f64) -> u8 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let pixel = |px: f64, py: f64| {
+        if px < 0.0 || py < 0.0 || px >= f64::from(image.width()) || py >= f64::from(image.height()) {
+            255.0
+        } else {
+            f64::from(image.get_pixel(px as u32, py as u32).0[0])
+        }
+    };
+    let top = pixel(x0, y0) * (1.0 - fx) + pixel(x0 + 1.0, y0) * fx;
+    let bottom = pixel(x0, y0 + 1.0) * (1.0 - fx) + pixel(x0 + 1.0, y0 + 1.0) * fx;
+    (top * (1.0 - fy) + bottom * fy).round() as u8
+}
+
+/// Rotates around the center onto a canvas large enough for the corners.
+fn rotate(image: &GrayImage, angle: f64) -> GrayImage {
+    let (w, h) = (f64::from(image.width()), f64::from(image.height()));
+    let (sin, cos) = angle.sin_cos();
+    let out_w = (w * cos.abs() + h * sin.abs()).ceil();
+    let out_h = (w * sin.abs() + h * cos.abs()).ceil();
+    GrayImage::from_fn(out_w as u32, out_h as u32, |x, y| {
+        let dx = f64::from(x) + 0.5 - out_w / 2.0;
+        let dy = f64::from(y) + 0.5 - out_h / 2.0;
+        let sx = cos * dx + sin * dy + w / 2.0 - 0.5;
+        let sy = -sin * dx + cos * dy + h / 2.0 - 0.5;
+        Luma([sample(image, sx, sy)])
+    })
+}
+
+/// Narrows the top edge as if the code were photographed from below.
+fn keystone(image: &GrayImage, amount: f64) -> Result<GrayImage, Box<dyn Error>> {
+    let (w, h) = (f64::from(image.width()), f64::from(image.height()));
+    let inset = w * amount;
+    let output = [(inset, 0.0), (w - inset, 0.0), (w, h), (0.0, h)];
+    let source = [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)];
+    let transform = Perspective::from_quads(output, source).ok_or("Degenerate perspective transform")?;
+    Ok(GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        let (sx, sy) = transform.map(f64::from(x) + 0.5, f64::from(y) + 0.5);
+        Luma([sample(image, sx - 0.5, sy - 0.5)])
+    }))
+}
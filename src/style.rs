@@ -0,0 +1,404 @@
+//! Visual styling of rendered symbols, shared by the raster and SVG renderers.
+
+use crate::frame::Frame;
+use crate::halftone::Halftone;
+use crate::label::Label;
+use crate::logo::Logo;
+use crate::raster;
+use crate::symbol::QrSymbol;
+use image::Rgba;
+use svg::node::element::{Definitions, LinearGradient, RadialGradient, Stop};
+
+/// Drawing shape of a single dark module.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ModuleShape {
+    #[default]
+    Square,
+    Circle,
+    /// Square with rounded corners.
+    Rounded,
+    Diamond,
+    /// Rounded only where a corner is exposed, so neighbouring modules merge
+    /// into smooth blobs.
+    Liquid,
+}
+
+impl ModuleShape {
+    pub const ALL: [ModuleShape; 5] = [
+        ModuleShape::Square,
+        ModuleShape::Circle,
+        ModuleShape::Rounded,
+        ModuleShape::Diamond,
+        ModuleShape::Liquid,
+    ];
+
+    pub fn parse(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        ModuleShape::ALL
+            .iter()
+            .copied()
+            .find(|shape| shape.name().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| format!("Unknown module shape: {}. Use square, circle, rounded, diamond or liquid", name).into())
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ModuleShape::Square => "square",
+            ModuleShape::Circle => "circle",
+            ModuleShape::Rounded => "rounded",
+            ModuleShape::Diamond => "diamond",
+            ModuleShape::Liquid => "liquid",
+        }
+    }
+
+    /// Corner radii in modules for the rounded shapes.
+    fn radii(self, corners: [bool; 4]) -> [f64; 4] {
+        let radius = match self {
+            ModuleShape::Liquid => 0.5,
+            _ => 0.25,
+        };
+        corners.map(|rounded| if rounded { radius } else { 0.0 })
+    }
+
+    /// Whether the point (`fx`, `fy`), relative to the module's top-left corner
+    /// in module units, is inside the shape. `corners` are the rounded corners
+    /// in the order top-left, top-right, bottom-right, bottom-left.
+    pub fn contains(self, corners: [bool; 4], fx: f64, fy: f64) -> bool {
+        let (dx, dy) = (fx - 0.5, fy - 0.5);
+        match self {
+            ModuleShape::Square => true,
+            ModuleShape::Circle => dx * dx + dy * dy <= 0.25,
+            ModuleShape::Diamond => dx.abs() + dy.abs() <= 0.5,
+            ModuleShape::Rounded | ModuleShape::Liquid => rounded_rect_contains(1.0, self.radii(corners), fx, fy),
+        }
+    }
+
+    /// SVG path data of the module whose top-left corner is at (`x`, `y`) in
+    /// module units.
+    pub fn svg_path(self, corners: [bool; 4], x: f64, y: f64) -> String {
+        match self {
+            ModuleShape::Square => format!("M{} {}h1v1h-1Z", x, y),
+            ModuleShape::Circle => format!("M{} {}a.5 .5 0 1 1 0 1a.5 .5 0 1 1 0-1Z", x + 0.5, y),
+            ModuleShape::Diamond => format!("M{} {}l.5 .5l-.5 .5l-.5-.5Z", x + 0.5, y),
+            ModuleShape::Rounded | ModuleShape::Liquid => rounded_rect_path(x, y, 1.0, self.radii(corners)),
+        }
+    }
+}
+
+/// Shape of a finder pattern's outer ring or inner eye.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EyeShape {
+    #[default]
+    Square,
+    Rounded,
+    Circle,
+    /// Two opposite corners fully rounded, mirrored between the finders.
+    Leaf,
+}
+
+impl EyeShape {
+    pub const ALL: [EyeShape; 4] = [EyeShape::Square, EyeShape::Rounded, EyeShape::Circle, EyeShape::Leaf];
+
+    pub fn parse(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        EyeShape::ALL
+            .iter()
+            .copied()
+            .find(|shape| shape.name().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| format!("Unknown eye shape: {}. Use square, rounded, circle or leaf", name).into())
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EyeShape::Square => "square",
+            EyeShape::Rounded => "rounded",
+            EyeShape::Circle => "circle",
+            EyeShape::Leaf => "leaf",
+        }
+    }
+
+    /// Corner radii of a `size` module square. Leaves round the top-left and
+    /// bottom-right corners, or the other pair when `mirrored`.
+    fn radii(self, size: f64, mirrored: bool) -> [f64; 4] {
+        let half = size / 2.0;
+        match (self, mirrored) {
+            (EyeShape::Square, _) => [0.0; 4],
+            (EyeShape::Rounded, _) => [size * 0.3; 4],
+            (EyeShape::Circle, _) => [half; 4],
+            (EyeShape::Leaf, false) => [half, 0.0, half, 0.0],
+            (EyeShape::Leaf, true) => [0.0, half, 0.0, half],
+        }
+    }
+}
+
+/// Geometry of a gradient over the symbol.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientKind {
+    /// Direction in degrees, 0 running left to right and 90 top to bottom.
+    Linear { angle: f64 },
+    /// Center relative to the symbol, from (0, 0) top-left to (1, 1)
+    /// bottom-right.
+    Radial { center: (f64, f64) },
+}
+
+/// Dark module fill running from the module color to `end`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub end: Rgba<u8>,
+}
+
+impl Gradient {
+    /// Parses `linear`, `linear:45` (angle in degrees), `radial` or
+    /// `radial:0.3,0.3` (center relative to the symbol).
+    pub fn parse(spec: &str, end: Rgba<u8>) -> Result<Self, Box<dyn std::error::Error>> {
+        let invalid = || format!("Invalid gradient: {}. Use linear, linear:ANGLE, radial or radial:X,Y", spec);
+        let (kind, params) = spec.trim().split_once(':').unwrap_or((spec.trim(), ""));
+        let kind = match (kind.to_lowercase().as_str(), params) {
+            ("linear", "") => GradientKind::Linear { angle: 0.0 },
+            ("linear", angle) => GradientKind::Linear { angle: angle.trim().parse().map_err(|_| invalid())? },
+            ("radial", "") => GradientKind::Radial { center: (0.5, 0.5) },
+            ("radial", center) => {
+                let (x, y) = center.split_once(',').ok_or_else(invalid)?;
+                let x: f64 = x.trim().parse().map_err(|_| invalid())?;
+                let y: f64 = y.trim().parse().map_err(|_| invalid())?;
+                if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) {
+                    return Err(format!("Gradient center must be between 0 and 1: {}", spec).into());
+                }
+                GradientKind::Radial { center: (x, y) }
+            }
+            _ => return Err(invalid().into()),
+        };
+        Ok(Self { kind, end })
+    }
+
+    pub fn describe(&self) -> String {
+        match self.kind {
+            GradientKind::Linear { angle } => format!("linear gradient at {}°", angle),
+            GradientKind::Radial { center: (x, y) } => format!("radial gradient from ({:.2}, {:.2})", x, y),
+        }
+    }
+
+    /// Position along the gradient, 0 at the start and 1 at the end color, of
+    /// the point (`u`, `v`) relative to the symbol.
+    pub fn position(&self, u: f64, v: f64) -> f64 {
+        let t = match self.kind {
+            GradientKind::Linear { angle } => {
+                let (sin, cos) = angle.to_radians().sin_cos();
+                // Scaled so the symbol's corners span exactly 0 to 1
+                0.5 + ((u - 0.5) * cos + (v - 0.5) * sin) / (cos.abs() + sin.abs())
+            }
+            GradientKind::Radial { center } => (u - center.0).hypot(v - center.1) / radial_extent(center),
+        };
+        t.clamp(0.0, 1.0)
+    }
+
+    /// Color at (`u`, `v`) relative to the symbol for a gradient from `start`.
+    pub fn color_at(&self, start: Rgba<u8>, u: f64, v: f64) -> Rgba<u8> {
+        let t = self.position(u, v);
+        let mix = |i: usize| (f64::from(start.0[i]) * (1.0 - t) + f64::from(self.end.0[i]) * t).round() as u8;
+        Rgba([mix(0), mix(1), mix(2), mix(3)])
+    }
+
+    /// Start and end points (x1, y1, x2, y2) of a linear gradient over a
+    /// symbol at `origin` that is `width` units wide, for SVG.
+    pub fn linear_endpoints(angle: f64, origin: f64, width: f64) -> [f64; 4] {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let center = origin + width / 2.0;
+        let half = width / 2.0 * (cos.abs() + sin.abs());
+        [center - cos * half, center - sin * half, center + cos * half, center + sin * half]
+    }
+
+    /// Center and radius (cx, cy, r) of a radial gradient over a symbol at
+    /// `origin` that is `width` units wide, for SVG.
+    pub fn radial_circle(center: (f64, f64), origin: f64, width: f64) -> [f64; 3] {
+        [origin + center.0 * width, origin + center.1 * width, radial_extent(center) * width]
+    }
+
+    /// SVG definition of the gradient from `start` as [`GRADIENT_ID`], over a
+    /// symbol at `origin` that is `width` units wide.
+    pub fn svg_definitions(&self, start: Rgba<u8>, origin: f64, width: f64) -> Definitions {
+        let stop = |offset: u8, color: Rgba<u8>| {
+            let stop = Stop::new()
+                .set("offset", offset)
+                .set("stop-color", format!("#{}", raster::hex(color)));
+            if raster::is_transparent(color) {
+                stop.set("stop-opacity", format!("{:.3}", f64::from(color.0[3]) / 255.0))
+            } else {
+                stop
+            }
+        };
+        let definitions = Definitions::new();
+        match self.kind {
+            GradientKind::Linear { angle } => {
+                let [x1, y1, x2, y2] = Self::linear_endpoints(angle, origin, width);
+                definitions.add(
+                    LinearGradient::new()
+                        .set("id", GRADIENT_ID)
+                        .set("gradientUnits", "userSpaceOnUse")
+                        .set("x1", x1)
+                        .set("y1", y1)
+                        .set("x2", x2)
+                        .set("y2", y2)
+                        .add(stop(0, start))
+                        .add(stop(1, self.end)),
+                )
+            }
+            GradientKind::Radial { center } => {
+                let [cx, cy, r] = Self::radial_circle(center, origin, width);
+                definitions.add(
+                    RadialGradient::new()
+                        .set("id", GRADIENT_ID)
+                        .set("gradientUnits", "userSpaceOnUse")
+                        .set("cx", cx)
+                        .set("cy", cy)
+                        .set("r", r)
+                        .add(stop(0, start))
+                        .add(stop(1, self.end)),
+                )
+            }
+        }
+    }
+}
+
+/// Element id of the gradient in SVG output.
+pub const GRADIENT_ID: &str = "qr-gradient";
+
+/// Distance from `center` to the farthest corner of the unit square.
+fn radial_extent(center: (f64, f64)) -> f64 {
+    let dx = center.0.max(1.0 - center.0);
+    let dy = center.1.max(1.0 - center.1);
+    dx.hypot(dy)
+}
+
+/// How a symbol is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Style<'a> {
+    pub shape: ModuleShape,
+    /// Outer 7x7 ring of the finder patterns.
+    pub eye_frame: EyeShape,
+    /// Inner 3x3 eye of the finder patterns.
+    pub eye: EyeShape,
+    /// Finder pattern color, `None` for the module color.
+    pub eye_color: Option<Rgba<u8>>,
+    /// Gradient fill of the dark modules, starting at the module color.
+    pub gradient: Option<Gradient>,
+    /// Logo drawn over the center of the symbol.
+    pub logo: Option<&'a Logo>,
+    /// Frame and caption around the code, added by the bitmap and SVG
+    /// writers after rendering.
+    pub frame: Option<&'a Frame>,
+    /// Text label under the code (and frame), added by the writers last.
+    pub label: Option<&'a Label>,
+    /// Photo blended into the data modules; replaces the module shapes.
+    pub halftone: Option<&'a Halftone>,
+}
+
+impl Style<'_> {
+    /// Whether every module and finder is a plain square, so rendering needs
+    /// no anti-aliasing.
+    pub fn is_square(&self) -> bool {
+        self.shape == ModuleShape::Square && self.eye_frame == EyeShape::Square && self.eye == EyeShape::Square
+    }
+
+    /// Whether finder patterns are drawn as whole shapes instead of modules.
+    pub fn custom_eyes(&self) -> bool {
+        self.eye_frame != EyeShape::Square || self.eye != EyeShape::Square || self.eye_color.is_some()
+    }
+
+    /// Whether the point (`u`, `v`), in modules from the top-left corner of
+    /// finder pattern `finder`, is dark.
+    pub fn finder_contains(&self, finder: usize, u: f64, v: f64) -> bool {
+        let outer = self.eye_frame.radii(7.0, finder > 0);
+        let hole = outer.map(|r| (r - 1.0).max(0.0));
+        let ring = rounded_rect_contains(7.0, outer, u, v) && !rounded_rect_contains(5.0, hole, u - 1.0, v - 1.0);
+        ring || rounded_rect_contains(3.0, self.eye.radii(3.0, finder > 0), u - 2.0, v - 2.0)
+    }
+
+    /// SVG path data of the ring (to fill with the even-odd rule) and of the
+    /// eye of finder pattern `finder`, whose top-left corner is at (`x`, `y`).
+    pub fn finder_paths(&self, finder: usize, x: f64, y: f64) -> (String, String) {
+        let outer = self.eye_frame.radii(7.0, finder > 0);
+        let hole = outer.map(|r| (r - 1.0).max(0.0));
+        let ring = rounded_rect_path(x, y, 7.0, outer) + &rounded_rect_path(x + 1.0, y + 1.0, 5.0, hole);
+        let eye = rounded_rect_path(x + 2.0, y + 2.0, 3.0, self.eye.radii(3.0, finder > 0));
+        (ring, eye)
+    }
+
+    /// Shape and rounded corners of the dark module at (`x`, `y`). Finder
+    /// patterns always stay square so scanners can locate the symbol.
+    pub fn module(&self, symbol: &QrSymbol, x: usize, y: usize) -> (ModuleShape, [bool; 4]) {
+        if finder_index(symbol.width(), x, y).is_some() {
+            return (ModuleShape::Square, [false; 4]);
+        }
+        let corners = match self.shape {
+            ModuleShape::Rounded => [true; 4],
+            ModuleShape::Liquid => {
+                let dark = |dx: isize, dy: isize| {
+                    let (nx, ny) = (x as isize + dx, y as isize + dy);
+                    let width = symbol.width() as isize;
+                    (0..width).contains(&nx)
+                        && (0..width).contains(&ny)
+                        && symbol[(nx as usize, ny as usize)] == qrcode::Color::Dark
+                };
+                let (left, right, up, down) = (dark(-1, 0), dark(1, 0), dark(0, -1), dark(0, 1));
+                [!left && !up, !right && !up, !right && !down, !left && !down]
+            }
+            _ => [false; 4],
+        };
+        (self.shape, corners)
+    }
+}
+
+/// Top-left corners of the top-left, top-right and bottom-left finder
+/// patterns of a symbol `width` modules wide.
+pub fn finder_origins(width: usize) -> [(usize, usize); 3] {
+    [(0, 0), (width - 7, 0), (0, width - 7)]
+}
+
+/// Which finder pattern, if any, covers the module at (`x`, `y`).
+pub fn finder_index(width: usize, x: usize, y: usize) -> Option<usize> {
+    finder_origins(width)
+        .iter()
+        .position(|&(fx, fy)| (fx..fx + 7).contains(&x) && (fy..fy + 7).contains(&y))
+}
+
+/// Whether (`u`, `v`) is inside a `size` square at the origin whose corners
+/// (top-left, top-right, bottom-right, bottom-left) have the given radii.
+fn rounded_rect_contains(size: f64, radii: [f64; 4], u: f64, v: f64) -> bool {
+    if !(0.0..=size).contains(&u) || !(0.0..=size).contains(&v) {
+        return false;
+    }
+    let half = size / 2.0;
+    let corner = match (u < half, v < half) {
+        (true, true) => 0,
+        (false, true) => 1,
+        (false, false) => 2,
+        (true, false) => 3,
+    };
+    let r = radii[corner];
+    // Distance into the corner square, from the rounding center
+    let cx = ((u - half).abs() - (half - r)).max(0.0);
+    let cy = ((v - half).abs() - (half - r)).max(0.0);
+    cx * cx + cy * cy <= r * r
+}
+
+/// SVG path data of a `size` square at (`x`, `y`) with rounded corners.
+fn rounded_rect_path(x: f64, y: f64, size: f64, radii: [f64; 4]) -> String {
+    let [tl, tr, br, bl] = radii;
+    let mut d = format!("M{} {}H{}", x + tl, y, x + size - tr);
+    if tr > 0.0 {
+        d += &format!("a{} {} 0 0 1 {} {}", tr, tr, tr, tr);
+    }
+    d += &format!("V{}", y + size - br);
+    if br > 0.0 {
+        d += &format!("a{} {} 0 0 1 {} {}", br, br, -br, br);
+    }
+    d += &format!("H{}", x + bl);
+    if bl > 0.0 {
+        d += &format!("a{} {} 0 0 1 {} {}", bl, bl, -bl, -bl);
+    }
+    d += &format!("V{}", y + tl);
+    if tl > 0.0 {
+        d += &format!("a{} {} 0 0 1 {} {}", tl, tl, tl, -tl);
+    }
+    d + "Z"
+}
@@ -0,0 +1,580 @@
+//! SVG output: the complete document, with the modules as one element each
+//! or merged into a single path that traces the outline of each connected
+//! dark area; and the title, description and metadata that make the document
+//! self-describing.
+
+use crate::frame::escape_xml;
+use crate::raster::{self, Geometry};
+use crate::style::{finder_index, finder_origins, ModuleShape, Style, GRADIENT_ID};
+use crate::symbol::QrSymbol;
+use image::Rgba;
+use qrcode::Version;
+use std::collections::BTreeMap;
+use svg::node::element::{Description, Element, Group, Path, Rectangle, Title};
+use svg::node::Text;
+use svg::{Document, Node};
+
+/// Namespace of the QR-specific metadata properties.
+pub const METADATA_NAMESPACE: &str = "https://github.com/fra2404/qr-forge#";
+
+/// How the SVG writer lays out, encodes and describes the symbol.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SvgOptions<'a> {
+    /// Printed size declared in the document, in millimetres.
+    pub physical_mm: Option<f64>,
+    /// Trace the dark modules into one path instead of one element each.
+    pub merge_paths: bool,
+    /// Ask renderers not to anti-alias square modules.
+    pub crisp_edges: bool,
+    /// Decimals kept in path coordinates, `None` to keep them as computed.
+    pub precision: Option<usize>,
+    /// Encoded text named in the title, description and metadata, `None` to
+    /// leave it out for sensitive content.
+    pub payload: Option<&'a str>,
+    /// Generator settings recorded in the metadata, as name and value.
+    pub settings: &'a [(&'a str, String)],
+}
+
+impl Default for SvgOptions<'_> {
+    fn default() -> Self {
+        Self { physical_mm: None, merge_paths: false, crisp_edges: true, precision: None, payload: None, settings: &[] }
+    }
+}
+
+/// Builds the SVG document of `symbol` on the canvas described by `geometry`:
+/// background, modules, custom finder patterns, logo, frame and label, then
+/// the title, description and metadata. The viewBox counts modules so the
+/// code fills the exact output size with fractional module sizes.
+pub fn document(
+    symbol: &QrSymbol,
+    geometry: &Geometry,
+    style: &Style,
+    dark: Rgba<u8>,
+    light: Rgba<u8>,
+    options: &SvgOptions,
+) -> Document {
+    let width = geometry.width;
+    let margin = geometry.margin;
+    let total_modules = geometry.total_modules();
+    // Physical documents declare their size in millimetres; a frame grows
+    // the canvas around the code
+    let dimension = match options.physical_mm {
+        Some(mm) => format!("{}mm", (mm * 1000.0).round() / 1000.0),
+        None => style.frame.map_or(geometry.size, |frame| frame.canvas).to_string(),
+    };
+
+    let mut document = Document::new()
+        .set("viewBox", (0, 0, total_modules, total_modules))
+        .set("width", dimension.clone())
+        .set("height", dimension.clone())
+        .set("xmlns", "http://www.w3.org/2000/svg");
+
+    // Colored background, left out entirely when transparent
+    if light.0[3] > 0 {
+        let mut background = Rectangle::new()
+            .set("x", 0)
+            .set("y", 0)
+            .set("width", total_modules)
+            .set("height", total_modules)
+            .set("fill", format!("#{}", raster::hex(light)));
+        if raster::is_transparent(light) {
+            background = background.set("fill-opacity", format!("{:.3}", f64::from(light.0[3]) / 255.0));
+        }
+        document = document.add(background);
+    }
+
+    // Group for all modules with custom color or gradient
+    let mut modules = match style.gradient {
+        Some(gradient) => {
+            document = document.add(gradient.svg_definitions(dark, f64::from(margin), f64::from(width)));
+            Group::new().set("fill", format!("url(#{})", GRADIENT_ID))
+        }
+        None => filled(Group::new(), dark),
+    };
+    if style.shape == ModuleShape::Square && options.crisp_edges {
+        modules = modules.set("shape-rendering", "crispEdges");
+    }
+    document = document.add(if options.merge_paths {
+        merged_modules(modules, symbol, style, margin, options.precision).0
+    } else {
+        module_elements(modules, symbol, style, margin, options.precision)
+    });
+
+    // Finder patterns drawn as whole shapes in their own color
+    if style.custom_eyes() {
+        let mut eyes = match (style.eye_color, style.gradient) {
+            (None, Some(_)) => Group::new().set("fill", format!("url(#{})", GRADIENT_ID)),
+            (eye_color, _) => filled(Group::new(), eye_color.unwrap_or(dark)),
+        };
+        for (finder, &(fx, fy)) in finder_origins(width as usize).iter().enumerate() {
+            let (ring, eye) = style.finder_paths(finder, f64::from(fx as u32 + margin), f64::from(fy as u32 + margin));
+            eyes = eyes
+                .add(Path::new().set("d", with_precision(&ring, options.precision)).set("fill-rule", "evenodd"))
+                .add(Path::new().set("d", with_precision(&eye, options.precision)));
+        }
+        document = document.add(eyes);
+    }
+
+    // Logo on its plate, embedded as a data URI
+    if let Some(logo) = style.logo {
+        document = document.add(logo.svg_group(width as usize, margin, options.precision));
+    }
+
+    // Frame and caption around the code, in canvas pixels
+    if let Some(frame) = style.frame {
+        document = frame.wrap_svg(document, &dimension, light);
+    }
+
+    // Label below everything, extending the canvas downwards
+    if let Some(label) = style.label {
+        let canvas = style.frame.map_or(geometry.size, |frame| frame.canvas);
+        document = label.wrap_svg(document, canvas, light);
+        if let Some(mm) = options.physical_mm {
+            let height_mm = mm * f64::from(label.canvas_height(canvas)) / f64::from(canvas);
+            document = document
+                .set("width", dimension.clone())
+                .set("height", format!("{}mm", (height_mm * 1000.0).round() / 1000.0));
+        }
+    }
+
+    describe(document, symbol, options)
+}
+
+/// `group` filled with `color`, with its opacity when translucent.
+fn filled(group: Group, color: Rgba<u8>) -> Group {
+    let group = group.set("fill", format!("#{}", raster::hex(color)));
+    if raster::is_transparent(color) {
+        group.set("fill-opacity", format!("{:.3}", f64::from(color.0[3]) / 255.0))
+    } else {
+        group
+    }
+}
+
+/// Short accessible name of a code encoding `payload`.
+pub fn accessible_name(payload: Option<&str>) -> String {
+    match payload {
+        Some(url) if url.starts_with("http://") || url.starts_with("https://") => format!("QR code linking to {}", url),
+        Some(text) => format!("QR code containing {}", text),
+        None => "QR code".to_string(),
+    }
+}
+
+/// Makes `document` self-describing: `role="img"` with an `aria-label`, a
+/// `<title>` and `<desc>` for screen readers, and RDF `<metadata>` with the
+/// payload, symbol parameters and generator settings. The payload is left
+/// out everywhere when `options.payload` is `None`.
+pub fn describe(mut document: Document, symbol: &QrSymbol, options: &SvgOptions) -> Document {
+    let name = accessible_name(options.payload);
+    let version = match symbol.version() {
+        Version::Normal(v) => format!("{}", v),
+        Version::Micro(v) => format!("M{}", v),
+    };
+    let ec_level = format!("{:?}", symbol.error_correction_level());
+    let mut description = format!(
+        "QR code version {}, {}x{} modules, error correction level {}, mask pattern {}.",
+        version,
+        symbol.width(),
+        symbol.width(),
+        ec_level,
+        symbol.mask()
+    );
+    match options.payload {
+        Some(url) if url.starts_with("http://") || url.starts_with("https://") => {
+            description += &format!(" Scan it to open {}.", url)
+        }
+        Some(text) => description += &format!(" It encodes: {}", text),
+        None => description += " The encoded content is not included.",
+    }
+    document.assign("role", "img");
+    document.assign("aria-label", escape_attribute(&name));
+
+    let mut properties = vec![
+        text_element("dc:title", &name),
+        text_element("dc:description", &description),
+        text_element("dc:format", "image/svg+xml"),
+        text_element("dc:creator", &format!("qr-forge {}", env!("CARGO_PKG_VERSION"))),
+    ];
+    if let Some(payload) = options.payload {
+        properties.push(text_element("qr:payload", payload));
+    }
+    properties.push(text_element("qr:version", &version));
+    properties.push(text_element("qr:errorCorrection", &ec_level));
+    properties.push(text_element("qr:mask", &symbol.mask().to_string()));
+    properties.push(text_element("qr:modules", &symbol.width().to_string()));
+    for (setting, value) in options.settings {
+        let mut property = text_element("qr:setting", value);
+        property.assign("qr:name", escape_attribute(setting));
+        properties.push(property);
+    }
+    let mut about = Element::new("rdf:Description");
+    about.assign("rdf:about", "");
+    for property in properties {
+        about.append(property);
+    }
+    let mut rdf = Element::new("rdf:RDF");
+    rdf.assign("xmlns:rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#");
+    rdf.assign("xmlns:dc", "http://purl.org/dc/elements/1.1/");
+    rdf.assign("xmlns:qr", METADATA_NAMESPACE);
+    rdf.append(about);
+    let mut metadata = Element::new("metadata");
+    metadata.append(rdf);
+
+    // Title and description come first so assistive technology finds them
+    let children = document.get_children_mut();
+    children.insert(0, Box::new(Title::new().add(Text::new(escape_xml(&name)))));
+    children.insert(1, Box::new(Description::new().add(Text::new(escape_xml(&description)))));
+    children.insert(2, Box::new(metadata));
+    document
+}
+
+/// Element `name` holding escaped `text`.
+fn text_element(name: &str, text: &str) -> Element {
+    let mut element = Element::new(name);
+    element.append(Text::new(escape_xml(text)));
+    element
+}
+
+/// Escapes an attribute value; the svg crate writes attributes verbatim and
+/// drops values with both kinds of quotes.
+fn escape_attribute(value: &str) -> String {
+    escape_xml(value).replace('"', "&quot;")
+}
+
+/// Whether the module at (`x`, `y`) is drawn as a module shape: dark, not
+/// cleared for the logo and not part of a finder drawn as a whole.
+fn draws_module(symbol: &QrSymbol, style: &Style, x: usize, y: usize) -> bool {
+    let width = symbol.width();
+    let cleared = style.logo.is_some_and(|logo| logo.clear && logo.covers(width, x, y));
+    let custom_eye = style.custom_eyes() && finder_index(width, x, y).is_some();
+    symbol[(x, y)] == qrcode::Color::Dark && !cleared && !custom_eye
+}
+
+/// Adds the dark modules to `group` as one `<rect>` or `<path>` each, in
+/// module units with the symbol starting at `margin`.
+pub fn module_elements(mut group: Group, symbol: &QrSymbol, style: &Style, margin: u32, precision: Option<usize>) -> Group {
+    let width = symbol.width();
+    for y in 0..width {
+        for x in 0..width {
+            if !draws_module(symbol, style, x, y) {
+                continue;
+            }
+            let (shape, corners) = style.module(symbol, x, y);
+            let (left, top) = (x as u32 + margin, y as u32 + margin);
+            if shape == ModuleShape::Square {
+                group = group.add(Rectangle::new().set("x", left).set("y", top).set("width", 1).set("height", 1));
+            } else {
+                let d = shape.svg_path(corners, f64::from(left), f64::from(top));
+                group = group.add(Path::new().set("d", with_precision(&d, precision)));
+            }
+        }
+    }
+    group
+}
+
+/// Adds the dark modules to `group` as a single `<path>`: square modules are
+/// traced into the outlines of the areas they form, other shapes follow as
+/// subpaths. Returns the group and the number of subpaths.
+pub fn merged_modules(group: Group, symbol: &QrSymbol, style: &Style, margin: u32, precision: Option<usize>) -> (Group, usize) {
+    let (d, subpaths) = merged_path(symbol, style, margin);
+    if d.is_empty() {
+        return (group, 0);
+    }
+    (group.add(Path::new().set("d", with_precision(&d, precision))), subpaths)
+}
+
+/// Path data of the dark modules as traced outlines of the square modules
+/// followed by the other shapes, in module units with the symbol starting at
+/// `margin`, and the number of subpaths.
+pub fn merged_path(symbol: &QrSymbol, style: &Style, margin: u32) -> (String, usize) {
+    let width = symbol.width();
+    let square = |x: usize, y: usize| draws_module(symbol, style, x, y) && style.module(symbol, x, y).0 == ModuleShape::Square;
+    let outlines = trace(width, square);
+    let mut d = outline_path(&outlines, margin);
+    let mut subpaths = outlines.len();
+    for y in 0..width {
+        for x in 0..width {
+            if !draws_module(symbol, style, x, y) || square(x, y) {
+                continue;
+            }
+            let (shape, corners) = style.module(symbol, x, y);
+            d += &shape.svg_path(corners, f64::from(x as u32 + margin), f64::from(y as u32 + margin));
+            subpaths += 1;
+        }
+    }
+    (d, subpaths)
+}
+
+/// Outlines of the areas formed by the cells of a `width` square grid for
+/// which `dark` holds, as closed polygons of cell corners. Outer outlines run
+/// clockwise and holes counterclockwise, so the nonzero fill rule fills
+/// exactly the dark cells.
+pub fn trace(width: usize, dark: impl Fn(usize, usize) -> bool) -> Vec<Vec<(i64, i64)>> {
+    // Directions right, down, left, up; turning right is the next one
+    const STEPS: [(i64, i64); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let is_dark = |x: i64, y: i64| x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < width && dark(x as usize, y as usize);
+
+    // Boundary edges by start corner, keyed (y, x) so outlines start at the
+    // top-left; every edge keeps its dark cell on the right
+    let mut edges: BTreeMap<(i64, i64), Vec<usize>> = BTreeMap::new();
+    for y in 0..width as i64 {
+        for x in 0..width as i64 {
+            if !is_dark(x, y) {
+                continue;
+            }
+            let sides = [
+                (!is_dark(x, y - 1), (x, y), 0),
+                (!is_dark(x + 1, y), (x + 1, y), 1),
+                (!is_dark(x, y + 1), (x + 1, y + 1), 2),
+                (!is_dark(x - 1, y), (x, y + 1), 3),
+            ];
+            for (open, (cx, cy), direction) in sides {
+                if open {
+                    edges.entry((cy, cx)).or_default().push(direction);
+                }
+            }
+        }
+    }
+
+    let mut outlines = Vec::new();
+    while let Some((&(sy, sx), _)) = edges.iter().next() {
+        let start = (sx, sy);
+        let mut direction = take_edge(&mut edges, start, &[0, 1, 2, 3]).unwrap_or(0);
+        let mut corners = vec![start];
+        let mut position = start;
+        loop {
+            position = (position.0 + STEPS[direction].0, position.1 + STEPS[direction].1);
+            // Prefer turning right where two outlines touch at a corner
+            let turns = [(direction + 1) % 4, direction, (direction + 3) % 4];
+            match take_edge(&mut edges, position, &turns) {
+                Some(next) => {
+                    if next != direction {
+                        corners.push(position);
+                    }
+                    direction = next;
+                }
+                None => break,
+            }
+        }
+        outlines.push(corners);
+    }
+    outlines
+}
+
+/// Removes and returns the first edge leaving `corner` in one of `directions`.
+fn take_edge(edges: &mut BTreeMap<(i64, i64), Vec<usize>>, corner: (i64, i64), directions: &[usize]) -> Option<usize> {
+    let key = (corner.1, corner.0);
+    let leaving = edges.get_mut(&key)?;
+    let index = directions.iter().find_map(|d| leaving.iter().position(|e| e == d))?;
+    let direction = leaving.remove(index);
+    if leaving.is_empty() {
+        edges.remove(&key);
+    }
+    Some(direction)
+}
+
+/// Path data for traced outlines, shifted by `margin` modules, using
+/// relative horizontal and vertical lines.
+fn outline_path(outlines: &[Vec<(i64, i64)>], margin: u32) -> String {
+    let margin = i64::from(margin);
+    let mut d = String::new();
+    for corners in outlines {
+        let (x, y) = corners[0];
+        d += &format!("M{} {}", x + margin, y + margin);
+        for pair in corners.windows(2) {
+            let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+            if y0 == y1 {
+                d += &format!("h{}", x1 - x0);
+            } else {
+                d += &format!("v{}", y1 - y0);
+            }
+        }
+        d += "Z";
+    }
+    d
+}
+
+/// Rounds the coordinates in path data `d` to `precision` decimals, dropping
+/// trailing zeros and leading zeros before the decimal point. Arc radii,
+/// rotations and flags are kept as written: rounding a radius of .5 to 0
+/// would turn circles into straight lines.
+pub fn with_precision(d: &str, precision: Option<usize>) -> String {
+    let Some(precision) = precision else {
+        return d.to_string();
+    };
+    let mut out = String::with_capacity(d.len());
+    let chars: Vec<char> = d.chars().collect();
+    let mut command = 'M';
+    let mut argument = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let starts_number = chars[i].is_ascii_digit()
+            || chars[i] == '.'
+            || (chars[i] == '-' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit() || *c == '.'));
+        if !starts_number {
+            if chars[i].is_ascii_alphabetic() {
+                command = chars[i];
+                argument = 0;
+            }
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        // A number ends at anything but a digit, or at a second point
+        let start = i;
+        let mut seen_point = false;
+        i += 1;
+        if chars[start] == '.' {
+            seen_point = true;
+        }
+        while i < chars.len() && (chars[i].is_ascii_digit() || (chars[i] == '.' && !seen_point)) {
+            seen_point |= chars[i] == '.';
+            i += 1;
+        }
+        let token: String = chars[start..i].iter().collect();
+        // Arcs take rx ry rotation large-arc sweep x y, repeated
+        let coordinate = !command.eq_ignore_ascii_case(&'a') || argument % 7 >= 5;
+        argument += 1;
+        match token.parse::<f64>() {
+            Ok(value) if coordinate => out += &format_number(value, precision),
+            _ => out += &token,
+        }
+    }
+    out
+}
+
+/// Shortest form of `value` rounded to `precision` decimals.
+pub fn format_number(value: f64, precision: usize) -> String {
+    let mut text = format!("{:.*}", precision, value);
+    if text.contains('.') {
+        text = text.trim_end_matches('0').trim_end_matches('.').to_string();
+    }
+    if text == "-0" {
+        text = "0".to_string();
+    }
+    if let Some(rest) = text.strip_prefix("0.") {
+        text = format!(".{}", rest);
+    } else if let Some(rest) = text.strip_prefix("-0.") {
+        text = format!("-.{}", rest);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symbol::VersionChoice;
+    use qrcode::{Color, EcLevel};
+
+    /// Radii of every arc command in path data `d`.
+    fn arc_radii(d: &str) -> Vec<(f64, f64)> {
+        d.split('a')
+            .skip(1)
+            .map(|arc| {
+                let mut numbers = arc.split(' ').map(|n| n.parse::<f64>().unwrap());
+                (numbers.next().unwrap(), numbers.next().unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn precision_keeps_arc_radii() {
+        let symbol = QrSymbol::encode(b"https://example.com", EcLevel::M, VersionChoice::Auto, None).unwrap();
+        let style = Style { shape: ModuleShape::Circle, ..Style::default() };
+        let (d, _) = merged_path(&symbol, &style, 4);
+        let rounded = with_precision(&d, Some(0));
+
+        let radii = arc_radii(&rounded);
+        assert_eq!(radii.len(), arc_radii(&d).len());
+        assert!(!radii.is_empty());
+        assert!(radii.iter().all(|&(rx, ry)| rx > 0.0 && ry > 0.0), "zero arc radius in {}", rounded);
+        // Coordinates are still rounded
+        assert!(rounded.starts_with('M') && !rounded.split('a').next().unwrap().contains('.'));
+    }
+
+    #[test]
+    fn precision_rounds_coordinates_only() {
+        assert_eq!(with_precision("M1.26 0.5h-0.24a.5 .5 0 1 1 0.004 1.256Z", Some(1)), "M1.3 .5h-.2a.5 .5 0 1 1 0 1.3Z");
+        assert_eq!(with_precision("M1.26 0.5", None), "M1.26 0.5");
+    }
+
+    /// Twice the signed area of a polygon; positive when it runs clockwise
+    /// on screen (y pointing down).
+    fn doubled_area(corners: &[(i64, i64)]) -> i64 {
+        corners.iter().zip(corners.iter().cycle().skip(1)).map(|(&(x0, y0), &(x1, y1))| x0 * y1 - x1 * y0).sum()
+    }
+
+    /// Polygons of path data made of `M`, `h`, `v` and `Z` commands.
+    fn polygons(d: &str) -> Vec<Vec<(i64, i64)>> {
+        let mut polygons: Vec<Vec<(i64, i64)>> = Vec::new();
+        let mut rest = d;
+        while let Some(command) = rest.chars().next() {
+            let end = rest[1..].find(|c: char| c.is_ascii_alphabetic()).map_or(rest.len(), |i| i + 1);
+            let numbers: Vec<i64> = rest[1..end].split(' ').filter(|n| !n.is_empty()).map(|n| n.parse().unwrap()).collect();
+            match command {
+                'M' => polygons.push(vec![(numbers[0], numbers[1])]),
+                'h' | 'v' => {
+                    let polygon = polygons.last_mut().unwrap();
+                    let &(x, y) = polygon.last().unwrap();
+                    polygon.push(if command == 'h' { (x + numbers[0], y) } else { (x, y + numbers[0]) });
+                }
+                'Z' => {}
+                _ => panic!("unexpected command {} in {}", command, d),
+            }
+            rest = &rest[end..];
+        }
+        polygons
+    }
+
+    /// Nonzero winding number of the cell whose top-left corner is (x, y).
+    fn winding(polygons: &[Vec<(i64, i64)>], x: i64, y: i64) -> i64 {
+        let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+        let mut winding = 0;
+        for polygon in polygons {
+            for (&(x0, y0), &(x1, y1)) in polygon.iter().zip(polygon.iter().cycle().skip(1)) {
+                if x0 == x1 && (x0 as f64) > px && (y0.min(y1) as f64) < py && (y0.max(y1) as f64) > py {
+                    winding += (y1 - y0).signum();
+                }
+            }
+        }
+        winding
+    }
+
+    #[test]
+    fn trace_cuts_holes_in_rings() {
+        // A finder pattern: 7x7 ring around a 3x3 square
+        let finder = |x: usize, y: usize| x == 0 || y == 0 || x == 6 || y == 6 || (2..5).contains(&x) && (2..5).contains(&y);
+        let outlines = trace(7, finder);
+        let areas: Vec<i64> = outlines.iter().map(|corners| doubled_area(corners)).collect();
+        assert_eq!(areas.len(), 3, "{:?}", outlines);
+        assert!(areas.contains(&(2 * 49)), "outer edge clockwise: {:?}", areas);
+        assert!(areas.contains(&(-2 * 25)), "hole counterclockwise: {:?}", areas);
+        assert!(areas.contains(&(2 * 9)), "eye clockwise: {:?}", areas);
+        for y in 0..7 {
+            for x in 0..7 {
+                assert_eq!(winding(&outlines, x, y), i64::from(finder(x as usize, y as usize)), "cell {},{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn trace_separates_diagonal_neighbours() {
+        let outlines = trace(2, |x, y| x == y);
+        assert_eq!(outlines, vec![vec![(0, 0), (1, 0), (1, 1), (0, 1)], vec![(1, 1), (2, 1), (2, 2), (1, 2)]]);
+    }
+
+    #[test]
+    fn merged_path_rasterizes_to_the_modules() {
+        let symbol = QrSymbol::encode(b"https://example.com/merged", EcLevel::Q, VersionChoice::Exact(7), None).unwrap();
+        let (d, subpaths) = merged_path(&symbol, &Style::default(), 4);
+        let polygons = polygons(&d);
+        assert_eq!(polygons.len(), subpaths);
+        let width = symbol.width();
+        for y in 0..width {
+            for x in 0..width {
+                let dark = symbol[(x, y)] == Color::Dark;
+                assert_eq!(winding(&polygons, x as i64 + 4, y as i64 + 4), i64::from(dark), "module {},{}", x, y);
+            }
+        }
+    }
+}
+
@@ -0,0 +1,263 @@
+//! QR symbol construction with explicit control over version and mask pattern.
+//!
+//! `QrCode::with_error_correction_level` always picks the smallest version and
+//! the mask with the lowest penalty. `QrSymbol` exposes both choices so codes
+//! can share the same physical layout across a product line.
+
+use qrcode::bits::Bits;
+use qrcode::canvas::{Canvas, MaskPattern};
+use crate::capacity;
+use crate::segment::Payload;
+use qrcode::{Color, EcLevel, Version};
+use std::ops::Index;
+
+/// Highest regular QR code version.
+pub const MAX_VERSION: i16 = 40;
+
+/// All QR mask patterns, indexed by their 3-bit mask reference.
+const MASK_PATTERNS: [MaskPattern; 8] = [
+    MaskPattern::Checkerboard,
+    MaskPattern::HorizontalLines,
+    MaskPattern::VerticalLines,
+    MaskPattern::DiagonalLines,
+    MaskPattern::LargeCheckerboard,
+    MaskPattern::Fields,
+    MaskPattern::Diamonds,
+    MaskPattern::Meadow,
+];
+
+/// How the symbol version is selected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersionChoice {
+    /// Smallest version that can hold the data.
+    Auto,
+    /// Exactly this version, failing if the data does not fit.
+    Exact(i16),
+    /// Smallest version that can hold the data, but never below this one.
+    Minimum(i16),
+}
+
+/// An encoded QR code symbol together with the version and mask that were used.
+#[derive(Clone)]
+pub struct QrSymbol {
+    modules: Vec<Color>,
+    width: usize,
+    version: Version,
+    ec_level: EcLevel,
+    mask: u8,
+    penalty_score: u32,
+    data_bits: usize,
+}
+
+impl QrSymbol {
+    /// Encodes `data` with the given error correction level, version policy and
+    /// mask pattern (`None` selects the mask with the lowest penalty score).
+    pub fn encode(
+        data: &[u8],
+        ec_level: EcLevel,
+        version: VersionChoice,
+        mask: Option<u8>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::encode_payload(&Payload::bytes(data), ec_level, version, mask)
+    }
+
+    /// Like [`QrSymbol::encode`], for a payload carrying an ECI designator or
+    /// Shift_JIS text.
+    pub fn encode_payload(
+        payload: &Payload,
+        ec_level: EcLevel,
+        version: VersionChoice,
+        mask: Option<u8>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let (first, last) = match version {
+            VersionChoice::Auto => (1, MAX_VERSION),
+            VersionChoice::Exact(v) => (v, v),
+            VersionChoice::Minimum(v) => (v, MAX_VERSION),
+        };
+        if !(1..=MAX_VERSION).contains(&first) {
+            return Err(format!("Invalid QR version {}. Use a value between 1 and {}", first, MAX_VERSION).into());
+        }
+
+        for v in first..=last {
+            let version = Version::Normal(v);
+            if payload.encoded_bits(version) <= capacity::data_bits(version, ec_level) {
+                let mut bits = Bits::new(version);
+                payload.push_to(&mut bits)?;
+                return Self::from_bits(bits, ec_level, mask);
+            }
+        }
+
+        // Report the shortfall against the last version that was tried
+        let last_version = Version::Normal(last);
+        let needed = payload.encoded_bits(last_version);
+        let available = capacity::data_bits(last_version, ec_level);
+        let target = if first == last {
+            format!("version {}", last)
+        } else {
+            format!("versions {}-{}", first, last)
+        };
+        Err(format!(
+            "Data does not fit in {} at error correction level {:?}: needs {} bits, only {} available",
+            target, ec_level, needed, available
+        )
+        .into())
+    }
+
+    /// Builds a symbol from encoded data segments. The terminator and padding
+    /// are appended here, so `bits` must contain only the segments.
+    pub fn from_bits(mut bits: Bits, ec_level: EcLevel, mask: Option<u8>) -> Result<Self, Box<dyn std::error::Error>> {
+        let version = bits.version();
+        if version.is_micro() {
+            return Err("Micro QR codes are not supported".into());
+        }
+        if let Some(m) = mask {
+            if m > 7 {
+                return Err(format!("Invalid mask pattern {}. Use a value between 0 and 7", m).into());
+            }
+        }
+
+        let data_bits = bits.len();
+        bits.push_terminator(ec_level)?;
+        let data = bits.into_bytes();
+        let (encoded_data, ec_data) = qrcode::ec::construct_codewords(&data, version, ec_level)?;
+        let mut canvas = Canvas::new(version, ec_level);
+        canvas.draw_all_functional_patterns();
+        canvas.draw_data(&encoded_data, &ec_data);
+
+        let width = version.width() as usize;
+        let candidates: Vec<u8> = match mask {
+            Some(m) => vec![m],
+            None => (0..8).collect(),
+        };
+
+        let (mask, modules, penalty_score) = candidates
+            .into_iter()
+            .map(|m| {
+                let mut masked = canvas.clone();
+                masked.apply_mask(MASK_PATTERNS[m as usize]);
+                let modules = masked.into_colors();
+                let score = penalty_score(&modules, width);
+                (m, modules, score)
+            })
+            .min_by_key(|(_, _, score)| *score)
+            .expect("at least one mask candidate");
+
+        Ok(Self { modules, width, version, ec_level, mask, penalty_score, data_bits })
+    }
+
+    /// Gets the version of this symbol.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Gets the error correction level of this symbol.
+    pub fn error_correction_level(&self) -> EcLevel {
+        self.ec_level
+    }
+
+    /// Number of modules per side, without quiet zone.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Mask pattern reference (0-7) applied to the data modules.
+    pub fn mask(&self) -> u8 {
+        self.mask
+    }
+
+    /// ISO/IEC 18004 penalty score of the applied mask (lower is better).
+    pub fn penalty_score(&self) -> u32 {
+        self.penalty_score
+    }
+
+    /// Number of bits taken by the encoded data segments (excluding terminator
+    /// and padding).
+    pub fn data_bits(&self) -> usize {
+        self.data_bits
+    }
+
+    /// Number of data bits this version and error correction level can hold.
+    pub fn capacity_bits(&self) -> usize {
+        capacity::data_bits(self.version, self.ec_level)
+    }
+
+    /// Checks whether the module at (x, y) belongs to a function pattern.
+    pub fn is_functional(&self, x: usize, y: usize) -> bool {
+        qrcode::canvas::is_functional(self.version, self.version.width(), x as i16, y as i16)
+    }
+}
+
+impl Index<(usize, usize)> for QrSymbol {
+    type Output = Color;
+
+    fn index(&self, (x, y): (usize, usize)) -> &Color {
+        &self.modules[y * self.width + x]
+    }
+}
+
+/// Computes the mask penalty score (rules N1-N4 of ISO/IEC 18004 section 7.8.3).
+pub fn penalty_score(modules: &[Color], width: usize) -> u32 {
+    let dark = |x: usize, y: usize| modules[y * width + x] == Color::Dark;
+    let mut score = 0;
+
+    // N1: runs of five or more same-colored modules in a row or column
+    for horizontal in [true, false] {
+        for i in 0..width {
+            let mut run = 1;
+            for j in 1..width {
+                let (a, b) = if horizontal { (dark(j - 1, i), dark(j, i)) } else { (dark(i, j - 1), dark(i, j)) };
+                if a == b {
+                    run += 1;
+                } else {
+                    if run >= 5 {
+                        score += 3 + (run - 5);
+                    }
+                    run = 1;
+                }
+            }
+            if run >= 5 {
+                score += 3 + (run - 5);
+            }
+        }
+    }
+
+    // N2: 2x2 blocks of the same color
+    for y in 0..width - 1 {
+        for x in 0..width - 1 {
+            let c = dark(x, y);
+            if c == dark(x + 1, y) && c == dark(x, y + 1) && c == dark(x + 1, y + 1) {
+                score += 3;
+            }
+        }
+    }
+
+    // N3: 1:1:3:1:1 finder-like patterns with four light modules on either side.
+    // Modules outside the symbol count as light (quiet zone).
+    const FINDER_LIKE: [bool; 7] = [true, false, true, true, true, false, true];
+    let at = |x: isize, y: isize| {
+        x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < width && dark(x as usize, y as usize)
+    };
+    for horizontal in [true, false] {
+        for i in 0..width as isize {
+            for j in -4..width as isize {
+                let get = |k: isize| if horizontal { at(j + k, i) } else { at(i, j + k) };
+                if (0..7).any(|k| get(k) != FINDER_LIKE[k as usize]) {
+                    continue;
+                }
+                let light_before = (-4..0).all(|k| !get(k));
+                let light_after = (7..11).all(|k| !get(k));
+                if light_before || light_after {
+                    score += 40;
+                }
+            }
+        }
+    }
+
+    // N4: proportion of dark modules, 10 points per 5% deviation from 50%
+    let total = (width * width) as u64;
+    let dark_count = modules.iter().filter(|c| **c == Color::Dark).count() as u64;
+    let deviation = (dark_count * 20).abs_diff(total * 10) / total;
+    score += 10 * deviation as u32;
+
+    score
+}
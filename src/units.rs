@@ -0,0 +1,57 @@
+//! Physical length parsing for print-oriented options.
+
+/// Millimetres per inch.
+pub const MM_PER_INCH: f64 = 25.4;
+
+/// Print resolution assumed when a physical size is given without a DPI.
+pub const DEFAULT_DPI: u16 = 300;
+
+/// Smallest module that common printers and scanners resolve reliably.
+pub const DEFAULT_MIN_MODULE_MM: f64 = 0.33;
+
+/// Pixels covering `mm` at `dpi`, rounded to the nearest pixel.
+pub fn mm_to_px(mm: f64, dpi: u16) -> u32 {
+    (mm / MM_PER_INCH * f64::from(dpi)).round() as u32
+}
+
+/// Printed length of `px` pixels at `dpi`.
+pub fn px_to_mm(px: f64, dpi: u16) -> f64 {
+    px / f64::from(dpi) * MM_PER_INCH
+}
+
+/// Parses a length such as `25mm`, `2.5cm`, `1.5m`, `1in` or `12pt` into millimetres.
+/// A bare number is taken as millimetres.
+pub fn parse_length_mm(input: &str) -> Result<f64, Box<dyn std::error::Error>> {
+    let trimmed = input.trim().to_lowercase();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid length: {}. Use a number with mm, cm, m, in or pt", input))?;
+
+    let mm = match unit.trim() {
+        "" | "mm" => value,
+        "cm" => value * 10.0,
+        "m" => value * 1000.0,
+        "in" | "\"" => value * MM_PER_INCH,
+        "pt" => value * MM_PER_INCH / 72.0,
+        other => return Err(format!("Unknown length unit: {}. Use mm, cm, m, in or pt", other).into()),
+    };
+    if mm <= 0.0 {
+        return Err(format!("Length must be positive: {}", input).into());
+    }
+    Ok(mm)
+}
+
+/// Formats a length in the most readable of mm, cm and m.
+pub fn format_length(mm: f64) -> String {
+    if mm < 10.0 {
+        format!("{:.1} mm", mm)
+    } else if mm < 1000.0 {
+        format!("{:.1} cm", mm / 10.0)
+    } else {
+        format!("{:.2} m", mm / 1000.0)
+    }
+}